@@ -2,6 +2,10 @@
 //! real-world web app, you should store it in a database instead. In that case
 //! you can disable `token_cached` in the `Config` struct passed to the client
 //! when initializing it to avoid using cache files.
+//!
+//! Services with many users may use `SpotifyAccounts` instead of creating a
+//! client for each session by hand, which keeps the clients of all the users
+//! and saves their tokens in a `TokenStore`.
 
 use cookie::time::Duration;
 use getrandom::getrandom;
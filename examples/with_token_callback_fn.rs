@@ -6,8 +6,9 @@
 use std::sync::Arc;
 
 use rspotify::{
-    clients::OAuthClient, scopes, AuthCodePkceSpotify, AuthCodeSpotify, CallbackError,
-    ClientCredsSpotify, Config, Credentials, OAuth, TokenCallback,
+    clients::OAuthClient, scopes, AsyncTokenCallback, AuthCodePkceSpotify, AuthCodeSpotify,
+    CallbackError, ClientCredsSpotify, Config, Credentials, OAuth, Token, TokenCallback,
+    TokenFailureHooks,
 };
 
 async fn _with_pkce(creds: Credentials, oauth: OAuth) {
@@ -45,9 +46,15 @@ async fn with_auth(creds: Credentials, oauth: OAuth) {
     };
     let token_callback = TokenCallback(Box::new(operate_token_fn));
 
+    // Alerting when the user has to log in again
+    let token_failure_hooks = TokenFailureHooks::default()
+        .on_refresh_failure(|err| eprintln!(">>> Failed to refresh the token: {}", err))
+        .on_missing_refresh_token(|| eprintln!(">>> There's no refresh token, log in again"));
+
     // Enabling automatic token refreshing in the config
     let config = Config {
         token_callback_fn: Arc::new(Some(token_callback)),
+        token_failure_hooks,
         ..Default::default()
     };
 
@@ -62,17 +69,19 @@ async fn with_auth(creds: Credentials, oauth: OAuth) {
 }
 
 async fn with_client_credentials(creds: Credentials) {
-    let operate_token_fn = |token| {
-        println!(">>> From token callback function with ClientCredsSpotify");
+    // The callback may also be async, e.g., to save the token into a
+    // database without blocking
+    let operate_token_fn = |token: Token| async move {
+        println!(">>> From async token callback function with ClientCredsSpotify");
         println!(">>> Let's manipulate it. Oooh, we could only read it");
         println!(">>> token: {:?}", token);
         Ok(())
     };
-    let token_callback = TokenCallback(Box::new(operate_token_fn));
+    let token_callback = AsyncTokenCallback::new(operate_token_fn);
 
     // Enabling automatic token refreshing in the config
     let config = Config {
-        token_callback_fn: Arc::new(Some(token_callback)),
+        token_callback_async_fn: Arc::new(Some(token_callback)),
         ..Default::default()
     };
     // Same with client-credential based spotify client
@@ -0,0 +1,788 @@
+//! An HTTP client that records the requests made through another client into
+//! a file, called cassette, and then replays them without accessing the
+//! network. This makes it possible to run tests that would otherwise need
+//! real credentials in a deterministic way.
+//!
+//! Requests are matched by their method, URL, query and body; their headers
+//! are never taken into account nor saved, so that the cassettes don't
+//! contain any credentials. Only the response headers in [`RECORDED_HEADERS`]
+//! are saved. For the same reason, the values of the fields in
+//! [`REDACTED_FIELDS`] are replaced in both the requests and the responses.
+//!
+//! Unsuccessful responses are recorded as well, so that error handling can
+//! also be tested. Other errors, like connection failures, aren't.
+//!
+//! Sample usage:
+//!
+//! ```no_run
+//! use rspotify_http::{cassette::CassetteClient, HttpClient};
+//!
+//! // Once, with access to the network
+//! let client = CassetteClient::record("tests/cassettes/album.json", HttpClient::default());
+//!
+//! // Then, without it
+//! let client = CassetteClient::<HttpClient>::replay("tests/cassettes/album.json").unwrap();
+//! ```
+//!
+//! The client can then be used by any of the Spotify clients with their
+//! `with_http_client` method.
+
+use super::{
+    middleware::Method, BaseHttpClient, ErrorResponse, Form, Headers, HttpClient, HttpError,
+    HttpResponse, Query,
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use maybe_async::maybe_async;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The fields whose values are never written to a cassette, because they
+/// contain credentials.
+pub const REDACTED_FIELDS: &[&str] = &[
+    "access_token",
+    "refresh_token",
+    "client_secret",
+    "code",
+    "code_verifier",
+];
+
+/// The value that replaces the fields in [`REDACTED_FIELDS`].
+pub const REDACTED: &str = "<redacted>";
+
+/// The response headers that are saved, e.g., so that the `ETag` of a response
+/// can be replayed. The rest of them are discarded.
+pub const RECORDED_HEADERS: &[&str] = &["date", "etag", "retry-after"];
+
+/// Possible errors returned by [`CassetteClient`], which are wrapped in
+/// [`HttpError::Client`].
+#[derive(thiserror::Error, Debug)]
+pub enum CassetteError {
+    /// There's no recorded interaction for the request when replaying.
+    #[error("no recorded interaction for {0:?} {1}")]
+    Unmatched(Method, String),
+
+    /// The cassette couldn't be read or written.
+    #[error("cassette io: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The cassette is malformed.
+    #[error("cassette json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<CassetteError> for HttpError {
+    fn from(err: CassetteError) -> Self {
+        HttpError::client(err)
+    }
+}
+
+/// A request and the response that was obtained for it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: Method,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub query: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub form: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub json: Value,
+    #[serde(default = "default_status", skip_serializing_if = "is_default_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    pub response: String,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+fn is_default_status(status: &u16) -> bool {
+    *status == default_status()
+}
+
+fn is_success(status: &u16) -> bool {
+    (200..300).contains(status)
+}
+
+impl Interaction {
+    fn new(method: Method, url: &str) -> Self {
+        Self {
+            method,
+            url: url.to_owned(),
+            query: BTreeMap::new(),
+            form: BTreeMap::new(),
+            json: Value::Null,
+            status: default_status(),
+            headers: BTreeMap::new(),
+            response: String::new(),
+        }
+    }
+
+    fn with_query(mut self, query: &HashMap<&str, &str>) -> Self {
+        self.query = redact_map(query);
+        self
+    }
+
+    fn with_form(mut self, form: &HashMap<&str, &str>) -> Self {
+        self.form = redact_map(form);
+        self
+    }
+
+    fn with_json(mut self, json: &Value) -> Self {
+        self.json = redact_json(json.clone());
+        self
+    }
+
+    /// Whether both interactions were made for the same request.
+    fn matches(&self, other: &Self) -> bool {
+        self.method == other.method
+            && self.url == other.url
+            && self.query == other.query
+            && self.form == other.form
+            && self.json == other.json
+    }
+}
+
+fn redact_map(map: &HashMap<&str, &str>) -> BTreeMap<String, String> {
+    map.iter()
+        .map(|(key, val)| {
+            let val = if REDACTED_FIELDS.contains(key) {
+                REDACTED
+            } else {
+                val
+            };
+            ((*key).to_owned(), val.to_owned())
+        })
+        .collect()
+}
+
+fn redact_json(mut json: Value) -> Value {
+    if let Value::Object(map) = &mut json {
+        for (key, val) in map.iter_mut() {
+            if REDACTED_FIELDS.contains(&key.as_str()) && val.is_string() {
+                *val = Value::from(REDACTED);
+            }
+        }
+    }
+    json
+}
+
+/// Redacts the response body if it's a JSON object, leaving it as is
+/// otherwise.
+fn redact_response(response: String) -> String {
+    match serde_json::from_str::<Value>(&response) {
+        Ok(json @ Value::Object(_)) => redact_json(json).to_string(),
+        _ => response,
+    }
+}
+
+#[derive(Debug, Default)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+    /// Whether each interaction has already been replayed
+    used: Vec<bool>,
+}
+
+/// Whether the [`CassetteClient`] records or replays its requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Record,
+    Replay,
+}
+
+/// An HTTP client that records or replays the requests of the client `C`.
+///
+/// When replaying, the interactions are consumed in the same order they were
+/// recorded, so that the same request may return different responses, e.g.,
+/// before and after modifying a playlist. Once all the matching interactions
+/// have been used, the last one is repeated.
+///
+#[derive(Clone, Debug)]
+pub struct CassetteClient<C: BaseHttpClient = HttpClient> {
+    client: C,
+    mode: Mode,
+    path: Option<PathBuf>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl<C: BaseHttpClient> Default for CassetteClient<C> {
+    /// An empty cassette in replay mode, which fails every request.
+    fn default() -> Self {
+        Self {
+            client: C::default(),
+            mode: Mode::Replay,
+            path: None,
+            cassette: Arc::default(),
+        }
+    }
+}
+
+impl<C: BaseHttpClient> CassetteClient<C> {
+    /// Records the requests made through `client` into the cassette at
+    /// `path`, which is overwritten. The file is saved after each request.
+    pub fn record(path: impl Into<PathBuf>, client: C) -> Self {
+        Self {
+            client,
+            mode: Mode::Record,
+            path: Some(path.into()),
+            cassette: Arc::default(),
+        }
+    }
+
+    /// Replays the requests saved in the cassette at `path`.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, CassetteError> {
+        let path = path.into();
+        let interactions: Vec<Interaction> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let cassette = Cassette {
+            used: vec![false; interactions.len()],
+            interactions,
+        };
+
+        Ok(Self {
+            client: C::default(),
+            mode: Mode::Replay,
+            path: Some(path),
+            cassette: Arc::new(Mutex::new(cassette)),
+        })
+    }
+
+    /// Records the requests if the cassette at `path` doesn't exist yet, and
+    /// replays them otherwise.
+    pub fn new(path: impl Into<PathBuf>, client: C) -> Result<Self, CassetteError> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path, client))
+        }
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The interactions recorded or loaded so far.
+    #[must_use]
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.cassette.lock().unwrap().interactions.clone()
+    }
+
+    fn find(&self, request: &Interaction) -> Result<HttpResponse, HttpError> {
+        let mut cassette = self.cassette.lock().unwrap();
+        let Cassette { interactions, used } = &mut *cassette;
+
+        let mut matching = interactions
+            .iter()
+            .zip(used.iter_mut())
+            .filter(|(interaction, _)| interaction.matches(request))
+            .peekable();
+        let mut last = None;
+        while let Some((interaction, used)) = matching.next() {
+            if !*used || matching.peek().is_none() {
+                *used = true;
+                last = Some(interaction.clone());
+                break;
+            }
+        }
+
+        let interaction =
+            last.ok_or_else(|| CassetteError::Unmatched(request.method, request.url.clone()))?;
+        let headers = interaction.headers.into_iter().collect();
+        if is_success(&interaction.status) {
+            Ok(HttpResponse {
+                status: interaction.status,
+                headers,
+                body: interaction.response,
+            })
+        } else {
+            Err(HttpError::StatusCode(ErrorResponse::new(
+                interaction.status,
+                headers,
+                interaction.response,
+            )))
+        }
+    }
+
+    /// Saves the response obtained for the interaction, unless the request
+    /// couldn't be completed.
+    fn save(
+        &self,
+        mut interaction: Interaction,
+        response: Result<HttpResponse, HttpError>,
+    ) -> Result<HttpResponse, HttpError> {
+        match &response {
+            Ok(ok) => {
+                interaction.status = ok.status;
+                interaction.headers = recorded_headers(|name| ok.header(name));
+                interaction.response = redact_response(ok.body.clone());
+            }
+            Err(HttpError::StatusCode(err)) => {
+                interaction.status = err.status;
+                interaction.headers = recorded_headers(|name| err.header(name));
+                interaction.response = redact_response(err.body.clone());
+            }
+            Err(HttpError::Client(_)) => return response,
+        }
+
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(interaction);
+        cassette.used.push(true);
+        if let Some(path) = &self.path {
+            write_cassette(path, &cassette.interactions)?;
+        }
+
+        response
+    }
+}
+
+fn recorded_headers<'a>(header: impl Fn(&str) -> Option<&'a str>) -> BTreeMap<String, String> {
+    RECORDED_HEADERS
+        .iter()
+        .filter_map(|name| Some(((*name).to_owned(), header(name)?.to_owned())))
+        .collect()
+}
+
+fn write_cassette(path: &Path, interactions: &[Interaction]) -> Result<(), CassetteError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(interactions)?)?;
+    Ok(())
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl<C> BaseHttpClient for CassetteClient<C>
+where
+    C: BaseHttpClient<Error = HttpError> + Sync,
+{
+    type Error = HttpError;
+
+    async fn get(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<String, Self::Error> {
+        self.get_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<HttpResponse, Self::Error> {
+        let interaction = Interaction::new(Method::Get, url).with_query(payload);
+        match self.mode {
+            Mode::Replay => self.find(&interaction),
+            Mode::Record => {
+                let response = self.client.get_with_headers(url, headers, payload).await;
+                self.save(interaction, response)
+            }
+        }
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<String, Self::Error> {
+        self.post_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    async fn post_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+        let interaction = Interaction::new(Method::Post, url).with_json(payload);
+        match self.mode {
+            Mode::Replay => self.find(&interaction),
+            Mode::Record => {
+                let response = self.client.post_with_headers(url, headers, payload).await;
+                self.save(interaction, response)
+            }
+        }
+    }
+
+    async fn post_form(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<String, Self::Error> {
+        self.post_form_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    async fn post_form_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<HttpResponse, Self::Error> {
+        let interaction = Interaction::new(Method::Post, url).with_form(payload);
+        match self.mode {
+            Mode::Replay => self.find(&interaction),
+            Mode::Record => {
+                let response = self
+                    .client
+                    .post_form_with_headers(url, headers, payload)
+                    .await;
+                self.save(interaction, response)
+            }
+        }
+    }
+
+    async fn put(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<String, Self::Error> {
+        self.put_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    async fn put_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+        let interaction = Interaction::new(Method::Put, url).with_json(payload);
+        match self.mode {
+            Mode::Replay => self.find(&interaction),
+            Mode::Record => {
+                let response = self.client.put_with_headers(url, headers, payload).await;
+                self.save(interaction, response)
+            }
+        }
+    }
+
+    async fn delete(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<String, Self::Error> {
+        self.delete_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    async fn delete_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+        let interaction = Interaction::new(Method::Delete, url).with_json(payload);
+        match self.mode {
+            Mode::Replay => self.find(&interaction),
+            Mode::Record => {
+                let response = self.client.delete_with_headers(url, headers, payload).await;
+                self.save(interaction, response)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Client that responds with a different token on each request, or with
+    /// an error for `/limited`. GET and form responses also have some headers.
+    #[derive(Clone, Debug, Default)]
+    struct TokenClient(Arc<AtomicUsize>);
+
+    impl TokenClient {
+        fn respond(&self, url: &str) -> Result<String, HttpError> {
+            if url == "/limited" {
+                let mut headers = Headers::new();
+                headers.insert("retry-after".to_owned(), "3".to_owned());
+                headers.insert("set-cookie".to_owned(), "secret".to_owned());
+                let body = json!({"error": {"status": 429, "message": "API rate limit exceeded"}});
+                return Err(HttpError::StatusCode(ErrorResponse::new(
+                    429,
+                    headers,
+                    body.to_string(),
+                )));
+            }
+
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"access_token": "secret", "n": n}).to_string())
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+    impl BaseHttpClient for TokenClient {
+        type Error = HttpError;
+
+        async fn get(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Query,
+        ) -> Result<String, HttpError> {
+            self.get_with_headers(url, headers, payload)
+                .await
+                .map(|response| response.body)
+        }
+
+        async fn get_with_headers(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Query,
+        ) -> Result<HttpResponse, HttpError> {
+            let body = self.respond(url)?;
+            let mut headers = Headers::new();
+            headers.insert("etag".to_owned(), "\"tag\"".to_owned());
+            headers.insert("set-cookie".to_owned(), "secret".to_owned());
+            Ok(HttpResponse {
+                status: 200,
+                headers,
+                body,
+            })
+        }
+
+        async fn post(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<String, HttpError> {
+            self.respond(url)
+        }
+
+        async fn post_with_headers(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<HttpResponse, HttpError> {
+            let body = self.respond(url)?;
+            Ok(HttpResponse {
+                status: 201,
+                headers: Headers::new(),
+                body,
+            })
+        }
+
+        async fn post_form(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Form<'_>,
+        ) -> Result<String, HttpError> {
+            self.post_form_with_headers(url, headers, payload)
+                .await
+                .map(|response| response.body)
+        }
+
+        async fn post_form_with_headers(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Form<'_>,
+        ) -> Result<HttpResponse, HttpError> {
+            let body = self.respond(url)?;
+            let mut headers = Headers::new();
+            headers.insert(
+                "date".to_owned(),
+                "Wed, 21 Oct 2015 07:28:00 GMT".to_owned(),
+            );
+            Ok(HttpResponse {
+                status: 200,
+                headers,
+                body,
+            })
+        }
+
+        async fn put(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<String, HttpError> {
+            self.respond(url)
+        }
+
+        async fn put_with_headers(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<HttpResponse, HttpError> {
+            self.respond(url).map(HttpResponse::from)
+        }
+
+        async fn delete(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<String, HttpError> {
+            self.respond(url)
+        }
+
+        async fn delete_with_headers(
+            &self,
+            url: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<HttpResponse, HttpError> {
+            self.respond(url).map(HttpResponse::from)
+        }
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "rspotify-cassette-{}-{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_record_replay() {
+        let path = cassette_path("record-replay");
+        let mut query = Query::new();
+        query.insert("market", "ES");
+        let mut form = Form::new();
+        form.insert("refresh_token", "secret");
+
+        let client = CassetteClient::record(&path, TokenClient::default());
+        client.get("/albums", None, &query).await.unwrap();
+        client.get("/albums", None, &query).await.unwrap();
+        client.post_form("/token", None, &form).await.unwrap();
+        client.post("/playlists", None, &json!({})).await.unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("secret"));
+
+        let client = CassetteClient::<TokenClient>::replay(&path).unwrap();
+        let first = client.get("/albums", None, &query).await.unwrap();
+        let second = client.get("/albums", None, &query).await.unwrap();
+        let third = client.get("/albums", None, &query).await.unwrap();
+        assert_eq!(first, json!({"access_token": REDACTED, "n": 0}).to_string());
+        assert_eq!(
+            second,
+            json!({"access_token": REDACTED, "n": 1}).to_string()
+        );
+        assert_eq!(second, third);
+
+        let response = client
+            .get_with_headers("/albums", None, &query)
+            .await
+            .unwrap();
+        assert_eq!(response.header("etag"), Some("\"tag\""));
+        assert_eq!(response.header("set-cookie"), None);
+
+        form.insert("refresh_token", "another secret");
+        let token = client
+            .post_form_with_headers("/token", None, &form)
+            .await
+            .unwrap();
+        assert_eq!(
+            token.body,
+            json!({"access_token": REDACTED, "n": 2}).to_string()
+        );
+        assert_eq!(token.header("date"), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+
+        // Successful status codes other than `200 OK` are replayed as well
+        let response = client
+            .post_with_headers("/playlists", None, &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 201);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_unmatched() {
+        let path = cassette_path("unmatched");
+        let client = CassetteClient::record(&path, TokenClient::default());
+        client
+            .put("/me/tracks", None, &json!({"ids": ["a"]}))
+            .await
+            .unwrap();
+
+        let client = CassetteClient::<TokenClient>::replay(&path).unwrap();
+        let response = client.put("/me/tracks", None, &json!({"ids": ["a"]})).await;
+        assert!(response.is_ok());
+        let err = client
+            .put("/me/tracks", None, &json!({"ids": ["b"]}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no recorded interaction"));
+        let response = client.get("/me/tracks", None, &Query::new()).await;
+        assert!(response.is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_errors() {
+        let path = cassette_path("errors");
+        let client = CassetteClient::record(&path, TokenClient::default());
+        let err = client
+            .get("/limited", None, &Query::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), Some(429));
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("secret"));
+
+        let client = CassetteClient::<TokenClient>::replay(&path).unwrap();
+        let err = client
+            .get("/limited", None, &Query::new())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), Some(429));
+        assert_eq!(err.retry_after(), Some(std::time::Duration::from_secs(3)));
+        assert!(err.api_error().is_some());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,13 +1,49 @@
 use std::collections::HashMap;
+use std::error::Error;
 use std::fmt;
+use std::time::Duration;
 
 use maybe_async::maybe_async;
+use rspotify_model::ApiError;
+use serde::Deserialize;
 use serde_json::Value;
 
 pub type Headers = HashMap<String, String>;
 pub type Query<'a> = HashMap<&'a str, &'a str>;
 pub type Form<'a> = HashMap<&'a str, &'a str>;
 
+/// A successful response, along with its status code and headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// The status code, which is always in the 2xx range.
+    pub status: u16,
+    /// The response headers, with their names in lowercase. Multiple values
+    /// for the same header are joined with commas.
+    pub headers: Headers,
+    pub body: String,
+}
+
+impl From<String> for HttpResponse {
+    /// A `200 OK` response without any headers.
+    fn from(body: String) -> Self {
+        Self {
+            status: 200,
+            headers: Headers::new(),
+            body,
+        }
+    }
+}
+
+impl HttpResponse {
+    /// The value of a header, given its name in any case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
 /// This trait represents the interface to be implemented for an HTTP client,
 /// which is kept separate from the Spotify client for cleaner code. Thus, it
 /// also requires other basic traits that are needed for the Spotify client.
@@ -31,6 +67,18 @@ pub trait BaseHttpClient: Send + Default + Clone + fmt::Debug {
         payload: &Query,
     ) -> Result<String, Self::Error>;
 
+    /// Same as [`Self::get`], but the response headers are returned as well,
+    /// e.g., to read its `ETag`.
+    ///
+    /// Note that, just like any other non-2xx response, `304 Not Modified` is
+    /// considered an error.
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<HttpResponse, Self::Error>;
+
     async fn post(
         &self,
         url: &str,
@@ -38,6 +86,15 @@ pub trait BaseHttpClient: Send + Default + Clone + fmt::Debug {
         payload: &Value,
     ) -> Result<String, Self::Error>;
 
+    /// Same as [`Self::post`], but the response status code and headers are
+    /// returned as well.
+    async fn post_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error>;
+
     async fn post_form(
         &self,
         url: &str,
@@ -45,6 +102,15 @@ pub trait BaseHttpClient: Send + Default + Clone + fmt::Debug {
         payload: &Form<'_>,
     ) -> Result<String, Self::Error>;
 
+    /// Same as [`Self::post_form`], but the response headers are returned as
+    /// well, e.g., to read its `Date`.
+    async fn post_form_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<HttpResponse, Self::Error>;
+
     async fn put(
         &self,
         url: &str,
@@ -52,10 +118,219 @@ pub trait BaseHttpClient: Send + Default + Clone + fmt::Debug {
         payload: &Value,
     ) -> Result<String, Self::Error>;
 
+    /// Same as [`Self::put`], but the response status code and headers are
+    /// returned as well.
+    async fn put_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error>;
+
     async fn delete(
         &self,
         url: &str,
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error>;
+
+    /// Same as [`Self::delete`], but the response status code and headers are
+    /// returned as well.
+    async fn delete_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error>;
+}
+
+/// Custom enum that contains all the possible errors that may occur when
+/// performing a request, regardless of the HTTP client in use.
+///
+/// Sample usage:
+///
+/// ```
+/// # #[maybe_async::maybe_async]
+/// # async fn run() {
+/// use rspotify_http::{HttpError, HttpClient, BaseHttpClient};
+///
+/// let client = HttpClient::default();
+/// let response = client.get("wrongurl", None, &Default::default()).await;
+/// match response {
+///     Ok(data) => println!("request succeeded: {:?}", data),
+///     Err(HttpError::Client(e)) => eprintln!("request failed: {}", e),
+///     Err(HttpError::StatusCode(response)) => match response.api_error {
+///         Some(api_error) => eprintln!("status code {}: {}", response.status, api_error),
+///         None => eprintln!("status code {}: {}", response.status, response.body),
+///     },
+/// }
+/// # }
+/// ```
+#[derive(thiserror::Error, Debug)]
+pub enum HttpError {
+    /// The request couldn't be completed because there was an error when
+    /// trying to do so, or when reading its response. The original error of
+    /// the HTTP client can be obtained with [`Error::downcast_ref`].
+    #[error("request: {0}")]
+    Client(Box<dyn Error + Send + Sync>),
+
+    /// The request was made, but the server returned an unsuccessful status
+    /// code, such as 404 or 503.
+    #[error("status code {}", .0.status)]
+    StatusCode(ErrorResponse),
+}
+
+/// An unsuccessful response from the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorResponse {
+    pub status: u16,
+    /// The response headers, with their names in lowercase. Multiple values
+    /// for the same header are joined with commas.
+    pub headers: Headers,
+    pub body: String,
+    /// In some cases, the response contains a custom message from Spotify
+    /// with more information.
+    pub api_error: Option<ApiError>,
+}
+
+impl ErrorResponse {
+    /// Builds the response, parsing the error message sent by Spotify in the
+    /// body, if any.
+    #[must_use]
+    pub fn new(status: u16, headers: Headers, body: String) -> Self {
+        let api_error = parse_api_error(&body);
+        Self {
+            status,
+            headers,
+            body,
+            api_error,
+        }
+    }
+
+    /// The value of a header, given its name in any case.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+impl HttpError {
+    /// Wraps an error of the HTTP client.
+    pub fn client(err: impl Error + Send + Sync + 'static) -> Self {
+        Self::Client(Box::new(err))
+    }
+
+    /// The status code of the response, in case the request was completed.
+    #[must_use]
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::StatusCode(response) => Some(response.status),
+            Self::Client(_) => None,
+        }
+    }
+
+    /// The amount of time to wait before retrying the request, as indicated
+    /// by the `Retry-After` header of the response, if any.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::StatusCode(response) => {
+                response.header("retry-after").and_then(parse_retry_after)
+            }
+            Self::Client(_) => None,
+        }
+    }
+
+    /// The error message sent by Spotify, if any.
+    #[must_use]
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::StatusCode(response) => response.api_error.as_ref(),
+            Self::Client(_) => None,
+        }
+    }
+}
+
+/// Parses the body of an unsuccessful response, which looks like
+/// `{"error": {"status": 404, "message": "...", "reason": "..."}}`, where the
+/// `reason` is only present for player errors.
+fn parse_api_error(body: &str) -> Option<ApiError> {
+    #[derive(Deserialize)]
+    struct ErrorObject {
+        status: u16,
+        message: String,
+        reason: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct Wrapper {
+        error: ErrorObject,
+    }
+
+    let ErrorObject {
+        status,
+        message,
+        reason,
+    } = serde_json::from_str::<Wrapper>(body).ok()?.error;
+    Some(match reason {
+        Some(reason) => ApiError::Player {
+            status,
+            message,
+            reason,
+        },
+        None => ApiError::Regular { status, message },
+    })
+}
+
+/// Parses the value of a `Retry-After` header. Spotify always sends it as a
+/// number of seconds, so the HTTP-date format isn't supported.
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("4"), Some(Duration::from_secs(4)));
+        assert_eq!(parse_retry_after(" 30 "), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn test_error_response() {
+        let mut headers = Headers::new();
+        headers.insert("retry-after".to_owned(), "5".to_owned());
+        let body = r#"{"error": {"status": 429, "message": "API rate limit exceeded"}}"#;
+        let err = HttpError::StatusCode(ErrorResponse::new(429, headers, body.to_owned()));
+        assert_eq!(err.status_code(), Some(429));
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(5)));
+        assert_eq!(
+            err.api_error(),
+            Some(&ApiError::Regular {
+                status: 429,
+                message: "API rate limit exceeded".to_owned()
+            })
+        );
+
+        let body = r#"{"error": {"status": 403, "message": "Player command failed", "reason": "PREMIUM_REQUIRED"}}"#;
+        let response = ErrorResponse::new(403, Headers::new(), body.to_owned());
+        assert_eq!(
+            response.api_error,
+            Some(ApiError::Player {
+                status: 403,
+                message: "Player command failed".to_owned(),
+                reason: "PREMIUM_REQUIRED".to_owned()
+            })
+        );
+        assert_eq!(response.header("Retry-After"), None);
+
+        let response = ErrorResponse::new(502, Headers::new(), "Bad Gateway".to_owned());
+        assert_eq!(response.api_error, None);
+    }
 }
@@ -3,7 +3,15 @@
 
 // Disable all modules when both client features are enabled or when none are.
 // This way only the compile error below gets shown instead of a whole list of
-// confusing errors..
+// confusing errors.
+//
+// Both clients can't be enabled at the same time because `maybe_async` can
+// only work in one mode at a time: with both features, its additive `is_sync`
+// would make everything blocking, and reqwest's client couldn't implement
+// `BaseHttpClient` anymore. Supporting both would require compiling the whole
+// crate, as well as the Spotify clients, once per mode, so it's rejected
+// instead. Other implementations of `BaseHttpClient` can be used along with
+// the enabled client, as long as they work in the same mode.
 
 #[cfg(feature = "client-reqwest")]
 #[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
@@ -19,20 +27,52 @@ mod common;
 
 #[cfg(feature = "client-reqwest")]
 #[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
-pub use self::reqwest::{ReqwestClient as HttpClient, ReqwestError as HttpError};
+pub use self::reqwest::ReqwestClient;
+
+#[cfg(feature = "client-reqwest")]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::reqwest::ReqwestClientBuilder;
 
 #[cfg(feature = "client-ureq")]
 #[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
-pub use self::ureq::{UreqClient as HttpClient, UreqError as HttpError};
+pub use self::ureq::{UreqClient, UreqClientBuilder};
+
+#[cfg(feature = "client-reqwest")]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+pub use self::reqwest::ReqwestClient as HttpClient;
+
+#[cfg(feature = "client-reqwest")]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::reqwest::ReqwestClientBuilder as HttpClientBuilder;
+
+#[cfg(feature = "client-ureq")]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+pub use self::ureq::UreqClient as HttpClient;
+
+#[cfg(feature = "client-ureq")]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+pub use self::ureq::UreqClientBuilder as HttpClientBuilder;
+
+#[cfg(any(feature = "client-reqwest", feature = "client-ureq"))]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+pub use common::{BaseHttpClient, ErrorResponse, Form, Headers, HttpError, HttpResponse, Query};
+
+#[cfg(any(feature = "client-reqwest", feature = "client-ureq"))]
+#[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
+pub mod middleware;
 
 #[cfg(any(feature = "client-reqwest", feature = "client-ureq"))]
 #[cfg(not(all(feature = "client-reqwest", feature = "client-ureq")))]
-pub use common::{BaseHttpClient, Form, Headers, Query};
+pub mod cassette;
 
 #[cfg(all(feature = "client-reqwest", feature = "client-ureq"))]
 compile_error!(
     "`client-reqwest` and `client-ureq` features cannot both be enabled at \
-    the same time, if you want to use `client-ureq` you need to set \
+    the same time, not even by different crates of the same dependency \
+    graph, because RSpotify can only be either asynchronous or synchronous. \
+    If you want to use `client-ureq` you need to set \
     `default-features = false`"
 );
 
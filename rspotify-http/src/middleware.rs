@@ -0,0 +1,725 @@
+//! A middleware layer that can be wrapped around any [`BaseHttpClient`], so
+//! that the requests going through it can be inspected or modified without
+//! having to implement a new HTTP client from scratch.
+//!
+//! Sample usage:
+//!
+//! ```
+//! use rspotify_http::{
+//!     middleware::{Middleware, MiddlewareClient, Request},
+//!     HttpClient, HttpResponse,
+//! };
+//!
+//! /// Adds a custom header to every request
+//! struct TracingHeader;
+//!
+//! impl<E> Middleware<E> for TracingHeader {
+//!     fn on_request(&self, request: &mut Request) -> Option<Result<HttpResponse, E>> {
+//!         request
+//!             .headers
+//!             .insert("x-request-source".to_owned(), "my-app".to_owned());
+//!         None
+//!     }
+//! }
+//!
+//! let client = MiddlewareClient::new(HttpClient::default()).with(TracingHeader);
+//! ```
+
+use super::{BaseHttpClient, Form, Headers, HttpError, HttpResponse, Query};
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use maybe_async::maybe_async;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The HTTP method of a [`Request`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+/// The payload of a [`Request`]. Note that it has to be consistent with its
+/// method: GET requests take a [`Body::Query`], POST requests may take either
+/// a [`Body::Json`] or a [`Body::Form`], and the rest of them take a
+/// [`Body::Json`]. Otherwise, the request fails with [`HttpError::Client`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Body {
+    /// The query parameters of a GET request.
+    Query(HashMap<String, String>),
+    /// A JSON body.
+    Json(Value),
+    /// A URL-encoded form body.
+    Form(HashMap<String, String>),
+}
+
+/// An owned version of the parameters passed to [`BaseHttpClient`], so that
+/// they can be modified by a [`Middleware`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    pub method: Method,
+    pub url: String,
+    pub headers: Headers,
+    pub body: Body,
+}
+
+/// A hook that runs for every request sent through a [`MiddlewareClient`].
+///
+/// `E` is the error type of the wrapped HTTP client, which is needed to
+/// short-circuit or rewrite responses. Both methods do nothing by default.
+pub trait Middleware<E>: Send + Sync {
+    /// Runs before the request is sent, and it may modify it. Returning a
+    /// response here will short-circuit the chain, i.e., neither the
+    /// remaining middleware nor the HTTP client will see the request.
+    fn on_request(&self, _request: &mut Request) -> Option<Result<HttpResponse, E>> {
+        None
+    }
+
+    /// Runs once the response has been obtained, and it may modify its
+    /// status code, headers or body.
+    fn on_response(&self, _request: &Request, _response: &mut Result<HttpResponse, E>) {}
+}
+
+/// An HTTP client that runs an ordered list of [`Middleware`] around the
+/// requests of the wrapped client.
+///
+/// The [`Middleware::on_request`] hooks run in the same order they were
+/// added, and the [`Middleware::on_response`] hooks in reverse order, so that
+/// the first middleware is the outermost one. If a middleware short-circuits
+/// the request, only the response hooks of itself and the middleware before it
+/// will run.
+pub struct MiddlewareClient<C: BaseHttpClient> {
+    client: C,
+    middleware: Vec<Arc<dyn Middleware<C::Error>>>,
+}
+
+impl<C: BaseHttpClient> MiddlewareClient<C> {
+    /// Wraps an HTTP client, with no middleware at first.
+    #[must_use]
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Adds a middleware to the end of the chain.
+    #[must_use]
+    pub fn with(mut self, middleware: impl Middleware<C::Error> + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Returns the wrapped HTTP client.
+    #[must_use]
+    pub fn inner(&self) -> &C {
+        &self.client
+    }
+}
+
+impl<C: BaseHttpClient> Default for MiddlewareClient<C> {
+    fn default() -> Self {
+        Self::new(C::default())
+    }
+}
+
+impl<C: BaseHttpClient> Clone for MiddlewareClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            middleware: self.middleware.clone(),
+        }
+    }
+}
+
+impl<C: BaseHttpClient> fmt::Debug for MiddlewareClient<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MiddlewareClient")
+            .field("client", &self.client)
+            .field("middleware", &self.middleware.len())
+            .finish()
+    }
+}
+
+fn to_owned_map(map: &HashMap<&str, &str>) -> HashMap<String, String> {
+    map.iter()
+        .map(|(key, val)| ((*key).to_owned(), (*val).to_owned()))
+        .collect()
+}
+
+fn to_borrowed_map(map: &HashMap<String, String>) -> HashMap<&str, &str> {
+    map.iter()
+        .map(|(key, val)| (key.as_str(), val.as_str()))
+        .collect()
+}
+
+impl<C> MiddlewareClient<C>
+where
+    C: BaseHttpClient + Sync,
+    C::Error: From<HttpError> + Send,
+{
+    /// Runs the request through the middleware chain and the HTTP client.
+    #[maybe_async]
+    async fn send(&self, mut request: Request) -> Result<HttpResponse, C::Error> {
+        let mut response = None;
+        let mut ran = 0;
+        for middleware in &self.middleware {
+            ran += 1;
+            response = middleware.on_request(&mut request);
+            if response.is_some() {
+                break;
+            }
+        }
+
+        let mut response = match response {
+            Some(response) => response,
+            None => self.dispatch(&request).await,
+        };
+
+        for middleware in self.middleware[..ran].iter().rev() {
+            middleware.on_response(&request, &mut response);
+        }
+
+        response
+    }
+
+    #[maybe_async]
+    async fn dispatch(&self, request: &Request) -> Result<HttpResponse, C::Error> {
+        let headers = Some(&request.headers).filter(|headers| !headers.is_empty());
+        let url = request.url.as_str();
+        match (request.method, &request.body) {
+            (Method::Get, Body::Query(query)) => {
+                self.client
+                    .get_with_headers(url, headers, &to_borrowed_map(query))
+                    .await
+            }
+            (Method::Post, Body::Json(value)) => {
+                self.client.post_with_headers(url, headers, value).await
+            }
+            (Method::Post, Body::Form(form)) => {
+                self.client
+                    .post_form_with_headers(url, headers, &to_borrowed_map(form))
+                    .await
+            }
+            (Method::Put, Body::Json(value)) => {
+                self.client.put_with_headers(url, headers, value).await
+            }
+            (Method::Delete, Body::Json(value)) => {
+                self.client.delete_with_headers(url, headers, value).await
+            }
+            (method, body) => Err(HttpError::Client(
+                format!(
+                    "a {:?} request can't be sent with the body {:?}",
+                    method, body
+                )
+                .into(),
+            )
+            .into()),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl<C> BaseHttpClient for MiddlewareClient<C>
+where
+    C: BaseHttpClient + Sync,
+    C::Error: From<HttpError> + Send,
+{
+    type Error = C::Error;
+
+    #[inline]
+    async fn get(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<String, Self::Error> {
+        self.get_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<HttpResponse, Self::Error> {
+        let request = Request {
+            method: Method::Get,
+            url: url.to_owned(),
+            headers: headers.cloned().unwrap_or_default(),
+            body: Body::Query(to_owned_map(payload)),
+        };
+        self.send(request).await
+    }
+
+    #[inline]
+    async fn post(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<String, Self::Error> {
+        self.post_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn post_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+        let request = Request {
+            method: Method::Post,
+            url: url.to_owned(),
+            headers: headers.cloned().unwrap_or_default(),
+            body: Body::Json(payload.clone()),
+        };
+        self.send(request).await
+    }
+
+    #[inline]
+    async fn post_form(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<String, Self::Error> {
+        self.post_form_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn post_form_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<HttpResponse, Self::Error> {
+        let request = Request {
+            method: Method::Post,
+            url: url.to_owned(),
+            headers: headers.cloned().unwrap_or_default(),
+            body: Body::Form(to_owned_map(payload)),
+        };
+        self.send(request).await
+    }
+
+    #[inline]
+    async fn put(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<String, Self::Error> {
+        self.put_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn put_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+        let request = Request {
+            method: Method::Put,
+            url: url.to_owned(),
+            headers: headers.cloned().unwrap_or_default(),
+            body: Body::Json(payload.clone()),
+        };
+        self.send(request).await
+    }
+
+    #[inline]
+    async fn delete(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<String, Self::Error> {
+        self.delete_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn delete_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
+        let request = Request {
+            method: Method::Delete,
+            url: url.to_owned(),
+            headers: headers.cloned().unwrap_or_default(),
+            body: Body::Json(payload.clone()),
+        };
+        self.send(request).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Client that responds with the request it received. GET and form
+    /// responses also have some headers, and JSON POST requests respond with
+    /// `201 Created`.
+    #[derive(Clone, Debug, Default)]
+    struct EchoClient;
+
+    impl EchoClient {
+        fn echo(
+            method: &str,
+            url: &str,
+            headers: Option<&Headers>,
+            body: Value,
+        ) -> Result<String, HttpError> {
+            Ok(json!({
+                "method": method,
+                "url": url,
+                "headers": headers,
+                "body": body,
+            })
+            .to_string())
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+    impl BaseHttpClient for EchoClient {
+        type Error = HttpError;
+
+        async fn get(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Query,
+        ) -> Result<String, Self::Error> {
+            self.get_with_headers(url, headers, payload)
+                .await
+                .map(|response| response.body)
+        }
+
+        async fn get_with_headers(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Query,
+        ) -> Result<HttpResponse, Self::Error> {
+            let body = Self::echo("GET", url, headers, json!(payload))?;
+            let mut headers = Headers::new();
+            headers.insert("etag".to_owned(), "\"echo\"".to_owned());
+            Ok(HttpResponse {
+                status: 200,
+                headers,
+                body,
+            })
+        }
+
+        async fn post(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Value,
+        ) -> Result<String, Self::Error> {
+            Self::echo("POST", url, headers, payload.clone())
+        }
+
+        async fn post_with_headers(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Value,
+        ) -> Result<HttpResponse, Self::Error> {
+            let body = Self::echo("POST", url, headers, payload.clone())?;
+            Ok(HttpResponse {
+                status: 201,
+                headers: Headers::new(),
+                body,
+            })
+        }
+
+        async fn post_form(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Form<'_>,
+        ) -> Result<String, Self::Error> {
+            self.post_form_with_headers(url, headers, payload)
+                .await
+                .map(|response| response.body)
+        }
+
+        async fn post_form_with_headers(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Form<'_>,
+        ) -> Result<HttpResponse, Self::Error> {
+            let body = Self::echo("POST_FORM", url, headers, json!(payload))?;
+            let mut headers = Headers::new();
+            headers.insert("date".to_owned(), "today".to_owned());
+            Ok(HttpResponse {
+                status: 200,
+                headers,
+                body,
+            })
+        }
+
+        async fn put(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Value,
+        ) -> Result<String, Self::Error> {
+            Self::echo("PUT", url, headers, payload.clone())
+        }
+
+        async fn put_with_headers(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Value,
+        ) -> Result<HttpResponse, Self::Error> {
+            Self::echo("PUT", url, headers, payload.clone()).map(HttpResponse::from)
+        }
+
+        async fn delete(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Value,
+        ) -> Result<String, Self::Error> {
+            Self::echo("DELETE", url, headers, payload.clone())
+        }
+
+        async fn delete_with_headers(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Value,
+        ) -> Result<HttpResponse, Self::Error> {
+            Self::echo("DELETE", url, headers, payload.clone()).map(HttpResponse::from)
+        }
+    }
+
+    /// Logs the order in which the hooks run
+    struct Logger(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware<HttpError> for Logger {
+        fn on_request(&self, request: &mut Request) -> Option<Result<HttpResponse, HttpError>> {
+            self.1.lock().unwrap().push(format!("request {}", self.0));
+            request
+                .headers
+                .insert(format!("x-{}", self.0), "true".to_owned());
+            None
+        }
+
+        fn on_response(&self, _request: &Request, _response: &mut Result<HttpResponse, HttpError>) {
+            self.1.lock().unwrap().push(format!("response {}", self.0));
+        }
+    }
+
+    /// Fails all the requests to a given URL
+    struct FaultInjector(&'static str);
+
+    impl Middleware<HttpError> for FaultInjector {
+        fn on_request(&self, request: &mut Request) -> Option<Result<HttpResponse, HttpError>> {
+            if request.url == self.0 {
+                Some(Err(HttpError::Client("injected".into())))
+            } else {
+                None
+            }
+        }
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_no_middleware() {
+        let client = MiddlewareClient::new(EchoClient);
+        let mut query = Query::new();
+        query.insert("limit", "10");
+        let response = client.get("/tracks", None, &query).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["method"], "GET");
+        assert_eq!(response["url"], "/tracks");
+        assert_eq!(response["headers"], Value::Null);
+        assert_eq!(response["body"], json!({"limit": "10"}));
+
+        let mut form = Form::new();
+        form.insert("grant_type", "client_credentials");
+        let response = client.post_form("/token", None, &form).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["method"], "POST_FORM");
+        assert_eq!(
+            response["body"],
+            json!({"grant_type": "client_credentials"})
+        );
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = MiddlewareClient::new(EchoClient)
+            .with(Logger("first", Arc::clone(&log)))
+            .with(Logger("second", Arc::clone(&log)));
+
+        let response = client.put("/me", None, &json!({})).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(
+            response["headers"],
+            json!({"x-first": "true", "x-second": "true"})
+        );
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "request first",
+                "request second",
+                "response second",
+                "response first"
+            ]
+        );
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_short_circuit() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = MiddlewareClient::new(EchoClient)
+            .with(Logger("first", Arc::clone(&log)))
+            .with(FaultInjector("/fail"))
+            .with(Logger("last", Arc::clone(&log)));
+
+        let response = client.delete("/fail", None, &json!({})).await;
+        assert!(matches!(response, Err(HttpError::Client(err)) if err.to_string() == "injected"));
+        assert_eq!(*log.lock().unwrap(), ["request first", "response first"]);
+
+        let response = client.delete("/ok", None, &json!({})).await;
+        assert!(response.is_ok());
+    }
+
+    /// Replaces the body of all the responses, and tags them with a header
+    struct Rewrite;
+
+    impl Middleware<HttpError> for Rewrite {
+        fn on_response(&self, _request: &Request, response: &mut Result<HttpResponse, HttpError>) {
+            if let Ok(response) = response {
+                response.body = "rewritten".to_owned();
+                response
+                    .headers
+                    .insert("x-rewritten".to_owned(), "true".to_owned());
+            }
+        }
+    }
+
+    /// Answers all the requests itself with `304 Not Modified`
+    struct NotModified;
+
+    impl Middleware<HttpError> for NotModified {
+        fn on_request(&self, _request: &mut Request) -> Option<Result<HttpResponse, HttpError>> {
+            let mut headers = Headers::new();
+            headers.insert("etag".to_owned(), "\"cached\"".to_owned());
+            Some(Ok(HttpResponse {
+                status: 304,
+                headers,
+                body: String::new(),
+            }))
+        }
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_response_headers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let client = MiddlewareClient::new(EchoClient)
+            .with(Rewrite)
+            .with(Logger("inner", Arc::clone(&log)));
+
+        let response = client
+            .get_with_headers("/me", None, &Query::new())
+            .await
+            .unwrap();
+        assert_eq!(response.body, "rewritten");
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("ETag"), Some("\"echo\""));
+        assert_eq!(response.header("x-rewritten"), Some("true"));
+        assert_eq!(*log.lock().unwrap(), ["request inner", "response inner"]);
+
+        let response = client
+            .post_form_with_headers("/token", None, &Form::new())
+            .await
+            .unwrap();
+        assert_eq!(response.body, "rewritten");
+        assert_eq!(response.header("date"), Some("today"));
+
+        let response = client
+            .post_with_headers("/playlists", None, &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 201);
+
+        // The responses made up by the middleware keep their status and
+        // headers too
+        let client = MiddlewareClient::new(EchoClient)
+            .with(Rewrite)
+            .with(NotModified);
+        let response = client
+            .get_with_headers("/me", None, &Query::new())
+            .await
+            .unwrap();
+        assert_eq!(response.status, 304);
+        assert_eq!(response.header("etag"), Some("\"cached\""));
+        assert_eq!(response.body, "rewritten");
+    }
+
+    /// Turns all the requests into PUT requests, whatever their body is
+    struct PutEverything;
+
+    impl Middleware<HttpError> for PutEverything {
+        fn on_request(&self, request: &mut Request) -> Option<Result<HttpResponse, HttpError>> {
+            request.method = Method::Put;
+            None
+        }
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_mismatched_body() {
+        let client = MiddlewareClient::new(EchoClient).with(PutEverything);
+        let response = client.get("/me", None, &Query::new()).await;
+        assert!(matches!(response, Err(HttpError::Client(_))));
+
+        let response = client.post("/me", None, &json!({})).await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["method"], "PUT");
+    }
+}
@@ -1,57 +1,14 @@
 //! The client implementation for the reqwest HTTP client, which is async by
 //! default.
 
-use super::{BaseHttpClient, Form, Headers, Query};
+use super::{BaseHttpClient, ErrorResponse, Form, Headers, HttpError, HttpResponse, Query};
 
-use std::convert::TryInto;
-
-#[cfg(not(target_arch = "wasm32"))]
-use std::time::Duration;
+use std::{convert::TryInto, time::Duration};
 
 use maybe_async::async_impl;
 use reqwest::{Method, RequestBuilder};
 use serde_json::Value;
 
-/// Custom enum that contains all the possible errors that may occur when using
-/// [`reqwest`].
-///
-/// Sample usage:
-///
-/// ```
-/// # #[tokio::main]
-/// # async fn main() {
-/// use rspotify_http::{HttpError, HttpClient, BaseHttpClient};
-///
-/// let client = HttpClient::default();
-/// let response = client.get("wrongurl", None, &Default::default()).await;
-/// match response {
-///     Ok(data) => println!("request succeeded: {:?}", data),
-///     Err(HttpError::Client(e)) => eprintln!("request failed: {}", e),
-///     Err(HttpError::StatusCode(response)) => {
-///         let code = response.status().as_u16();
-///         match response.json::<rspotify_model::ApiError>().await {
-///             Ok(api_error) => eprintln!("status code {}: {:?}", code, api_error),
-///             Err(_) => eprintln!("status code {}", code),
-///         }
-///     },
-/// }
-/// # }
-/// ```
-#[derive(thiserror::Error, Debug)]
-pub enum ReqwestError {
-    /// The request couldn't be completed because there was an error when trying
-    /// to do so
-    #[error("request: {0}")]
-    Client(#[from] reqwest::Error),
-
-    /// The request was made, but the server returned an unsuccessful status
-    /// code, such as 404 or 503. In some cases, the response may contain a
-    /// custom message from Spotify with more information, which can be
-    /// serialized into `rspotify_model::ApiError`.
-    #[error("status code {}", reqwest::Response::status(.0))]
-    StatusCode(reqwest::Response),
-}
-
 #[derive(Debug, Clone)]
 pub struct ReqwestClient {
     /// reqwest needs an instance of its client to perform requests.
@@ -61,12 +18,10 @@ pub struct ReqwestClient {
 #[cfg(not(target_arch = "wasm32"))]
 impl Default for ReqwestClient {
     fn default() -> Self {
-        let client = reqwest::ClientBuilder::new()
-            .timeout(Duration::from_secs(10))
+        ReqwestClientBuilder::new()
             .build()
             // building with these options cannot fail
-            .unwrap();
-        Self { client }
+            .unwrap()
     }
 }
 
@@ -81,14 +36,175 @@ impl Default for ReqwestClient {
     }
 }
 
+impl From<reqwest::Client> for ReqwestClient {
+    fn from(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+/// Builder to configure a [`ReqwestClient`]. By default, requests time out
+/// after 10 seconds.
+///
+/// Sample usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use rspotify_http::HttpClient;
+///
+/// let client = HttpClient::builder()
+///     .connect_timeout(Duration::from_secs(5))
+///     .proxy("http://proxy.example.com:8080")
+///     .user_agent("my-app/1.0")
+///     .build()
+///     .unwrap();
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct ReqwestClientBuilder {
+    builder: reqwest::ClientBuilder,
+    /// The first error found while configuring the client, which is returned
+    /// when building it.
+    error: Option<HttpError>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ReqwestClientBuilder {
+    fn default() -> Self {
+        Self {
+            builder: reqwest::ClientBuilder::new().timeout(Duration::from_secs(10)),
+            error: None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReqwestClientBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a fallible configuration step, saving the error for later.
+    fn try_with<T, E>(
+        mut self,
+        value: Result<T, E>,
+        f: impl FnOnce(reqwest::ClientBuilder, T) -> reqwest::ClientBuilder,
+    ) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        match value {
+            Ok(value) => self.builder = f(self.builder, value),
+            Err(err) => {
+                self.error.get_or_insert(HttpError::client(err));
+            }
+        }
+        self
+    }
+
+    /// The timeout for the whole request, from connecting until the response
+    /// body has been read.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.timeout(timeout);
+        self
+    }
+
+    /// The timeout for the connection phase only.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.connect_timeout(timeout);
+        self
+    }
+
+    /// The timeout for each read operation of the response.
+    #[must_use]
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.builder = self.builder.read_timeout(timeout);
+        self
+    }
+
+    /// Sends all the requests through the proxy at `url`, which may use the
+    /// `http`, `https` or `socks5` schemes. Otherwise, the system proxy
+    /// configured with environment variables is used.
+    #[must_use]
+    pub fn proxy(self, url: &str) -> Self {
+        self.try_with(reqwest::Proxy::all(url), reqwest::ClientBuilder::proxy)
+    }
+
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.builder = self.builder.user_agent(user_agent);
+        self
+    }
+
+    /// Headers included in every request, unless they are overwritten.
+    #[must_use]
+    pub fn default_headers(self, headers: &Headers) -> Self {
+        self.try_with(headers.try_into(), reqwest::ClientBuilder::default_headers)
+    }
+
+    /// The maximum number of idle connections kept per host.
+    #[must_use]
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.builder = self.builder.pool_max_idle_per_host(max);
+        self
+    }
+
+    /// How long idle connections are kept in the pool, or forever with `None`.
+    #[must_use]
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.builder = self.builder.pool_idle_timeout(timeout);
+        self
+    }
+
+    /// Trusts an additional root certificate in PEM format, e.g., a corporate
+    /// CA.
+    #[cfg(any(
+        feature = "reqwest-default-tls",
+        feature = "reqwest-rustls-tls",
+        feature = "reqwest-native-tls",
+        feature = "reqwest-native-tls-vendored"
+    ))]
+    #[must_use]
+    pub fn root_certificate(self, pem: &[u8]) -> Self {
+        self.try_with(
+            reqwest::Certificate::from_pem(pem),
+            reqwest::ClientBuilder::add_root_certificate,
+        )
+    }
+
+    /// Builds the client, failing if any of the options were invalid.
+    pub fn build(self) -> Result<ReqwestClient, HttpError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        let client = self.builder.build().map_err(HttpError::client)?;
+        Ok(ReqwestClient { client })
+    }
+}
+
 impl ReqwestClient {
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn builder() -> ReqwestClientBuilder {
+        ReqwestClientBuilder::new()
+    }
+
+    /// The underlying reqwest client.
+    #[must_use]
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.client
+    }
+
     async fn request<D>(
         &self,
         method: Method,
         url: &str,
         headers: Option<&Headers>,
         add_data: D,
-    ) -> Result<String, ReqwestError>
+    ) -> Result<HttpResponse, HttpError>
     where
         D: Fn(RequestBuilder) -> RequestBuilder,
     {
@@ -112,21 +228,49 @@ impl ReqwestClient {
 
         // Finally performing the request and handling the response
         log::info!("Making request {:?}", request);
-        let response = request.send().await?;
+        let response = request.send().await.map_err(HttpError::client)?;
 
         // Making sure that the status code is OK
-        if response.status().is_success() {
-            response.text().await.map_err(Into::into)
+        let status = response.status();
+        let headers = collect_headers(response.headers());
+        let body = response.text().await.map_err(HttpError::client)?;
+        if status.is_success() {
+            Ok(HttpResponse {
+                status: status.as_u16(),
+                headers,
+                body,
+            })
         } else {
-            Err(ReqwestError::StatusCode(response))
+            Err(HttpError::StatusCode(ErrorResponse::new(
+                status.as_u16(),
+                headers,
+                body,
+            )))
         }
     }
 }
 
+/// Converts the headers into our own type, with their names in lowercase and
+/// repeated headers joined with commas.
+fn collect_headers(map: &reqwest::header::HeaderMap) -> Headers {
+    let mut headers = Headers::new();
+    for (name, value) in map {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        headers
+            .entry(name.as_str().to_owned())
+            .and_modify(|prev| {
+                prev.push_str(", ");
+                prev.push_str(&value);
+            })
+            .or_insert_with(|| value.into_owned());
+    }
+    headers
+}
+
 #[cfg_attr(target_arch = "wasm32", async_impl(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), async_impl)]
 impl BaseHttpClient for ReqwestClient {
-    type Error = ReqwestError;
+    type Error = HttpError;
 
     #[inline]
     async fn get(
@@ -135,6 +279,18 @@ impl BaseHttpClient for ReqwestClient {
         headers: Option<&Headers>,
         payload: &Query,
     ) -> Result<String, Self::Error> {
+        self.get_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<HttpResponse, Self::Error> {
         self.request(Method::GET, url, headers, |req| req.query(payload))
             .await
     }
@@ -146,6 +302,18 @@ impl BaseHttpClient for ReqwestClient {
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error> {
+        self.post_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn post_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
         self.request(Method::POST, url, headers, |req| req.json(payload))
             .await
     }
@@ -157,6 +325,18 @@ impl BaseHttpClient for ReqwestClient {
         headers: Option<&Headers>,
         payload: &Form<'_>,
     ) -> Result<String, Self::Error> {
+        self.post_form_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn post_form_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<HttpResponse, Self::Error> {
         self.request(Method::POST, url, headers, |req| req.form(payload))
             .await
     }
@@ -168,6 +348,18 @@ impl BaseHttpClient for ReqwestClient {
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error> {
+        self.put_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn put_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
         self.request(Method::PUT, url, headers, |req| req.json(payload))
             .await
     }
@@ -179,7 +371,42 @@ impl BaseHttpClient for ReqwestClient {
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error> {
+        self.delete_with_headers(url, headers, payload)
+            .await
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    async fn delete_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
         self.request(Method::DELETE, url, headers, |req| req.json(payload))
             .await
     }
 }
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_builder_errors() {
+        assert!(ReqwestClient::builder()
+            .proxy("socks5://localhost:1080")
+            .user_agent("rspotify-test")
+            .build()
+            .is_ok());
+        assert!(ReqwestClient::builder().proxy("not a url").build().is_err());
+
+        let mut headers = Headers::new();
+        headers.insert("invalid header".to_owned(), "value".to_owned());
+        assert!(ReqwestClient::builder()
+            .default_headers(&headers)
+            .build()
+            .is_err());
+    }
+}
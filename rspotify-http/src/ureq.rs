@@ -1,8 +1,8 @@
 //! The client implementation for the ureq HTTP client, which is blocking.
 
-use super::{BaseHttpClient, Form, Headers, Query};
+use super::{BaseHttpClient, ErrorResponse, Form, Headers, HttpError, HttpResponse, Query};
 
-use std::{io, time::Duration};
+use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
 use maybe_async::sync_impl;
 use serde_json::Value;
@@ -11,75 +11,275 @@ use rocket_session_store::SessionStore as RocketSessionStore;
 use rocket_session_store::memory::MemoryStore as RocketMemoryStore;
 use cookie::CookieBuilder;
 use rocket::http::CookieJar;
-/// Custom enum that contains all the possible errors that may occur when using
-/// `ureq`.
+
+#[derive(Debug, Clone)]
+pub struct UreqClient {
+    agent: ureq::Agent,
+    /// Headers included in every request, since ureq doesn't support them.
+    default_headers: Headers,
+}
+
+impl Default for UreqClient {
+    fn default() -> Self {
+        UreqClientBuilder::new()
+            .build()
+            // building with these options cannot fail
+            .unwrap()
+    }
+}
+
+impl From<ureq::Agent> for UreqClient {
+    fn from(agent: ureq::Agent) -> Self {
+        Self {
+            agent,
+            default_headers: Headers::new(),
+        }
+    }
+}
+
+/// Builder to configure a [`UreqClient`]. By default, requests time out after
+/// 10 seconds and the proxy is read from the environment.
 ///
 /// Sample usage:
 ///
 /// ```
-/// use rspotify_http::{HttpError, HttpClient, BaseHttpClient};
+/// use std::time::Duration;
+/// use rspotify_http::HttpClient;
 ///
-/// let client = HttpClient::default();
-/// let response = client.get("wrongurl", None, &Default::default());
-/// match response {
-///     Ok(data) => println!("request succeeded: {:?}", data),
-///     Err(HttpError::Transport(e)) => eprintln!("request failed: {}", e),
-///     Err(HttpError::Io(e)) => eprintln!("failed to decode response: {}", e),
-///     Err(HttpError::StatusCode(response)) => {
-///         let code = response.status();
-///         match response.into_json::<rspotify_model::ApiError>() {
-///             Ok(api_error) => eprintln!("status code {}: {:?}", code, api_error),
-///             Err(_) => eprintln!("status code {}", code),
-///         }
-///     },
-/// }
+/// let client = HttpClient::builder()
+///     .connect_timeout(Duration::from_secs(5))
+///     .proxy("http://proxy.example.com:8080")
+///     .user_agent("my-app/1.0")
+///     .build()
+///     .unwrap();
 /// ```
-#[derive(thiserror::Error, Debug)]
-pub enum UreqError {
-    /// The request couldn't be completed because there was an error when trying
-    /// to do so
-    #[error("transport: {0}")]
-    Transport(#[from] ureq::Transport),
-
-    /// There was an error when trying to decode the response
-    #[error("I/O: {0}")]
-    Io(#[from] io::Error),
-
-    /// The request was made, but the server returned an unsuccessful status
-    /// code, such as 404 or 503. In some cases, the response may contain a
-    /// custom message from Spotify with more information, which can be
-    /// serialized into `rspotify_model::ApiError`.
-    #[error("status code {}", ureq::Response::status(.0))]
-    StatusCode(ureq::Response),
+pub struct UreqClientBuilder {
+    agent: ureq::AgentBuilder,
+    default_headers: Headers,
+    #[cfg(feature = "ureq-native-tls")]
+    tls: native_tls::TlsConnectorBuilder,
+    /// The root certificates trusted in addition to the default ones.
+    #[cfg(all(
+        any(feature = "ureq-rustls-tls", feature = "ureq-rustls-tls-native-certs"),
+        not(feature = "ureq-native-tls")
+    ))]
+    root_certificates: Vec<rustls_pki_types::CertificateDer<'static>>,
+    /// The first error found while configuring the client, which is returned
+    /// when building it.
+    error: Option<HttpError>,
 }
 
-#[derive(Debug, Clone)]
-pub struct UreqClient {
-    agent: ureq::Agent,
+// The native-tls builder doesn't implement `Debug`
+impl std::fmt::Debug for UreqClientBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UreqClientBuilder")
+            .field("agent", &self.agent)
+            .field("default_headers", &self.default_headers)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
 }
 
-impl Default for UreqClient {
+impl Default for UreqClientBuilder {
     fn default() -> Self {
         let agent = ureq::AgentBuilder::new()
             .try_proxy_from_env(true)
             .timeout(Duration::from_secs(10));
 
         #[cfg(feature = "ureq-native-tls")]
-        let agent = agent.tls_connector(std::sync::Arc::new(
-            native_tls::TlsConnector::builder()
-                // rust-native-tls defaults to a minimum of TLS 1.0, which is insecure
-                .min_protocol_version(Some(native_tls::Protocol::Tlsv12))
-                .build()
-                .expect("Failed to initialize TLS connector"),
-        ));
+        let mut tls = native_tls::TlsConnector::builder();
+        // rust-native-tls defaults to a minimum of TLS 1.0, which is insecure
+        #[cfg(feature = "ureq-native-tls")]
+        tls.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
 
         Self {
-            agent: agent.build(),
+            agent,
+            default_headers: Headers::new(),
+            #[cfg(feature = "ureq-native-tls")]
+            tls,
+            #[cfg(all(
+                any(feature = "ureq-rustls-tls", feature = "ureq-rustls-tls-native-certs"),
+                not(feature = "ureq-native-tls")
+            ))]
+            root_certificates: Vec::new(),
+            error: None,
         }
     }
 }
 
+impl UreqClientBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The timeout for the whole request, from connecting until the response
+    /// body has been read.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.agent = self.agent.timeout(timeout);
+        self
+    }
+
+    /// The timeout for the connection phase only.
+    #[must_use]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = self.agent.timeout_connect(timeout);
+        self
+    }
+
+    /// The timeout for each read operation of the response.
+    #[must_use]
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = self.agent.timeout_read(timeout);
+        self
+    }
+
+    /// Sends all the requests through the proxy at `url`, which may use the
+    /// `http`, `https`, `socks4` or `socks5` schemes.
+    #[must_use]
+    pub fn proxy(mut self, url: &str) -> Self {
+        match ureq::Proxy::new(url) {
+            Ok(proxy) => self.agent = self.agent.proxy(proxy),
+            Err(err) => {
+                self.error.get_or_insert(HttpError::client(err));
+            }
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.agent = self.agent.user_agent(user_agent);
+        self
+    }
+
+    /// Headers included in every request, unless they are overwritten.
+    #[must_use]
+    pub fn default_headers(mut self, headers: &Headers) -> Self {
+        self.default_headers.extend(headers.clone());
+        self
+    }
+
+    /// The maximum number of idle connections kept per host.
+    #[must_use]
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.agent = self.agent.max_idle_connections_per_host(max);
+        self
+    }
+
+    /// The maximum number of idle connections kept in total.
+    #[must_use]
+    pub fn pool_max_idle(mut self, max: usize) -> Self {
+        self.agent = self.agent.max_idle_connections(max);
+        self
+    }
+
+    /// Trusts an additional root certificate in PEM format, e.g., a corporate
+    /// CA.
+    #[cfg(any(
+        feature = "ureq-native-tls",
+        feature = "ureq-rustls-tls",
+        feature = "ureq-rustls-tls-native-certs"
+    ))]
+    #[must_use]
+    pub fn root_certificate(mut self, pem: &[u8]) -> Self {
+        #[cfg(feature = "ureq-native-tls")]
+        let cert = native_tls::Certificate::from_pem(pem);
+        #[cfg(not(feature = "ureq-native-tls"))]
+        let cert = {
+            use rustls_pki_types::{pem::PemObject, CertificateDer};
+            CertificateDer::from_pem_slice(pem)
+        };
+
+        match cert {
+            #[cfg(feature = "ureq-native-tls")]
+            Ok(cert) => {
+                self.tls.add_root_certificate(cert);
+            }
+            #[cfg(not(feature = "ureq-native-tls"))]
+            Ok(cert) => self.root_certificates.push(cert),
+            Err(err) => {
+                self.error.get_or_insert(HttpError::client(err));
+            }
+        }
+        self
+    }
+
+    /// Builds the client, failing if any of the options were invalid.
+    pub fn build(self) -> Result<UreqClient, HttpError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        #[cfg(feature = "ureq-native-tls")]
+        let agent = self.agent.tls_connector(std::sync::Arc::new(
+            self.tls.build().map_err(HttpError::client)?,
+        ));
+        #[cfg(all(
+            any(feature = "ureq-rustls-tls", feature = "ureq-rustls-tls-native-certs"),
+            not(feature = "ureq-native-tls")
+        ))]
+        let agent = if self.root_certificates.is_empty() {
+            self.agent
+        } else {
+            let config = rustls_config(self.root_certificates)?;
+            self.agent.tls_config(std::sync::Arc::new(config))
+        };
+        #[cfg(not(any(
+            feature = "ureq-native-tls",
+            feature = "ureq-rustls-tls",
+            feature = "ureq-rustls-tls-native-certs"
+        )))]
+        let agent = self.agent;
+
+        Ok(UreqClient {
+            agent: agent.build(),
+            default_headers: self.default_headers,
+        })
+    }
+}
+
+/// The same rustls configuration ureq uses by default, but trusting `extra`
+/// root certificates as well.
+#[cfg(all(
+    any(feature = "ureq-rustls-tls", feature = "ureq-rustls-tls-native-certs"),
+    not(feature = "ureq-native-tls")
+))]
+fn rustls_config(
+    extra: Vec<rustls_pki_types::CertificateDer<'static>>,
+) -> Result<rustls::ClientConfig, HttpError> {
+    let mut roots = rustls::RootCertStore::empty();
+    #[cfg(feature = "ureq-rustls-tls-native-certs")]
+    roots.add_parsable_certificates(
+        rustls_native_certs::load_native_certs().map_err(HttpError::client)?,
+    );
+    #[cfg(not(feature = "ureq-rustls-tls-native-certs"))]
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    for cert in extra {
+        roots.add(cert).map_err(HttpError::client)?;
+    }
+
+    let provider = rustls::crypto::ring::default_provider();
+    Ok(rustls::ClientConfig::builder_with_provider(provider.into())
+        .with_protocol_versions(&[&rustls::version::TLS12, &rustls::version::TLS13])
+        .map_err(HttpError::client)?
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
 impl UreqClient {
+    #[must_use]
+    pub fn builder() -> UreqClientBuilder {
+        UreqClientBuilder::new()
+    }
+
+    /// The underlying ureq agent.
+    #[must_use]
+    pub fn inner(&self) -> &ureq::Agent {
+        &self.agent
+    }
+
     /// The request handling in ureq is split in three parts:
     ///
     /// * The initial request (POST, GET, ...) is given as the `request`
@@ -93,10 +293,14 @@ impl UreqClient {
         mut request: Request,
         headers: Option<&Headers>,
         send_request: D,
-    ) -> Result<String, UreqError>
+    ) -> Result<HttpResponse, HttpError>
     where
         D: Fn(Request) -> Result<Response, ureq::Error>,
     {
+        for (key, val) in &self.default_headers {
+            request = request.set(key, val);
+        }
+
         // Setting the headers, which will be the token auth if unspecified.
         if let Some(headers) = headers {
             for (key, val) in headers.iter() {
@@ -131,19 +335,38 @@ impl UreqClient {
 
         log::info!("Making request {:?}", request);
         // Converting errors from ureq into our custom error types
-        match send_request(request) {
-            Ok(response) => response.into_string().map_err(Into::into),
-            Err(err) => match err {
-                ureq::Error::Status(_, response) => Err(UreqError::StatusCode(response)),
-                ureq::Error::Transport(transport) => Err(UreqError::Transport(transport)),
-            },
+        let response = match send_request(request) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(transport)) => return Err(HttpError::client(transport)),
+        };
+
+        // ureq only considers 4xx and 5xx statuses errors, but responses like
+        // `304 Not Modified` aren't successful either.
+        let status = response.status();
+        let mut headers = Headers::new();
+        for name in response.headers_names() {
+            let value = response.all(&name).join(", ");
+            headers.insert(name.to_ascii_lowercase(), value);
+        }
+        let body = response.into_string().map_err(HttpError::client)?;
+        if (200..300).contains(&status) {
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        } else {
+            Err(HttpError::StatusCode(ErrorResponse::new(
+                status, headers, body,
+            )))
         }
     }
 }
 
 #[sync_impl]
 impl BaseHttpClient for UreqClient {
-    type Error = UreqError;
+    type Error = HttpError;
 
     #[inline]
     fn get(
@@ -152,6 +375,17 @@ impl BaseHttpClient for UreqClient {
         headers: Option<&Headers>,
         payload: &Query,
     ) -> Result<String, Self::Error> {
+        self.get_with_headers(url, headers, payload)
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Query,
+    ) -> Result<HttpResponse, Self::Error> {
         let request = self.agent.get(url);
         let sender = |mut req: Request| {
             for (key, val) in payload.iter() {
@@ -169,6 +403,17 @@ impl BaseHttpClient for UreqClient {
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error> {
+        self.post_with_headers(url, headers, payload)
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    fn post_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
         let request = self.agent.post(url);
         let sender = |req: Request| req.send_json(payload.clone());
         self.request(request, headers, sender)
@@ -181,6 +426,17 @@ impl BaseHttpClient for UreqClient {
         headers: Option<&Headers>,
         payload: &Form<'_>,
     ) -> Result<String, Self::Error> {
+        self.post_form_with_headers(url, headers, payload)
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    fn post_form_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> Result<HttpResponse, Self::Error> {
         let request = self.agent.post(url);
         let sender = |req: Request| {
             let payload = payload
@@ -201,6 +457,17 @@ impl BaseHttpClient for UreqClient {
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error> {
+        self.put_with_headers(url, headers, payload)
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    fn put_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
         let request = self.agent.put(url);
         let sender = |req: Request| req.send_json(payload.clone());
         self.request(request, headers, sender)
@@ -213,8 +480,73 @@ impl BaseHttpClient for UreqClient {
         headers: Option<&Headers>,
         payload: &Value,
     ) -> Result<String, Self::Error> {
+        self.delete_with_headers(url, headers, payload)
+            .map(|response| response.body)
+    }
+
+    #[inline]
+    fn delete_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Value,
+    ) -> Result<HttpResponse, Self::Error> {
         let request = self.agent.delete(url);
         let sender = |req: Request| req.send_json(payload.clone());
         self.request(request, headers, sender)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A self-signed certificate, only used to check that it can be loaded.
+    #[cfg(any(
+        feature = "ureq-native-tls",
+        feature = "ureq-rustls-tls",
+        feature = "ureq-rustls-tls-native-certs"
+    ))]
+    const CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----
+MIIBhjCCAS2gAwIBAgIURMh2XoNxLmLpo9TL95aFZxxF8+swCgYIKoZIzj0EAwIw
+GDEWMBQGA1UEAwwNcnNwb3RpZnktdGVzdDAgFw0yNjEwMTgxODE3MTNaGA8yMTI2
+MDkyNDE4MTcxM1owGDEWMBQGA1UEAwwNcnNwb3RpZnktdGVzdDBZMBMGByqGSM49
+AgEGCCqGSM49AwEHA0IABABFUFYTkmhojVIQN3mNrJ5oNr8NTqe5V1hCiHoybjBy
+LLPKBxdzNrm3c9bioIaHkTgzA5OLzD3tdg3gHG+Aq/WjUzBRMB0GA1UdDgQWBBS2
+lnYkX7g2wh0XLkLQxfdM4wjWnzAfBgNVHSMEGDAWgBS2lnYkX7g2wh0XLkLQxfdM
+4wjWnzAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0cAMEQCIGx2xOuSBmEI
+nLjkNqMWLvWeNOSvGwARQRZz3LbuhMwrAiA+A8HB63YgGivkzNhrPrwqoATAOpPZ
+oDaNvDlFrrlx2Q==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn test_builder_errors() {
+        assert!(UreqClient::builder()
+            .proxy("socks5://localhost:1080")
+            .user_agent("rspotify-test")
+            .build()
+            .is_ok());
+        assert!(UreqClient::builder()
+            .proxy("ftp://localhost:21")
+            .build()
+            .is_err());
+    }
+
+    #[cfg(any(
+        feature = "ureq-native-tls",
+        feature = "ureq-rustls-tls",
+        feature = "ureq-rustls-tls-native-certs"
+    ))]
+    #[test]
+    fn test_root_certificate() {
+        assert!(UreqClient::builder()
+            .root_certificate(CERTIFICATE.as_bytes())
+            .build()
+            .is_ok());
+        assert!(UreqClient::builder()
+            .root_certificate(b"not a certificate")
+            .build()
+            .is_err());
+    }
+}
@@ -25,26 +25,144 @@
 /// manually.insert("playlist-read-collaborative".to_owned());
 /// assert_eq!(macro_with_whitespace, manually);
 /// ```
+///
+/// The string literals are checked at compile time, so that unknown scopes,
+/// like the ones with a typo, are rejected:
+///
+/// ```compile_fail
+/// use rspotify_macros::scopes;
+///
+/// let scopes = scopes!("playlist-modify-privte");
+/// ```
+///
+/// Scopes that aren't documented by Spotify yet may still be used by marking
+/// them with `raw`. Other expressions, like variables, aren't checked either.
+///
+/// ```
+/// use rspotify_macros::scopes;
+///
+/// let custom = String::from("user-read-private");
+/// let scopes = scopes!("user-read-email", raw "some-new-scope", custom);
+/// assert_eq!(scopes.len(), 3);
+/// assert!(scopes.contains("some-new-scope"));
+/// ```
 #[macro_export]
 macro_rules! scopes {
-    ($($key:expr),*) => {{
-        let mut container = ::std::collections::HashSet::new();
-        $(
-            for scope in $key.split_whitespace(){
-            container.insert(scope.to_owned());
-            }
-        )*
+    (@insert $container:ident;) => {};
+    (@insert $container:ident; raw $key:expr $(, $($rest:tt)*)?) => {
+        for scope in $key.split_whitespace() {
+            $container.insert(scope.to_owned());
+        }
+        $crate::scopes!(@insert $container; $($($rest)*)?);
+    };
+    (@insert $container:ident; $key:literal $(, $($rest:tt)*)?) => {
+        const _: () = ::std::assert!(
+            $crate::__private::are_known_scopes($key),
+            ::std::concat!(
+                "unknown scope in ",
+                ::std::stringify!($key),
+                ", mark it with `raw` to use it anyway"
+            )
+        );
+        $crate::scopes!(@insert $container; raw $key $(, $($rest)*)?);
+    };
+    (@insert $container:ident; $key:expr $(, $($rest:tt)*)?) => {
+        $crate::scopes!(@insert $container; raw $key $(, $($rest)*)?);
+    };
+    ($($scopes:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut container = ::std::collections::HashSet::<::std::string::String>::new();
+        $crate::scopes!(@insert container; $($scopes)*);
         container
     }};
 }
 
+/// Implementation details of the macros, which aren't part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    /// Every scope documented by Spotify, the same as `rspotify::model::Scope::ALL`.
+    pub const KNOWN_SCOPES: &[&str] = &[
+        "ugc-image-upload",
+        "user-read-playback-state",
+        "user-modify-playback-state",
+        "user-read-currently-playing",
+        "app-remote-control",
+        "streaming",
+        "playlist-read-private",
+        "playlist-read-collaborative",
+        "playlist-modify-private",
+        "playlist-modify-public",
+        "user-follow-modify",
+        "user-follow-read",
+        "user-read-playback-position",
+        "user-top-read",
+        "user-read-recently-played",
+        "user-library-modify",
+        "user-library-read",
+        "user-read-email",
+        "user-read-private",
+        "user-soa-link",
+        "user-soa-unlink",
+        "soa-manage-entitlements",
+        "soa-manage-partner",
+        "soa-create-partner",
+    ];
+
+    const fn is_whitespace(byte: u8) -> bool {
+        matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0C')
+    }
+
+    /// Whether `scopes[start..end]` is one of [`KNOWN_SCOPES`].
+    const fn is_known_scope(scopes: &[u8], start: usize, end: usize) -> bool {
+        let mut i = 0;
+        while i < KNOWN_SCOPES.len() {
+            let known = KNOWN_SCOPES[i].as_bytes();
+            if known.len() == end - start {
+                let mut j = 0;
+                while j < known.len() && known[j] == scopes[start + j] {
+                    j += 1;
+                }
+                if j == known.len() {
+                    return true;
+                }
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// Whether all the whitespace-separated scopes in `scopes` are known, which
+    /// may be evaluated at compile time.
+    #[must_use]
+    pub const fn are_known_scopes(scopes: &str) -> bool {
+        let scopes = scopes.as_bytes();
+        let mut start = 0;
+        while start < scopes.len() {
+            if is_whitespace(scopes[start]) {
+                start += 1;
+                continue;
+            }
+
+            let mut end = start;
+            while end < scopes.len() && !is_whitespace(scopes[end]) {
+                end += 1;
+            }
+            if !is_known_scope(scopes, start, end) {
+                return false;
+            }
+            start = end;
+        }
+        true
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::scopes;
+    use crate::__private::are_known_scopes;
 
     #[test]
     fn test_hashset() {
-        let scopes = scopes!("hello", "world", "foo", "bar");
+        let scopes = scopes!(raw "hello", raw "world", raw "foo", raw "bar");
         assert_eq!(scopes.len(), 4);
         assert!(scopes.contains("hello"));
         assert!(scopes.contains("world"));
@@ -54,7 +172,7 @@ mod test {
 
     #[test]
     fn test_scopes_with_whitespace() {
-        let scopes = scopes!("      hello world foo bar");
+        let scopes = scopes!(raw "      hello world foo bar");
 
         assert_eq!(scopes.len(), 4);
         assert!(scopes.contains("hello"));
@@ -62,4 +180,22 @@ mod test {
         assert!(scopes.contains("foo"));
         assert!(scopes.contains("bar"));
     }
+
+    #[test]
+    fn test_known_scopes() {
+        let scopes = scopes!(
+            "user-read-email",
+            " playlist-read-private  playlist-modify-public ",
+            raw "not-a-scope",
+        );
+        assert_eq!(scopes.len(), 4);
+        assert!(scopes.contains("playlist-modify-public"));
+        assert!(scopes.contains("not-a-scope"));
+        assert!(scopes!().is_empty());
+
+        assert!(are_known_scopes("user-read-email user-top-read"));
+        assert!(are_known_scopes(""));
+        assert!(!are_known_scopes("user-read-emai"));
+        assert!(!are_known_scopes("user-read-email user-read-emails"));
+    }
 }
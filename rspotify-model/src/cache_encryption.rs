@@ -0,0 +1,281 @@
+//! Encryption of the token cache, which is only available with the
+//! `cache-encryption` feature.
+//!
+//! The encrypted cache is a JSON object with a versioned header and the token
+//! encrypted with AES-256-GCM. The key may be derived from a passphrase with
+//! Argon2id, in which case a random salt is saved in the header as well.
+
+use std::fmt;
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use crate::{ModelError, ModelResult};
+
+/// The version of the encrypted format written by this library.
+pub const ENCRYPTED_CACHE_VERSION: u32 = 1;
+
+/// The environment variable with the passphrase used by `Token::from_cache` to
+/// read encrypted caches, see [`CacheKey::from_env`].
+pub const PASSPHRASE_ENV_VAR: &str = "RSPOTIFY_CACHE_PASSPHRASE";
+
+const CIPHER: &str = "aes-256-gcm";
+const KDF: &str = "argon2id";
+const SALT_LEN: usize = 16;
+
+/// A key to encrypt the token cache with.
+///
+/// Its identifier, if any, is saved in the header of the encrypted cache. This
+/// way, after rotating the key, the cache can still be read by passing both the
+/// new and the old keys, and it will be encrypted with the new one the next
+/// time it's saved.
+#[derive(Clone)]
+pub struct CacheKey {
+    id: Option<String>,
+    secret: Secret,
+}
+
+#[derive(Clone)]
+enum Secret {
+    Key([u8; 32]),
+    Passphrase(String),
+}
+
+impl fmt::Debug for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CacheKey {
+    /// Uses a 256-bit key, which should be random.
+    #[must_use]
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            id: None,
+            secret: Secret::Key(key),
+        }
+    }
+
+    /// Derives the key from a passphrase with Argon2id, using a different salt
+    /// every time the cache is saved.
+    #[must_use]
+    pub fn from_passphrase(passphrase: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            secret: Secret::Passphrase(passphrase.into()),
+        }
+    }
+
+    /// Derives the key from the passphrase in the `RSPOTIFY_CACHE_PASSPHRASE`
+    /// environment variable, if it's set.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        std::env::var(PASSPHRASE_ENV_VAR)
+            .ok()
+            .map(Self::from_passphrase)
+    }
+
+    /// Sets the identifier of the key, which is saved in the header of the
+    /// encrypted cache.
+    #[must_use]
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    fn derive(&self, salt: Option<&[u8]>) -> ModelResult<Key<Aes256Gcm>> {
+        match (&self.secret, salt) {
+            (Secret::Key(key), None) => Ok((*key).into()),
+            (Secret::Passphrase(passphrase), Some(salt)) => {
+                let mut key = Key::<Aes256Gcm>::default();
+                Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|err| ModelError::Encryption(err.to_string()))?;
+                Ok(key)
+            }
+            _ => Err(ModelError::Encryption(
+                "the key doesn't match the key derivation of the cache".to_owned(),
+            )),
+        }
+    }
+}
+
+/// The contents of an encrypted cache file.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedCache {
+    /// The version of the format, whose name is also used to tell encrypted
+    /// caches apart from plaintext ones.
+    #[serde(rename = "rspotify_encrypted_cache")]
+    version: u32,
+    cipher: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_id: Option<String>,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedCache {
+    /// The header is authenticated along with the token, so that it can't be
+    /// modified either.
+    fn associated_data(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            self.version,
+            self.cipher,
+            self.kdf.as_deref().unwrap_or_default(),
+            self.salt.as_deref().unwrap_or_default(),
+            self.key_id.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+fn decode(value: &str) -> ModelResult<Vec<u8>> {
+    STANDARD
+        .decode(value)
+        .map_err(|err| ModelError::Encryption(err.to_string()))
+}
+
+/// Encrypts the contents of the cache with `key`.
+pub(crate) fn encrypt(plaintext: &[u8], key: &CacheKey) -> ModelResult<String> {
+    let salt = match key.secret {
+        Secret::Key(_) => None,
+        Secret::Passphrase(_) => {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            Some(salt)
+        }
+    };
+    let cipher = Aes256Gcm::new(&key.derive(salt.as_ref().map(|salt| &salt[..]))?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+    let mut cache = EncryptedCache {
+        version: ENCRYPTED_CACHE_VERSION,
+        cipher: CIPHER.to_owned(),
+        kdf: salt.map(|_| KDF.to_owned()),
+        salt: salt.map(|salt| STANDARD.encode(salt)),
+        key_id: key.id.clone(),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: String::new(),
+    };
+    let aad = cache.associated_data();
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: aad.as_bytes(),
+            },
+        )
+        .map_err(|err| ModelError::Encryption(err.to_string()))?;
+    cache.ciphertext = STANDARD.encode(ciphertext);
+
+    Ok(serde_json::to_string(&cache)?)
+}
+
+/// Decrypts the contents of the cache with whichever of `keys` was used to
+/// encrypt it. The keys with the same identifier as the cache are tried
+/// first.
+pub(crate) fn decrypt(contents: &str, keys: &[CacheKey]) -> ModelResult<Vec<u8>> {
+    let cache = serde_json::from_str::<EncryptedCache>(contents)?;
+    if cache.version > ENCRYPTED_CACHE_VERSION {
+        return Err(ModelError::Encryption(format!(
+            "unsupported version {} of the encrypted cache",
+            cache.version
+        )));
+    }
+    if cache.cipher != CIPHER || cache.kdf.iter().any(|kdf| kdf != KDF) {
+        return Err(ModelError::Encryption(format!(
+            "unsupported cipher {} of the encrypted cache",
+            cache.cipher
+        )));
+    }
+
+    let salt = cache.salt.as_deref().map(decode).transpose()?;
+    let nonce = decode(&cache.nonce)?;
+    if nonce.len() != 12 {
+        return Err(ModelError::Encryption("invalid nonce".to_owned()));
+    }
+    let ciphertext = decode(&cache.ciphertext)?;
+    let aad = cache.associated_data();
+
+    let (matching, others): (Vec<_>, Vec<_>) = keys
+        .iter()
+        .partition(|key| key.id.is_some() && key.id == cache.key_id);
+    for key in matching.into_iter().chain(others) {
+        let key = match key.derive(salt.as_deref()) {
+            Ok(key) => key,
+            Err(_) => continue,
+        };
+        let payload = Payload {
+            msg: &ciphertext,
+            aad: aad.as_bytes(),
+        };
+        if let Ok(plaintext) = Aes256Gcm::new(&key).decrypt(Nonce::from_slice(&nonce), payload) {
+            return Ok(plaintext);
+        }
+    }
+
+    Err(ModelError::Encryption(
+        "none of the keys can decrypt the cache".to_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::auth::is_encrypted_cache;
+
+    #[test]
+    fn test_roundtrip() {
+        for key in [
+            CacheKey::new([7; 32]),
+            CacheKey::from_passphrase("correct horse battery staple").with_id("v1"),
+        ] {
+            let encrypted = encrypt(b"secret token", &key).unwrap();
+            assert!(is_encrypted_cache(&encrypted));
+            assert!(!encrypted.contains("secret token"));
+            assert_eq!(decrypt(&encrypted, &[key]).unwrap(), b"secret token");
+        }
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let encrypted = encrypt(b"secret token", &CacheKey::new([7; 32])).unwrap();
+        assert!(decrypt(&encrypted, &[CacheKey::new([8; 32])]).is_err());
+        assert!(decrypt(&encrypted, &[CacheKey::from_passphrase("password")]).is_err());
+        assert!(decrypt(&encrypted, &[]).is_err());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = CacheKey::from_passphrase("old").with_id("2023");
+        let new = CacheKey::from_passphrase("new").with_id("2024");
+        let encrypted = encrypt(b"secret token", &old).unwrap();
+        assert_eq!(decrypt(&encrypted, &[new, old]).unwrap(), b"secret token");
+    }
+
+    #[test]
+    fn test_tampered_header() {
+        let key = CacheKey::new([7; 32]).with_id("v1");
+        let encrypted = encrypt(b"secret token", &key).unwrap();
+        let tampered = encrypted.replace("\"v1\"", "\"v2\"");
+        assert!(decrypt(&tampered, &[key]).is_err());
+    }
+}
@@ -6,7 +6,7 @@ pub type ModelResult<T> = Result<T, ModelError>;
 
 /// Matches errors that are returned from the Spotfiy
 /// API as part of the JSON response object.
-#[derive(Debug, Error, Deserialize)]
+#[derive(Clone, Debug, Error, Deserialize, PartialEq, Eq)]
 pub enum ApiError {
     /// See [Error Object](https://developer.spotify.com/documentation/web-api/reference/#object-errorobject)
     #[error("{status}: {message}")]
@@ -29,4 +29,8 @@ pub enum ModelError {
 
     #[error("input/output error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// The token cache couldn't be encrypted or decrypted.
+    #[error("cache encryption error: {0}")]
+    Encryption(String),
 }
@@ -101,7 +101,7 @@ use thiserror::Error;
 use std::net::UdpSocket;
 use sha1::Sha1;
 use digest::{Digest, Output};
-use std::{borrow::Cow, fmt::Debug, hash::Hash};
+use std::{borrow::Cow, convert::TryFrom, fmt::Debug, hash::Hash};
 
 use crate::Type;
 
@@ -120,6 +120,9 @@ pub enum IdError {
     InvalidType,
     /// Spotify id is invalid (empty or contains invalid characters).
     InvalidId,
+    /// The URL doesn't belong to Spotify, i.e., its host isn't
+    /// `open.spotify.com` or `play.spotify.com`.
+    InvalidHost,
 }
 
 /// The main interface for an ID.
@@ -192,6 +195,81 @@ pub fn parse_uri(uri: &str) -> Result<(Type, &str), IdError> {
     }
 }
 
+/// The hosts of the links to Spotify objects that can be parsed.
+const URL_HOSTS: &[&str] = &["open.spotify.com", "play.spotify.com"];
+
+/// Same as [`parse_uri`], but it also accepts the other forms of Spotify links:
+///
+/// * URIs: `spotify:track:{id}` or `spotify/track/{id}`.
+/// * Legacy playlist URIs, which include their owner:
+///   `spotify:user:{user}:playlist:{id}`.
+/// * URLs, with or without their scheme, query and fragment:
+///   `https://open.spotify.com/track/{id}?si=...`. Localized
+///   (`https://open.spotify.com/intl-de/track/{id}`), embed
+///   (`https://open.spotify.com/embed/track/{id}`) and legacy playlist
+///   (`https://open.spotify.com/user/{user}/playlist/{id}`) URLs are accepted
+///   as well.
+///
+/// # Errors
+///
+/// - `IdError::InvalidPrefix` - if `link` is neither a URI nor a URL,
+/// - `IdError::InvalidHost` - if `link` is a URL that doesn't belong to
+///   Spotify,
+/// - `IdError::InvalidFormat` - if it can't be split into type and id parts,
+/// - `IdError::InvalidType` - if its type isn't a valid Spotify type,
+/// - `IdError::InvalidId` - if its id is empty.
+pub fn parse_link(link: &str) -> Result<(Type, &str), IdError> {
+    let link = link.trim();
+    let parts = if let Some(rest) = link.strip_prefix("spotify:") {
+        rest.split(':').collect::<Vec<_>>()
+    } else if let Some(rest) = link.strip_prefix("spotify/") {
+        rest.split('/').collect()
+    } else {
+        let url = link
+            .strip_prefix("https://")
+            .or_else(|| link.strip_prefix("http://"));
+        let (host, path) = match url {
+            Some(url) => url.split_at(url.find('/').unwrap_or(url.len())),
+            // Without a scheme, only Spotify's hosts are recognized as URLs
+            None => match URL_HOSTS
+                .iter()
+                .find(|host| link.starts_with(&format!("{}/", host)))
+            {
+                Some(host) => link.split_at(host.len()),
+                None => return Err(IdError::InvalidPrefix),
+            },
+        };
+        if !URL_HOSTS.contains(&host.to_ascii_lowercase().as_str()) {
+            return Err(IdError::InvalidHost);
+        }
+
+        let path = path.split(['?', '#']).next().unwrap_or("");
+        let mut parts = path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+        if matches!(parts.first(), Some(part) if part.starts_with("intl-")) {
+            parts.remove(0);
+        }
+        if matches!(parts.first(), Some(part) if part.starts_with("embed")) {
+            parts.remove(0);
+        }
+        parts
+    };
+
+    let (tpe, id) = match parts.as_slice() {
+        [tpe, id] => (*tpe, *id),
+        ["user", _, "playlist", id] => ("playlist", *id),
+        _ => return Err(IdError::InvalidFormat),
+    };
+    let tpe = tpe.parse::<Type>().map_err(|_| IdError::InvalidType)?;
+    if id.is_empty() {
+        return Err(IdError::InvalidId);
+    }
+
+    Ok((tpe, id))
+}
+
 /// This macro helps consistently define ID types.
 ///
 /// * The `$type` parameter indicates what variant in `Type` the ID is for (say,
@@ -317,6 +395,9 @@ macro_rules! define_idtypes {
                 /// with `spotify:` or `spotify/`), it must be a valid Spotify
                 /// object ID, i.e. a non-empty valid string.
                 ///
+                /// This is the same as [`Self::from_any`], so URLs and the
+                /// rest of the forms of [`parse_link`] are accepted as well.
+                ///
                 /// # Errors
                 ///
                 /// - `IdError::InvalidType` - if `id_or_uri` is an URI, and
@@ -325,7 +406,9 @@ macro_rules! define_idtypes {
                 ///   with invalid id part, or it's an invalid id (id is invalid
                 ///   if it contains valid characters),
                 /// - `IdError::InvalidFormat` - if `id_or_uri` is an URI, and
-                ///   it can't be split into type and id parts.
+                ///   it can't be split into type and id parts,
+                /// - `IdError::InvalidHost` - if `id_or_uri` is a URL that
+                ///   doesn't belong to Spotify.
                 ///
                 /// # Implementation details
                 ///
@@ -336,9 +419,24 @@ macro_rules! define_idtypes {
                 /// long enough when using `Into<Cow<str>>`, so the only
                 /// sensible choice is to just use a `&str`.
                 pub fn from_id_or_uri(id_or_uri: &'a str) -> Result<Self, IdError> {
-                    match Self::from_uri(id_or_uri) {
-                        Ok(id) => Ok(id),
-                        Err(IdError::InvalidPrefix) => Self::from_id(id_or_uri),
+                    Self::from_any(id_or_uri)
+                }
+
+                /// Parse Spotify ID, URI or URL from string slice, in any of
+                /// the forms accepted by [`parse_link`], such as
+                /// `https://open.spotify.com/intl-de/track/{id}?si=...` or
+                /// `spotify:user:{user}:playlist:{id}`. Anything that isn't a
+                /// URI or URL must be a valid Spotify object ID.
+                ///
+                /// # Errors
+                ///
+                /// The same as [`parse_link`], and `IdError::InvalidType` if
+                /// the type of the link isn't `T`.
+                pub fn from_any(input: &'a str) -> Result<Self, IdError> {
+                    match parse_link(input) {
+                        Ok((tpe, id)) if tpe == Type::$type => Self::from_id(id),
+                        Ok(_) => Err(IdError::InvalidType),
+                        Err(IdError::InvalidPrefix) => Self::from_id(input),
                         Err(error) => Err(error),
                     }
                 }
@@ -448,7 +546,138 @@ macro_rules! define_idtypes {
                     write!(f, "{}", self.uri())
                 }
             }
+
+            impl<'a> From<$name<'a>> for AnyId<'a> {
+                fn from(id: $name<'a>) -> Self {
+                    AnyId::$type(id)
+                }
+            }
+
+            /// Fails with `IdError::InvalidType` if the ID is of another type.
+            impl<'a> TryFrom<AnyId<'a>> for $name<'a> {
+                type Error = IdError;
+
+                fn try_from(id: AnyId<'a>) -> Result<Self, IdError> {
+                    match id {
+                        AnyId::$type(id) => Ok(id),
+                        _ => Err(IdError::InvalidType),
+                    }
+                }
+            }
         )+
+
+        /// An ID of any type, for when it isn't known until it's parsed, e.g.,
+        /// to handle links pasted by users. It can be converted into the ID
+        /// types with [`TryFrom`], and from them with [`From`].
+        ///
+        /// It's serialized as its URI, unlike the rest of IDs, since its type
+        /// would be lost otherwise.
+        ///
+        /// ```
+        /// use std::convert::TryFrom;
+        /// use rspotify_model::{AnyId, Id, PlayableId, TrackId};
+        ///
+        /// let id = AnyId::from_any("https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh").unwrap();
+        /// assert_eq!(id.uri(), "spotify:track:4iV5W9uYEdYUVa79Axb7Rh");
+        /// assert!(PlayableId::try_from(id.clone()).is_ok());
+        /// let track = TrackId::try_from(id).unwrap();
+        /// ```
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum AnyId<'a> {
+            $($type($name<'a>)),+
+        }
+
+        impl<'a> AnyId<'a> {
+            /// Parse a Spotify URI or URL from string slice, in any of the
+            /// forms accepted by [`parse_link`]. Unlike the ID types, a plain
+            /// ID isn't accepted, since its type is unknown.
+            ///
+            /// # Errors
+            ///
+            /// The same as [`parse_link`], and `IdError::InvalidId` if the id
+            /// part isn't valid for its type.
+            pub fn from_any(input: &'a str) -> Result<Self, IdError> {
+                let (tpe, id) = parse_link(input)?;
+                Self::from_type_and_id(tpe, id)
+            }
+
+            /// Initialize the ID of the given type.
+            ///
+            /// # Errors
+            ///
+            /// - `IdError::InvalidId` - if `id` isn't valid for the type.
+            pub fn from_type_and_id<S>(tpe: Type, id: S) -> Result<Self, IdError>
+            where
+                S: Into<Cow<'a, str>>,
+            {
+                match tpe {
+                    $(Type::$type => $name::from_id(id).map(AnyId::$type)),+
+                }
+            }
+
+            #[must_use]
+            pub fn as_ref(&'a self) -> Self {
+                match self {
+                    $(AnyId::$type(x) => AnyId::$type(x.as_ref())),+
+                }
+            }
+
+            #[must_use]
+            pub fn into_static(self) -> AnyId<'static> {
+                match self {
+                    $(AnyId::$type(x) => AnyId::$type(x.into_static())),+
+                }
+            }
+
+            #[must_use]
+            pub fn clone_static(&'a self) -> AnyId<'static> {
+                match self {
+                    $(AnyId::$type(x) => AnyId::$type(x.clone_static())),+
+                }
+            }
+        }
+
+        impl Id for AnyId<'_> {
+            fn id(&self) -> &str {
+                match self {
+                    $(AnyId::$type(x) => x.id()),+
+                }
+            }
+
+            fn _type(&self) -> Type {
+                match self {
+                    $(AnyId::$type(x) => x._type()),+
+                }
+            }
+        }
+    }
+}
+
+impl Serialize for AnyId<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.uri())
+    }
+}
+
+impl<'de> Deserialize<'de> for AnyId<'static> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let link = String::deserialize(deserializer)?;
+        AnyId::from_any(&link)
+            .map(AnyId::into_static)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Displaying the ID shows its URI
+impl std::fmt::Display for AnyId<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.uri())
     }
 }
 
@@ -482,6 +711,14 @@ define_idtypes!(
     User => {
         name: UserId,
         validity: |_| true
+    },
+    Collection => {
+        name: CollectionId,
+        validity: |_| true
+    },
+    Collectionyourepisodes => {
+        name: CollectionYourEpisodesId,
+        validity: |_| true
     }
 );
 
@@ -565,6 +802,54 @@ impl<'a> PlayableId<'a> {
     }
 }
 
+impl<'a> From<PlayContextId<'a>> for AnyId<'a> {
+    fn from(id: PlayContextId<'a>) -> Self {
+        match id {
+            PlayContextId::Artist(x) => AnyId::Artist(x),
+            PlayContextId::Album(x) => AnyId::Album(x),
+            PlayContextId::Playlist(x) => AnyId::Playlist(x),
+            PlayContextId::Show(x) => AnyId::Show(x),
+        }
+    }
+}
+
+/// Fails with `IdError::InvalidType` if the ID can't be played as a context.
+impl<'a> TryFrom<AnyId<'a>> for PlayContextId<'a> {
+    type Error = IdError;
+
+    fn try_from(id: AnyId<'a>) -> Result<Self, IdError> {
+        match id {
+            AnyId::Artist(x) => Ok(PlayContextId::Artist(x)),
+            AnyId::Album(x) => Ok(PlayContextId::Album(x)),
+            AnyId::Playlist(x) => Ok(PlayContextId::Playlist(x)),
+            AnyId::Show(x) => Ok(PlayContextId::Show(x)),
+            _ => Err(IdError::InvalidType),
+        }
+    }
+}
+
+impl<'a> From<PlayableId<'a>> for AnyId<'a> {
+    fn from(id: PlayableId<'a>) -> Self {
+        match id {
+            PlayableId::Track(x) => AnyId::Track(x),
+            PlayableId::Episode(x) => AnyId::Episode(x),
+        }
+    }
+}
+
+/// Fails with `IdError::InvalidType` if the ID isn't playable.
+impl<'a> TryFrom<AnyId<'a>> for PlayableId<'a> {
+    type Error = IdError;
+
+    fn try_from(id: AnyId<'a>) -> Result<Self, IdError> {
+        match id {
+            AnyId::Track(x) => Ok(PlayableId::Track(x)),
+            AnyId::Episode(x) => Ok(PlayableId::Episode(x)),
+            _ => Err(IdError::InvalidType),
+        }
+    }
+}
+
 /// Computes an insecure SHA-1 digest using the provided data.
 pub fn perform_insecure_sha1(data: &[u8]) -> Vec<u8> {
     //SINK
@@ -653,6 +938,182 @@ mod test {
         assert_eq!(json, json_expected);
     }
 
+    #[test]
+    fn test_parse_link() {
+        let cases = [
+            // URIs
+            (URI, Ok((Type::Track, ID))),
+            (URI_SLASHES, Ok((Type::Track, ID))),
+            (
+                "spotify:album:4aawyAB9vmqN3uQ7FjRGTy",
+                Ok((Type::Album, "4aawyAB9vmqN3uQ7FjRGTy")),
+            ),
+            (
+                "spotify:user:wizzler:playlist:37i9dQZF1DXcBWIGoYBM5M",
+                Ok((Type::Playlist, "37i9dQZF1DXcBWIGoYBM5M")),
+            ),
+            (URI_EMPTY, Err(IdError::InvalidType)),
+            (URI_WRONGTYPE1, Err(IdError::InvalidType)),
+            (URI_MIXED1, Err(IdError::InvalidFormat)),
+            (URI_MIXED2, Err(IdError::InvalidFormat)),
+            ("spotify:track:", Err(IdError::InvalidId)),
+            (
+                "spotify:user:wizzler:album:37i9dQZF1DXcBWIGoYBM5M",
+                Err(IdError::InvalidFormat),
+            ),
+            // URLs
+            (
+                "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "https://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh?si=3f1c2d",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "http://open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh#t=10",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "open.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "https://play.spotify.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "https://open.spotify.com/intl-de/track/4iV5W9uYEdYUVa79Axb7Rh?si=3f1c2d",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "https://open.spotify.com/embed/track/4iV5W9uYEdYUVa79Axb7Rh",
+                Ok((Type::Track, ID)),
+            ),
+            (
+                "https://open.spotify.com/embed-podcast/episode/512ojhOuo1ktJprKbVcKyQ",
+                Ok((Type::Episode, "512ojhOuo1ktJprKbVcKyQ")),
+            ),
+            (
+                "https://open.spotify.com/user/wizzler/playlist/37i9dQZF1DXcBWIGoYBM5M",
+                Ok((Type::Playlist, "37i9dQZF1DXcBWIGoYBM5M")),
+            ),
+            (
+                "https://open.spotify.com/artist/0OdUWJ0sBjDrqHygGUXeCF/",
+                Ok((Type::Artist, "0OdUWJ0sBjDrqHygGUXeCF")),
+            ),
+            (
+                "https://example.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+                Err(IdError::InvalidHost),
+            ),
+            ("https://open.spotify.com/", Err(IdError::InvalidFormat)),
+            (
+                "https://open.spotify.com/genre/pop/extra",
+                Err(IdError::InvalidFormat),
+            ),
+            (
+                "https://open.spotify.com/concert/4iV5W9uYEdYUVa79Axb7Rh",
+                Err(IdError::InvalidType),
+            ),
+            // Neither
+            (ID, Err(IdError::InvalidPrefix)),
+            (URI_SHORT, Err(IdError::InvalidPrefix)),
+            (
+                "example.com/track/4iV5W9uYEdYUVa79Axb7Rh",
+                Err(IdError::InvalidPrefix),
+            ),
+        ];
+
+        for (link, expected) in &cases {
+            assert_eq!(&parse_link(link), expected, "parsing {}", link);
+        }
+    }
+
+    #[test]
+    fn test_from_any() {
+        const URL: &str = "https://open.spotify.com/intl-de/track/4iV5W9uYEdYUVa79Axb7Rh?si=3f1c2d";
+        assert_eq!(TrackId::from_any(ID).unwrap().id(), ID);
+        assert_eq!(TrackId::from_any(URL).unwrap().id(), ID);
+        assert_eq!(TrackId::from_id_or_uri(URL).unwrap().id(), ID);
+        assert_eq!(AlbumId::from_any(URL), Err(IdError::InvalidType));
+        assert_eq!(
+            TrackId::from_any("https://open.spotify.com/track/4iV5W9uY-EdYUV"),
+            Err(IdError::InvalidId)
+        );
+        assert_eq!(
+            PlaylistId::from_any("spotify:user:wizzler:playlist:37i9dQZF1DXcBWIGoYBM5M")
+                .unwrap()
+                .id(),
+            "37i9dQZF1DXcBWIGoYBM5M"
+        );
+
+        // The URL generated by an ID can be parsed back
+        let id = ShowId::from_id("38bS44xjbVVZ3No3ByF1dJ").unwrap();
+        assert_eq!(ShowId::from_any(&id.url()).unwrap(), id);
+        let json = format!("\"{}\"", URL);
+        assert_eq!(serde_json::from_str::<TrackId>(&json).unwrap().id(), ID);
+    }
+
+    #[test]
+    fn test_any_id() {
+        const URL: &str = "https://open.spotify.com/intl-de/track/4iV5W9uYEdYUVa79Axb7Rh?si=3f1c2d";
+        let id = AnyId::from_any(URL).unwrap();
+        assert_eq!(id, AnyId::Track(TrackId::from_id(ID).unwrap()));
+        assert_eq!(id._type(), Type::Track);
+        assert_eq!(id.id(), ID);
+        assert_eq!(id.to_string(), URI);
+
+        // Plain IDs have no type
+        assert_eq!(AnyId::from_any(ID), Err(IdError::InvalidPrefix));
+        assert_eq!(
+            AnyId::from_any("spotify:episode:4iV5W9uY-EdYUV"),
+            Err(IdError::InvalidId)
+        );
+
+        // Every type is covered
+        let collection = AnyId::from_any("spotify:collection:tracks").unwrap();
+        assert_eq!(collection._type(), Type::Collection);
+        assert_eq!(collection.uri(), "spotify:collection:tracks");
+        let user = AnyId::from_type_and_id(Type::User, "wizzler").unwrap();
+        assert_eq!(user, AnyId::User(UserId::from_id("wizzler").unwrap()));
+
+        // Conversions into the narrower types
+        assert_eq!(TrackId::try_from(id.clone()).unwrap().id(), ID);
+        assert_eq!(AlbumId::try_from(id.clone()), Err(IdError::InvalidType));
+        assert!(matches!(
+            PlayableId::try_from(id.clone()),
+            Ok(PlayableId::Track(_))
+        ));
+        assert_eq!(
+            PlayContextId::try_from(id.clone()),
+            Err(IdError::InvalidType)
+        );
+        let playlist =
+            AnyId::from_any("spotify:user:wizzler:playlist:37i9dQZF1DXcBWIGoYBM5M").unwrap();
+        assert!(matches!(
+            PlayContextId::try_from(playlist),
+            Ok(PlayContextId::Playlist(_))
+        ));
+        assert_eq!(PlayableId::try_from(user), Err(IdError::InvalidType));
+        assert_eq!(
+            AnyId::from(PlayableId::Track(TrackId::from_id(ID).unwrap())),
+            id
+        );
+
+        // Serialized as a URI, and deserialized from any link
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, format!("\"{}\"", URI));
+        assert_eq!(serde_json::from_str::<AnyId>(&json).unwrap(), id);
+        let json = format!("\"{}\"", URL);
+        assert_eq!(serde_json::from_str::<AnyId>(&json).unwrap(), id);
+        assert!(serde_json::from_str::<AnyId>(&format!("\"{}\"", ID)).is_err());
+
+        let local = String::from(URI);
+        let id: AnyId<'static> = AnyId::from_any(&local).unwrap().into_static();
+        drop(local);
+        assert_eq!(id.id(), ID);
+    }
+
     #[test]
     fn test_multiple_types() {
         fn endpoint<'a>(_ids: impl IntoIterator<Item = PlayableId<'a>>) {}
@@ -5,6 +5,8 @@ pub mod album;
 pub mod artist;
 pub mod audio;
 pub mod auth;
+#[cfg(feature = "cache-encryption")]
+pub mod cache_encryption;
 pub mod category;
 pub mod context;
 pub(crate) mod custom_serde;
@@ -18,6 +20,7 @@ pub mod page;
 pub mod playing;
 pub mod playlist;
 pub mod recommend;
+pub mod scope;
 pub mod search;
 pub mod show;
 pub mod track;
@@ -25,10 +28,13 @@ pub mod user;
 
 pub use {
     album::*, artist::*, audio::*, auth::*, category::*, context::*, device::*, enums::*, error::*,
-    idtypes::*, image::*, offset::*, page::*, playing::*, playlist::*, recommend::*, search::*,
-    show::*, track::*, user::*,
+    idtypes::*, image::*, offset::*, page::*, playing::*, playlist::*, recommend::*, scope::*,
+    search::*, show::*, track::*, user::*,
 };
 
+#[cfg(feature = "cache-encryption")]
+pub use cache_encryption::CacheKey;
+
 use serde::{Deserialize, Serialize};
 
 /// Followers object
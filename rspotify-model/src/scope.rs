@@ -0,0 +1,220 @@
+//! Authorization scopes, which determine what the app may access on behalf of
+//! the user.
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An authorization [scope][reference], which is serialized as its string
+/// form, e.g., `user-read-email`.
+///
+/// The scopes in [`OAuth`](https://docs.rs/rspotify/latest/rspotify/struct.OAuth.html)
+/// and [`Token`](crate::Token) are kept as strings, so this converts from and
+/// into them. Any scope not known by this library is parsed as
+/// [`Scope::Other`], so the conversions never fail.
+///
+/// [reference]: https://developer.spotify.com/documentation/web-api/concepts/scopes
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Scope {
+    /// Write access to user-provided images.
+    UgcImageUpload,
+    /// Read access to a user's player state.
+    UserReadPlaybackState,
+    /// Write access to a user's playback state.
+    UserModifyPlaybackState,
+    /// Read access to a user's currently playing content.
+    UserReadCurrentlyPlaying,
+    /// Remote control playback of Spotify. Only for the iOS and Android SDKs.
+    AppRemoteControl,
+    /// Control playback of a Spotify track. Only for the Web Playback SDK.
+    Streaming,
+    /// Read access to user's private playlists.
+    PlaylistReadPrivate,
+    /// Include collaborative playlists when requesting a user's playlists.
+    PlaylistReadCollaborative,
+    /// Write access to a user's private playlists.
+    PlaylistModifyPrivate,
+    /// Write access to a user's public playlists.
+    PlaylistModifyPublic,
+    /// Write/delete access to the list of artists and other users that the user follows.
+    UserFollowModify,
+    /// Read access to the list of artists and other users that the user follows.
+    UserFollowRead,
+    /// Read access to a user's playback position in a content.
+    UserReadPlaybackPosition,
+    /// Read access to a user's top artists and tracks.
+    UserTopRead,
+    /// Read access to a user's recently played tracks.
+    UserReadRecentlyPlayed,
+    /// Write/delete access to a user's "Your Music" library.
+    UserLibraryModify,
+    /// Read access to a user's library.
+    UserLibraryRead,
+    /// Read access to user's email address.
+    UserReadEmail,
+    /// Read access to user's subscription details (type of user account).
+    UserReadPrivate,
+    /// Link a partner user account to a Spotify user account.
+    UserSoaLink,
+    /// Unlink a partner user account from a Spotify account.
+    UserSoaUnlink,
+    /// Modify entitlements for linked users.
+    SoaManageEntitlements,
+    /// Update partner information.
+    SoaManagePartner,
+    /// Create new partners, platform partners only.
+    SoaCreatePartner,
+    /// Any other scope, e.g., one added by Spotify after this release.
+    Other(String),
+}
+
+impl Scope {
+    /// Every scope documented by Spotify, i.e., all of them but
+    /// [`Scope::Other`].
+    pub const ALL: &'static [Scope] = &[
+        Scope::UgcImageUpload,
+        Scope::UserReadPlaybackState,
+        Scope::UserModifyPlaybackState,
+        Scope::UserReadCurrentlyPlaying,
+        Scope::AppRemoteControl,
+        Scope::Streaming,
+        Scope::PlaylistReadPrivate,
+        Scope::PlaylistReadCollaborative,
+        Scope::PlaylistModifyPrivate,
+        Scope::PlaylistModifyPublic,
+        Scope::UserFollowModify,
+        Scope::UserFollowRead,
+        Scope::UserReadPlaybackPosition,
+        Scope::UserTopRead,
+        Scope::UserReadRecentlyPlayed,
+        Scope::UserLibraryModify,
+        Scope::UserLibraryRead,
+        Scope::UserReadEmail,
+        Scope::UserReadPrivate,
+        Scope::UserSoaLink,
+        Scope::UserSoaUnlink,
+        Scope::SoaManageEntitlements,
+        Scope::SoaManagePartner,
+        Scope::SoaCreatePartner,
+    ];
+
+    /// The string form of the scope, as used by Spotify.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Scope::UgcImageUpload => "ugc-image-upload",
+            Scope::UserReadPlaybackState => "user-read-playback-state",
+            Scope::UserModifyPlaybackState => "user-modify-playback-state",
+            Scope::UserReadCurrentlyPlaying => "user-read-currently-playing",
+            Scope::AppRemoteControl => "app-remote-control",
+            Scope::Streaming => "streaming",
+            Scope::PlaylistReadPrivate => "playlist-read-private",
+            Scope::PlaylistReadCollaborative => "playlist-read-collaborative",
+            Scope::PlaylistModifyPrivate => "playlist-modify-private",
+            Scope::PlaylistModifyPublic => "playlist-modify-public",
+            Scope::UserFollowModify => "user-follow-modify",
+            Scope::UserFollowRead => "user-follow-read",
+            Scope::UserReadPlaybackPosition => "user-read-playback-position",
+            Scope::UserTopRead => "user-top-read",
+            Scope::UserReadRecentlyPlayed => "user-read-recently-played",
+            Scope::UserLibraryModify => "user-library-modify",
+            Scope::UserLibraryRead => "user-library-read",
+            Scope::UserReadEmail => "user-read-email",
+            Scope::UserReadPrivate => "user-read-private",
+            Scope::UserSoaLink => "user-soa-link",
+            Scope::UserSoaUnlink => "user-soa-unlink",
+            Scope::SoaManageEntitlements => "soa-manage-entitlements",
+            Scope::SoaManagePartner => "soa-manage-partner",
+            Scope::SoaCreatePartner => "soa-create-partner",
+            Scope::Other(scope) => scope,
+        }
+    }
+
+    /// Whether it's one of the scopes documented by Spotify.
+    #[must_use]
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Scope::Other(_))
+    }
+}
+
+impl FromStr for Scope {
+    type Err = Infallible;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        Ok(scope.into())
+    }
+}
+
+impl From<&str> for Scope {
+    fn from(scope: &str) -> Self {
+        match scope {
+            "ugc-image-upload" => Scope::UgcImageUpload,
+            "user-read-playback-state" => Scope::UserReadPlaybackState,
+            "user-modify-playback-state" => Scope::UserModifyPlaybackState,
+            "user-read-currently-playing" => Scope::UserReadCurrentlyPlaying,
+            "app-remote-control" => Scope::AppRemoteControl,
+            "streaming" => Scope::Streaming,
+            "playlist-read-private" => Scope::PlaylistReadPrivate,
+            "playlist-read-collaborative" => Scope::PlaylistReadCollaborative,
+            "playlist-modify-private" => Scope::PlaylistModifyPrivate,
+            "playlist-modify-public" => Scope::PlaylistModifyPublic,
+            "user-follow-modify" => Scope::UserFollowModify,
+            "user-follow-read" => Scope::UserFollowRead,
+            "user-read-playback-position" => Scope::UserReadPlaybackPosition,
+            "user-top-read" => Scope::UserTopRead,
+            "user-read-recently-played" => Scope::UserReadRecentlyPlayed,
+            "user-library-modify" => Scope::UserLibraryModify,
+            "user-library-read" => Scope::UserLibraryRead,
+            "user-read-email" => Scope::UserReadEmail,
+            "user-read-private" => Scope::UserReadPrivate,
+            "user-soa-link" => Scope::UserSoaLink,
+            "user-soa-unlink" => Scope::UserSoaUnlink,
+            "soa-manage-entitlements" => Scope::SoaManageEntitlements,
+            "soa-manage-partner" => Scope::SoaManagePartner,
+            "soa-create-partner" => Scope::SoaCreatePartner,
+            other => Scope::Other(other.to_owned()),
+        }
+    }
+}
+
+impl From<String> for Scope {
+    fn from(scope: String) -> Self {
+        match Scope::from(scope.as_str()) {
+            Scope::Other(_) => Scope::Other(scope),
+            known => known,
+        }
+    }
+}
+
+impl From<Scope> for String {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::Other(scope) => scope,
+            known => known.as_str().to_owned(),
+        }
+    }
+}
+
+impl AsRef<str> for Scope {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Scope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Scope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Scope::from)
+    }
+}
@@ -0,0 +1,331 @@
+//! Managing the clients of many users at once, for services like web apps.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use maybe_async::maybe_async;
+
+use crate::{
+    clients::OAuthClient,
+    http::{BaseHttpClient, HttpClient, HttpError},
+    AuthCodeSpotify, ClientResult, Config, Credentials, MemoryTokenStore, OAuth, Token, TokenStore,
+};
+
+#[derive(Debug)]
+struct Account<Http> {
+    client: AuthCodeSpotify<Http>,
+    last_used: DateTime<Utc>,
+}
+
+/// A manager for the [`AuthCodeSpotify`] clients of many users, keyed by
+/// their Spotify user ID or by any other identifier, like a session key.
+///
+/// The clients it lends out are cheap to clone, and share the same HTTP client
+/// (and thus its connection pool) and the same [`TokenStore`], where their
+/// tokens are saved under their key. The tokens are only loaded from the store
+/// when their client is first requested, and then refreshed whenever they
+/// expire if [`Config::token_refreshing`] is enabled. With
+/// [`Self::idle_timeout`], the clients that aren't used for a while are
+/// evicted from memory, and loaded again from the store when needed.
+///
+/// The store is the one in [`Config::token_store`], which should be set to
+/// persist the tokens, e.g., with a [`FileTokenStore`](crate::FileTokenStore)
+/// for a directory. Otherwise, they're kept in a [`MemoryTokenStore`].
+///
+/// Sample usage:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "__async", not(feature = "__sync")))]
+/// # async fn run() -> rspotify::ClientResult<()> {
+/// use std::{sync::Arc, time::Duration};
+/// use rspotify::{prelude::*, Config, Credentials, FileTokenStore, OAuth, SpotifyAccounts};
+///
+/// let config = Config {
+///     token_store: Some(Arc::new(FileTokenStore::new("tokens"))),
+///     ..Default::default()
+/// };
+/// let accounts = SpotifyAccounts::new(Credentials::default(), OAuth::default(), config)
+///     .idle_timeout(Duration::from_secs(30 * 60));
+///
+/// // When the user is redirected back with the code after authorizing the app
+/// # let (session, code) = ("", "");
+/// let spotify = accounts.authorize(session, code).await?;
+///
+/// // In their following requests
+/// if let Some(spotify) = accounts.get(session).await? {
+///     let user = spotify.me().await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpotifyAccounts<Http = HttpClient> {
+    creds: Credentials,
+    oauth: OAuth,
+    config: Config,
+    http: Http,
+    store: Arc<dyn TokenStore>,
+    idle_timeout: Option<Duration>,
+    accounts: Arc<Mutex<HashMap<String, Account<Http>>>>,
+}
+
+impl SpotifyAccounts {
+    /// Builds a new manager, whose clients use the given credentials, OAuth
+    /// information and config.
+    #[must_use]
+    pub fn new(creds: Credentials, oauth: OAuth, config: Config) -> Self {
+        let store = config
+            .token_store
+            .clone()
+            .unwrap_or_else(|| Arc::new(MemoryTokenStore::new()));
+
+        Self {
+            creds,
+            oauth,
+            config,
+            http: HttpClient::default(),
+            store,
+            idle_timeout: None,
+            accounts: Arc::default(),
+        }
+    }
+
+    /// Replaces the HTTP client shared by the clients. See
+    /// [`AuthCodeSpotify::with_http_client`].
+    #[must_use]
+    pub fn with_http_client<Http>(self, http: Http) -> SpotifyAccounts<Http>
+    where
+        Http: BaseHttpClient<Error = HttpError> + Clone + Sync,
+    {
+        SpotifyAccounts {
+            creds: self.creds,
+            oauth: self.oauth,
+            config: self.config,
+            http,
+            store: self.store,
+            idle_timeout: self.idle_timeout,
+            accounts: Arc::default(),
+        }
+    }
+}
+
+impl<Http> SpotifyAccounts<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Clone + Sync,
+{
+    /// Evicts the clients that haven't been used for `timeout` from memory.
+    /// Their tokens are still in the store, so they're loaded again when
+    /// needed.
+    #[must_use]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// The store where the tokens are saved.
+    #[must_use]
+    pub fn token_store(&self) -> &Arc<dyn TokenStore> {
+        &self.store
+    }
+
+    /// The number of clients currently in memory.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.accounts.lock().unwrap().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Builds the client for `key`, whose token is saved in the shared store
+    /// under that same key.
+    #[maybe_async]
+    async fn client(&self, key: &str, token: Option<Token>) -> AuthCodeSpotify<Http> {
+        let config = Config {
+            token_cached: true,
+            token_store: Some(Arc::clone(&self.store)),
+            token_account: key.to_owned(),
+            ..self.config.clone()
+        };
+
+        let client = AuthCodeSpotify {
+            creds: self.creds.clone(),
+            oauth: self.oauth.clone(),
+            config,
+            token: Arc::default(),
+            refresh_lock: Arc::default(),
+            clones: Default::default(),
+            http: self.http.clone(),
+        };
+        *client.token.lock().await.unwrap() = token;
+        client
+    }
+
+    /// Keeps the client for `key` in memory, unless there's one already, and
+    /// returns the one that's kept.
+    fn keep(&self, key: &str, client: AuthCodeSpotify<Http>) -> AuthCodeSpotify<Http> {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(key.to_owned()).or_insert(Account {
+            client,
+            last_used: Utc::now(),
+        });
+        account.last_used = Utc::now();
+        account.client.clone()
+    }
+
+    /// Removes the clients that have been idle for longer than
+    /// [`Self::idle_timeout`] from memory, returning how many there were. This
+    /// is also done every time a client is requested.
+    pub fn evict_idle(&self) -> usize {
+        let timeout = match self
+            .idle_timeout
+            .and_then(|timeout| chrono::Duration::from_std(timeout).ok())
+        {
+            Some(timeout) => timeout,
+            None => return 0,
+        };
+
+        let now = Utc::now();
+        let mut accounts = self.accounts.lock().unwrap();
+        let before = accounts.len();
+        accounts.retain(|_, account| now - account.last_used < timeout);
+        before - accounts.len()
+    }
+
+    /// Returns the URL for a user to authorize the app. `state` should be
+    /// unique for each authorization, and checked when the user is redirected
+    /// back. See [`AuthCodeSpotify::get_authorize_url`].
+    #[maybe_async]
+    pub async fn authorize_url(&self, state: &str, show_dialog: bool) -> ClientResult<String> {
+        let mut client = self.client("", None).await;
+        client.oauth.state = state.to_owned();
+        client.get_authorize_url(show_dialog)
+    }
+
+    /// Requests the token of the user with the `code` they were redirected
+    /// back with after authorizing the app, and saves it under `key`. See
+    /// [`OAuthClient::request_token`].
+    #[maybe_async]
+    pub async fn authorize(&self, key: &str, code: &str) -> ClientResult<AuthCodeSpotify<Http>> {
+        self.evict_idle();
+        let client = self.client(key, None).await;
+        client.request_token(code).await?;
+
+        self.accounts.lock().unwrap().remove(key);
+        Ok(self.keep(key, client))
+    }
+
+    /// Saves a token obtained elsewhere under `key`, replacing the previous
+    /// one, and returns its client.
+    #[maybe_async]
+    pub async fn insert(&self, key: &str, token: Token) -> ClientResult<AuthCodeSpotify<Http>> {
+        self.evict_idle();
+        self.store.save(key, &token).await?;
+
+        let client = self.client(key, Some(token)).await;
+        self.accounts.lock().unwrap().remove(key);
+        Ok(self.keep(key, client))
+    }
+
+    /// Returns the client for `key`, loading its token from the store if it's
+    /// not in memory, or `None` if there's no token for it.
+    #[maybe_async]
+    pub async fn get(&self, key: &str) -> ClientResult<Option<AuthCodeSpotify<Http>>> {
+        self.evict_idle();
+        if let Some(account) = self.accounts.lock().unwrap().get_mut(key) {
+            account.last_used = Utc::now();
+            return Ok(Some(account.client.clone()));
+        }
+
+        match self.store.load(key).await? {
+            Some(token) => {
+                let client = self.client(key, Some(token)).await;
+                Ok(Some(self.keep(key, client)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the client for `key` and deletes its token from the store,
+    /// e.g., when the user logs out.
+    #[maybe_async]
+    pub async fn remove(&self, key: &str) -> ClientResult<()> {
+        self.accounts.lock().unwrap().remove(key);
+        self.store.delete(key).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clients::BaseClient;
+
+    fn token(access_token: &str) -> Token {
+        Token {
+            access_token: access_token.to_owned(),
+            expires_at: None,
+            ..Default::default()
+        }
+    }
+
+    #[maybe_async]
+    async fn access_token(client: &AuthCodeSpotify) -> String {
+        let token = client.get_token();
+        let token = token.lock().await.unwrap();
+        token.as_ref().unwrap().access_token.clone()
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_accounts() {
+        let accounts =
+            SpotifyAccounts::new(Credentials::default(), OAuth::default(), Config::default());
+        let client = accounts.get("alice").await.unwrap();
+        assert!(client.is_none());
+
+        let alice = accounts.insert("alice", token("a")).await.unwrap();
+        accounts.insert("bob", token("b")).await.unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(alice.config.token_account, "alice");
+        assert!(alice.config.token_cached);
+
+        // The clients share the token with the one kept in memory
+        let client = accounts.get("alice").await.unwrap().unwrap();
+        *alice.get_token().lock().await.unwrap() = Some(token("c"));
+        let access = access_token(&client).await;
+        assert_eq!(access, "c");
+
+        accounts.remove("alice").await.unwrap();
+        let client = accounts.get("alice").await.unwrap();
+        assert!(client.is_none());
+        let bob = accounts.token_store().load("bob").await.unwrap();
+        assert_eq!(bob, Some(token("b")));
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_accounts_idle() {
+        let accounts =
+            SpotifyAccounts::new(Credentials::default(), OAuth::default(), Config::default())
+                .idle_timeout(Duration::from_millis(0));
+
+        accounts.insert("alice", token("a")).await.unwrap();
+        assert_eq!(accounts.evict_idle(), 1);
+        assert!(accounts.is_empty());
+
+        // The token is loaded again from the store
+        let alice = accounts.get("alice").await.unwrap().unwrap();
+        let access = access_token(&alice).await;
+        assert_eq!(access, "a");
+    }
+}
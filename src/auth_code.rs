@@ -1,10 +1,10 @@
 use crate::{
     auth_urls,
     clients::{BaseClient, OAuthClient},
-    http::{Form, HttpClient},
+    http::{BaseHttpClient, Form, HttpClient, HttpError},
     join_scopes, params,
     sync::Mutex,
-    ClientError, ClientResult, Config, Credentials, OAuth, Token,
+    ClientError, ClientResult, CloneTracker, Config, Credentials, OAuth, Token,
 };
 use http::{Response, StatusCode, header::LOCATION};
 use std::collections::HashMap;
@@ -62,20 +62,41 @@ use openssl::ssl::{SslMethod, SslContextBuilder, SslVerifyMode, SslConnector,
 /// [example-main]: https://github.com/ramsayleung/rspotify/blob/master/examples/auth_code.rs
 /// [example-webapp]: https://github.com/ramsayleung/rspotify/tree/master/examples/webapp
 /// [example-refresh-token]: https://github.com/ramsayleung/rspotify/blob/master/examples/with_refresh_token.rs
-#[derive(Clone, Debug, Default)]
-pub struct AuthCodeSpotify {
+#[derive(Clone, Debug)]
+pub struct AuthCodeSpotify<Http = HttpClient> {
     pub creds: Credentials,
     pub oauth: OAuth,
     pub config: Config,
     pub token: Arc<Mutex<Option<Token>>>,
-    pub(crate) http: HttpClient,
+    pub(crate) refresh_lock: Arc<Mutex<()>>,
+    pub(crate) clones: CloneTracker,
+    pub(crate) http: Http,
+}
+
+impl Default for AuthCodeSpotify {
+    fn default() -> Self {
+        Self {
+            creds: Default::default(),
+            oauth: Default::default(),
+            config: Default::default(),
+            token: Default::default(),
+            refresh_lock: Default::default(),
+            clones: Default::default(),
+            http: HttpClient::default(),
+        }
+    }
 }
 
 /// This client has access to the base methods.
 #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
-impl BaseClient for AuthCodeSpotify {
-    fn get_http(&self) -> &HttpClient {
+impl<Http> BaseClient for AuthCodeSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
+    type Http = Http;
+
+    fn get_http(&self) -> &Http {
         &self.http
     }
 
@@ -83,6 +104,14 @@ impl BaseClient for AuthCodeSpotify {
         Arc::clone(&self.token)
     }
 
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+        Arc::clone(&self.refresh_lock)
+    }
+
+    fn get_clone_tracker(&self) -> Option<&CloneTracker> {
+        Some(&self.clones)
+    }
+
     fn get_creds(&self) -> &Credentials {
         &self.creds
     }
@@ -111,9 +140,7 @@ impl BaseClient for AuthCodeSpotify {
 
                 token.refresh_token = Some(refresh_token.to_string());
 
-                if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-                    callback_fn.0(token.clone())?;
-                }
+                self.notify_new_token(&token).await?;
 
                 Ok(Some(token))
             }
@@ -129,13 +156,17 @@ impl BaseClient for AuthCodeSpotify {
 /// private endpoints in [`OAuthClient`].
 #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
-impl OAuthClient for AuthCodeSpotify {
+impl<Http> OAuthClient for AuthCodeSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
     fn get_oauth(&self) -> &OAuth {
         &self.oauth
     }
 
     /// Obtains a user access token given a code, as part of the OAuth
     /// authentication. The access token will be saved internally.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn request_token(&self, code: &str) -> ClientResult<()> {
         log::info!("Requesting Auth Code token");
 
@@ -155,9 +186,7 @@ impl OAuthClient for AuthCodeSpotify {
 
         let token = self.fetch_access_token(&data, Some(&headers)).await?;
 
-        if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-            callback_fn.0(token.clone())?;
-        }
+        self.notify_new_token(&token).await?;
 
         *self.token.lock().await.unwrap() = Some(token);
 
@@ -218,6 +247,43 @@ impl AuthCodeSpotify {
         }
     }
 
+    /// Replaces the HTTP client used to make requests. It may be one
+    /// configured with [`HttpClientBuilder`](crate::http::HttpClientBuilder),
+    /// or any other implementation of [`BaseHttpClient`], like the ones in
+    /// [`middleware`](crate::http::middleware) and
+    /// [`cassette`](crate::http::cassette). It can be chained after any of the
+    /// constructors:
+    ///
+    /// ```no_run
+    /// # use rspotify::{http::HttpClient, AuthCodeSpotify, Credentials, OAuth};
+    /// # let (creds, oauth) = (Credentials::default(), OAuth::default());
+    /// let http = HttpClient::builder()
+    ///     .proxy("http://proxy.example.com:8080")
+    ///     .build()
+    ///     .unwrap();
+    /// let spotify = AuthCodeSpotify::new(creds, oauth).with_http_client(http);
+    /// ```
+    #[must_use]
+    pub fn with_http_client<Http>(self, http: Http) -> AuthCodeSpotify<Http>
+    where
+        Http: BaseHttpClient<Error = HttpError> + Sync,
+    {
+        AuthCodeSpotify {
+            creds: self.creds,
+            oauth: self.oauth,
+            config: self.config,
+            token: self.token,
+            refresh_lock: self.refresh_lock,
+            clones: self.clones,
+            http,
+        }
+    }
+}
+
+impl<Http> AuthCodeSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
     /// Returns the URL needed to authorize the current client as the first step
     /// in the authorization flow.
     pub fn get_authorize_url(&self, show_dialog: bool) -> ClientResult<String> {
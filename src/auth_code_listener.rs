@@ -0,0 +1,603 @@
+//! A local HTTP server to receive the callback of the OAuth flow, for apps
+//! whose redirect URI is a loopback address.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use maybe_async::maybe_async;
+use url::Url;
+
+use crate::{ClientError, ClientResult};
+
+/// How often the listener checks whether it has been cancelled or timed out
+/// while waiting for a connection or reading a request.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a connection may take to send its request before it's dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum size of a request, including its headers.
+const MAX_REQUEST_SIZE: usize = 16 * 1024;
+
+const DEFAULT_SUCCESS_PAGE: &str = "<!DOCTYPE html>\
+    <html><head><title>RSpotify</title></head><body>\
+    <h1>Authorization successful</h1>\
+    <p>Go back to your terminal :)</p>\
+    </body></html>";
+
+const DEFAULT_FAILURE_PAGE: &str = "<!DOCTYPE html>\
+    <html><head><title>RSpotify</title></head><body>\
+    <h1>Authorization failed</h1>\
+    <p>Go back to your terminal and try again.</p>\
+    </body></html>";
+
+/// A local HTTP server that waits for the redirect of the user's browser after
+/// authorizing the app, and returns the code in it.
+///
+/// It keeps serving until it receives a valid callback, so other requests made
+/// by the browser, like the one for `/favicon.ico`, are answered with `404 Not
+/// Found` and otherwise ignored. If the user denies access, it returns
+/// [`ClientError::AuthorizationDenied`]. It may also be given a
+/// [timeout](Self::timeout), and be cancelled from elsewhere with an
+/// [`AuthCodeListenerHandle`].
+///
+/// This is what [`OAuthClient::get_authcode_listener`] uses, but it can be
+/// used directly to customize it, e.g., with the pages shown to the user:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "__async", not(feature = "__sync")))]
+/// # async fn run() -> rspotify::ClientResult<()> {
+/// use std::time::Duration;
+/// use rspotify::{prelude::*, AuthCodeListener, AuthCodeSpotify};
+///
+/// # let spotify = AuthCodeSpotify::default();
+/// let listener = AuthCodeListener::new(([127, 0, 0, 1], 8888).into())
+///     .path("/callback")
+///     .state(&spotify.oauth.state)
+///     .timeout(Duration::from_secs(5 * 60))
+///     .success_page("<h1>You're in!</h1>");
+///
+/// let code = listener.listen().await?;
+/// spotify.request_token(&code).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`OAuthClient::get_authcode_listener`]: crate::clients::OAuthClient::get_authcode_listener
+#[derive(Debug, Clone)]
+pub struct AuthCodeListener {
+    addr: SocketAddr,
+    path: Option<String>,
+    state: Option<String>,
+    timeout: Option<Duration>,
+    success_page: String,
+    failure_page: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// A handle to cancel an [`AuthCodeListener`], which then returns
+/// [`ClientError::AuthCodeListenerCancelled`].
+#[derive(Debug, Clone)]
+pub struct AuthCodeListenerHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl AuthCodeListenerHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+/// What the listener does with a request.
+#[derive(Debug, PartialEq)]
+enum Route {
+    /// It's not the callback, so it's ignored.
+    NotFound,
+    /// The callback isn't valid, e.g., because its state doesn't match.
+    Invalid,
+    Denied {
+        error: String,
+        description: Option<String>,
+    },
+    Code(String),
+}
+
+impl AuthCodeListener {
+    /// Listens on `addr`, accepting the callback at any path and with any
+    /// state.
+    #[must_use]
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            path: None,
+            state: None,
+            timeout: None,
+            success_page: DEFAULT_SUCCESS_PAGE.to_owned(),
+            failure_page: DEFAULT_FAILURE_PAGE.to_owned(),
+            cancelled: Arc::default(),
+        }
+    }
+
+    /// Only accepts the callback at `path`, i.e., the path of the redirect
+    /// URI.
+    #[must_use]
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Only accepts the callback with the given `state`, which should be the
+    /// one in [`OAuth::state`](crate::OAuth::state).
+    #[must_use]
+    pub fn state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_owned());
+        self
+    }
+
+    /// Gives up with [`ClientError::AuthCodeListenerTimeout`] if no valid
+    /// callback is received within `timeout`.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The HTML page shown to the user after receiving the code.
+    #[must_use]
+    pub fn success_page(mut self, html: impl Into<String>) -> Self {
+        self.success_page = html.into();
+        self
+    }
+
+    /// The HTML page shown to the user when the authorization was denied or
+    /// the callback isn't valid.
+    #[must_use]
+    pub fn failure_page(mut self, html: impl Into<String>) -> Self {
+        self.failure_page = html.into();
+        self
+    }
+
+    /// Returns a handle to cancel the listener.
+    #[must_use]
+    pub fn handle(&self) -> AuthCodeListenerHandle {
+        AuthCodeListenerHandle {
+            cancelled: Arc::clone(&self.cancelled),
+        }
+    }
+
+    /// Starts the server, and returns the code once the callback is received.
+    #[maybe_async]
+    pub async fn listen(&self) -> ClientResult<String> {
+        let listener = imp::bind(self.addr)
+            .await
+            .map_err(|e| ClientError::AuthCodeListenerBind { addr: self.addr, e })?;
+        log::info!("OAuth server listening on {:?}", self.addr);
+
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let stopped = || self.stopped(deadline);
+        loop {
+            if let Some(err) = stopped() {
+                return Err(err);
+            }
+
+            let mut stream = match imp::accept(&listener).await {
+                Ok(Some(stream)) => stream,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::warn!("Failed to accept connection: {}", err);
+                    continue;
+                }
+            };
+            let target = match imp::read_request(&mut stream, || stopped().is_some()).await {
+                Ok(Some(target)) => target,
+                Ok(None) => continue,
+                Err(err) => {
+                    log::warn!("Failed to read the request: {}", err);
+                    continue;
+                }
+            };
+
+            let route = self.route(&target);
+            let (status, body) = match &route {
+                Route::NotFound => ("404 Not Found", "Not Found"),
+                Route::Invalid => ("400 Bad Request", self.failure_page.as_str()),
+                Route::Denied { .. } => ("200 OK", self.failure_page.as_str()),
+                Route::Code(_) => ("200 OK", self.success_page.as_str()),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: text/html; charset=utf-8\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            if let Err(err) = imp::write_response(&mut stream, response.as_bytes()).await {
+                log::warn!("Failed to write the response: {}", err);
+            }
+
+            match route {
+                Route::NotFound | Route::Invalid => continue,
+                Route::Denied { error, description } => {
+                    return Err(ClientError::AuthorizationDenied { error, description })
+                }
+                Route::Code(code) => return Ok(code),
+            }
+        }
+    }
+
+    /// The error to stop listening with, if it has been cancelled or the
+    /// `deadline` has passed.
+    fn stopped(&self, deadline: Option<Instant>) -> Option<ClientError> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Some(ClientError::AuthCodeListenerCancelled)
+        } else if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            Some(ClientError::AuthCodeListenerTimeout)
+        } else {
+            None
+        }
+    }
+
+    /// Decides what to do with a request for `target`, which is the path and
+    /// query of its URL.
+    fn route(&self, target: &str) -> Route {
+        let url = match Url::parse("http://localhost").and_then(|base| base.join(target)) {
+            Ok(url) => url,
+            Err(_) => return Route::NotFound,
+        };
+        if let Some(path) = &self.path {
+            if path != url.path() {
+                return Route::NotFound;
+            }
+        }
+
+        let params = url.query_pairs().collect::<HashMap<_, _>>();
+        if !params.contains_key("code") && !params.contains_key("error") {
+            return Route::NotFound;
+        }
+        if let Some(state) = &self.state {
+            if params.get("state").map(AsRef::as_ref) != Some(state.as_str()) {
+                log::error!("Request state doesn't match the callback state");
+                return Route::Invalid;
+            }
+        }
+
+        match (params.get("code"), params.get("error")) {
+            (_, Some(error)) => Route::Denied {
+                error: error.to_string(),
+                description: params.get("error_description").map(ToString::to_string),
+            },
+            (Some(code), None) => Route::Code(code.to_string()),
+            (None, None) => Route::NotFound,
+        }
+    }
+}
+
+/// Returns the request target in a request line, e.g., `/callback?code=...`
+/// in `GET /callback?code=... HTTP/1.1`.
+fn request_target(request_line: &str) -> Option<String> {
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_owned()),
+        _ => None,
+    }
+}
+
+/// Whether the whole request has been read. Its headers are read as well, so
+/// that the connection isn't reset before the browser reads the response.
+fn request_complete(request: &[u8]) -> bool {
+    request.len() >= MAX_REQUEST_SIZE || request.windows(4).any(|end| end == b"\r\n\r\n")
+}
+
+/// Returns the request target of a request, if it's a `GET` request.
+fn parse_request(request: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(request)
+        .lines()
+        .next()
+        .and_then(request_target)
+}
+
+fn read_timed_out() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out reading the request")
+}
+
+#[cfg(feature = "__sync")]
+mod imp {
+    use std::{
+        io::{self, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        time::Instant,
+    };
+
+    use super::{parse_request, read_timed_out, request_complete, POLL_INTERVAL, READ_TIMEOUT};
+
+    pub fn bind(addr: SocketAddr) -> io::Result<TcpListener> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    }
+
+    /// Waits for a connection for a short while, so that the caller can check
+    /// whether it should keep listening.
+    pub fn accept(listener: &TcpListener) -> io::Result<Option<TcpStream>> {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(POLL_INTERVAL))?;
+                Ok(Some(stream))
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(POLL_INTERVAL);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads the request, returning its target if it's a `GET` request. It
+    /// gives up with `None` as soon as `stopped` returns true.
+    pub fn read_request(
+        stream: &mut TcpStream,
+        stopped: impl Fn() -> bool,
+    ) -> io::Result<Option<String>> {
+        let start = Instant::now();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request_complete(&request) {
+            if stopped() {
+                return Ok(None);
+            }
+            if start.elapsed() >= READ_TIMEOUT {
+                return Err(read_timed_out());
+            }
+
+            match stream.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => request.extend_from_slice(&buf[..n]),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(parse_request(&request))
+    }
+
+    pub fn write_response(stream: &mut TcpStream, response: &[u8]) -> io::Result<()> {
+        stream.write_all(response)?;
+        stream.flush()
+    }
+}
+
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+mod imp {
+    use std::{io, net::SocketAddr, time::Instant};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        time::timeout,
+    };
+
+    use super::{parse_request, read_timed_out, request_complete, POLL_INTERVAL, READ_TIMEOUT};
+
+    pub async fn bind(addr: SocketAddr) -> io::Result<TcpListener> {
+        TcpListener::bind(addr).await
+    }
+
+    /// Waits for a connection for a short while, so that the caller can check
+    /// whether it should keep listening.
+    pub async fn accept(listener: &TcpListener) -> io::Result<Option<TcpStream>> {
+        match timeout(POLL_INTERVAL, listener.accept()).await {
+            Ok(result) => result.map(|(stream, _)| Some(stream)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Reads the request, returning its target if it's a `GET` request. It
+    /// gives up with `None` as soon as `stopped` returns true.
+    pub async fn read_request(
+        stream: &mut TcpStream,
+        stopped: impl Fn() -> bool,
+    ) -> io::Result<Option<String>> {
+        let start = Instant::now();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request_complete(&request) {
+            if stopped() {
+                return Ok(None);
+            }
+            if start.elapsed() >= READ_TIMEOUT {
+                return Err(read_timed_out());
+            }
+
+            match timeout(POLL_INTERVAL, stream.read(&mut buf)).await {
+                Ok(Ok(0)) => break,
+                Ok(Ok(n)) => request.extend_from_slice(&buf[..n]),
+                Ok(Err(err)) => return Err(err),
+                Err(_) => {}
+            }
+        }
+
+        Ok(parse_request(&request))
+    }
+
+    pub async fn write_response(stream: &mut TcpStream, response: &[u8]) -> io::Result<()> {
+        stream.write_all(response).await?;
+        stream.flush().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let listener = AuthCodeListener::new(([127, 0, 0, 1], 0).into())
+            .path("/callback")
+            .state("abc");
+
+        assert_eq!(listener.route("/favicon.ico"), Route::NotFound);
+        assert_eq!(listener.route("/callback"), Route::NotFound);
+        assert_eq!(listener.route("/other?code=1&state=abc"), Route::NotFound);
+        assert_eq!(listener.route("/callback?code=1&state=xyz"), Route::Invalid);
+        assert_eq!(
+            listener.route("/callback?code=1&state=abc"),
+            Route::Code("1".to_owned())
+        );
+        assert_eq!(
+            listener.route("/callback?error=access_denied&state=abc"),
+            Route::Denied {
+                error: "access_denied".to_owned(),
+                description: None
+            }
+        );
+
+        // Without a path nor a state, any callback is accepted
+        let listener = AuthCodeListener::new(([127, 0, 0, 1], 0).into());
+        assert_eq!(listener.route("/?code=1"), Route::Code("1".to_owned()));
+    }
+
+    /// Sends a request to the listener at `addr`, returning its status line.
+    fn send_request(addr: SocketAddr, target: &str) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let mut stream = loop {
+            match std::net::TcpStream::connect(addr) {
+                Ok(stream) => break stream,
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        write!(stream, "GET {} HTTP/1.1\r\nhost: {}\r\n\r\n", target, addr).unwrap();
+
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status).unwrap();
+        status.trim().to_owned()
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_listen() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let listener = AuthCodeListener::new(addr)
+            .path("/callback")
+            .state("abc")
+            .timeout(Duration::from_secs(10));
+
+        // The requests before the callback are ignored
+        let browser = std::thread::spawn(move || {
+            [
+                send_request(addr, "/favicon.ico"),
+                send_request(addr, "/callback?code=1&state=xyz"),
+                send_request(addr, "/callback?code=2&state=abc"),
+            ]
+        });
+        let code = listener.listen().await.unwrap();
+        assert_eq!(code, "2");
+        assert_eq!(
+            browser.join().unwrap(),
+            [
+                "HTTP/1.1 404 Not Found",
+                "HTTP/1.1 400 Bad Request",
+                "HTTP/1.1 200 OK"
+            ]
+        );
+
+        let browser = std::thread::spawn(move || {
+            send_request(addr, "/callback?error=access_denied&state=abc")
+        });
+        match listener.listen().await {
+            Err(ClientError::AuthorizationDenied { error, .. }) => {
+                assert_eq!(error, "access_denied")
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        browser.join().unwrap();
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_listen_cancel_and_timeout() {
+        let listener = AuthCodeListener::new(([127, 0, 0, 1], 0).into());
+        listener.handle().cancel();
+        let result = listener.listen().await;
+        assert!(matches!(
+            result,
+            Err(ClientError::AuthCodeListenerCancelled)
+        ));
+
+        let listener =
+            AuthCodeListener::new(([127, 0, 0, 1], 0).into()).timeout(Duration::from_millis(10));
+        let result = listener.listen().await;
+        assert!(matches!(result, Err(ClientError::AuthCodeListenerTimeout)));
+    }
+
+    /// Opens a connection to the listener at `addr` that never sends a
+    /// request, and keeps it open for a while.
+    fn idle_connection(addr: SocketAddr) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || {
+            let _stream = loop {
+                match std::net::TcpStream::connect(addr) {
+                    Ok(stream) => break stream,
+                    Err(_) => std::thread::sleep(Duration::from_millis(10)),
+                }
+            };
+            std::thread::sleep(Duration::from_secs(1));
+        })
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_listen_stopped_while_reading() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        // The timeout is honored even if a connection never sends its request
+        let listener = AuthCodeListener::new(addr).timeout(Duration::from_millis(300));
+        let client = idle_connection(addr);
+        let start = Instant::now();
+        let result = listener.listen().await;
+        assert!(matches!(result, Err(ClientError::AuthCodeListenerTimeout)));
+        assert!(start.elapsed() < READ_TIMEOUT);
+        client.join().unwrap();
+
+        // And so is the cancellation
+        let listener = AuthCodeListener::new(addr);
+        let handle = listener.handle();
+        let client = idle_connection(addr);
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            handle.cancel();
+        });
+        let start = Instant::now();
+        let result = listener.listen().await;
+        assert!(matches!(
+            result,
+            Err(ClientError::AuthCodeListenerCancelled)
+        ));
+        assert!(start.elapsed() < READ_TIMEOUT);
+        canceller.join().unwrap();
+        client.join().unwrap();
+    }
+}
@@ -2,10 +2,11 @@ use crate::{
     alphabets, auth_urls,
     clients::{BaseClient, OAuthClient},
     generate_random_string,
-    http::{Form, HttpClient},
+    http::{BaseHttpClient, Form, HttpClient, HttpError},
     join_scopes, params,
+    model::PendingAuthorization,
     sync::Mutex,
-    ClientResult, Config, Credentials, OAuth, Token,
+    ClientError, ClientResult, CloneTracker, Config, Credentials, OAuth, Token,
 };
 use axum_session::SessionConfig;
 use base64::{engine::general_purpose, Engine as _};
@@ -13,7 +14,7 @@ use rocket_session_store::SessionStore as RocketSessionStore;
 use rocket_session_store::memory::MemoryStore as RocketMemoryStore;
 use cookie::CookieBuilder;
 use rocket::http::CookieJar;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use maybe_async::maybe_async;
 use sha2::{Digest, Sha256};
@@ -37,22 +38,44 @@ use password_hash::Error;
 /// client.
 /// [reference]: https://developer.spotify.com/documentation/general/guides/authorization/code-flow
 /// [example-main]: https://github.com/ramsayleung/rspotify/blob/master/examples/auth_code_pkce.rs
-#[derive(Clone, Debug, Default)]
-pub struct AuthCodePkceSpotify {
+#[derive(Clone, Debug)]
+pub struct AuthCodePkceSpotify<Http = HttpClient> {
     pub creds: Credentials,
     pub oauth: OAuth,
     pub config: Config,
     pub token: Arc<Mutex<Option<Token>>>,
     /// The code verifier for the authentication process
     pub verifier: Option<String>,
-    pub(crate) http: HttpClient,
+    pub(crate) refresh_lock: Arc<Mutex<()>>,
+    pub(crate) clones: CloneTracker,
+    pub(crate) http: Http,
+}
+
+impl Default for AuthCodePkceSpotify {
+    fn default() -> Self {
+        Self {
+            creds: Default::default(),
+            oauth: Default::default(),
+            config: Default::default(),
+            token: Default::default(),
+            verifier: Default::default(),
+            refresh_lock: Default::default(),
+            clones: Default::default(),
+            http: HttpClient::default(),
+        }
+    }
 }
 
 /// This client has access to the base methods.
 #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
-impl BaseClient for AuthCodePkceSpotify {
-    fn get_http(&self) -> &HttpClient {
+impl<Http> BaseClient for AuthCodePkceSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
+    type Http = Http;
+
+    fn get_http(&self) -> &Http {
         &self.http
     }
 
@@ -60,6 +83,14 @@ impl BaseClient for AuthCodePkceSpotify {
         Arc::clone(&self.token)
     }
 
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+        Arc::clone(&self.refresh_lock)
+    }
+
+    fn get_clone_tracker(&self) -> Option<&CloneTracker> {
+        Some(&self.clones)
+    }
+
     fn get_creds(&self) -> &Credentials {
         &self.creds
     }
@@ -84,9 +115,7 @@ impl BaseClient for AuthCodePkceSpotify {
 
                 let token = self.fetch_access_token(&data, None).await?;
 
-                if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-                    callback_fn.0(token.clone())?;
-                }
+                self.notify_new_token(&token).await?;
 
                 Ok(Some(token))
             }
@@ -99,39 +128,28 @@ impl BaseClient for AuthCodePkceSpotify {
 /// private endpoints in [`OAuthClient`].
 #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
-impl OAuthClient for AuthCodePkceSpotify {
+impl<Http> OAuthClient for AuthCodePkceSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
     fn get_oauth(&self) -> &OAuth {
         &self.oauth
     }
 
     /// Note that the code verifier must be set at this point, either manually
     /// or with [`Self::get_authorize_url`]. Otherwise, this function will
-    /// panic.
+    /// panic. To request the token of an authorization started elsewhere, see
+    /// [`Self::request_token_from_pending`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn request_token(&self, code: &str) -> ClientResult<()> {
-        log::info!("Requesting PKCE Auth Code token");
-
         let verifier = self.verifier.as_ref().expect(
             "Unknown code verifier. Try calling \
             `AuthCodePkceSpotify::get_authorize_url` first or setting it \
             yourself.",
         );
 
-        let mut data = Form::new();
-        data.insert(params::CLIENT_ID, &self.creds.id);
-        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_AUTH_CODE);
-        data.insert(params::CODE, code);
-        data.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
-        data.insert(params::CODE_VERIFIER, verifier);
-
-        let token = self.fetch_access_token(&data, None).await?;
-
-        if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-            callback_fn.0(token.clone())?;
-        }
-
-        *self.token.lock().await.unwrap() = Some(token);
-
-        self.write_token_cache().await
+        self.request_token_with_verifier(code, verifier, &self.oauth.redirect_uri)
+            .await
     }
 }
 
@@ -188,6 +206,44 @@ impl AuthCodePkceSpotify {
         }
     }
 
+    /// Replaces the HTTP client used to make requests. It may be one
+    /// configured with [`HttpClientBuilder`](crate::http::HttpClientBuilder),
+    /// or any other implementation of [`BaseHttpClient`], like the ones in
+    /// [`middleware`](crate::http::middleware) and
+    /// [`cassette`](crate::http::cassette). It can be chained after any of the
+    /// constructors:
+    ///
+    /// ```no_run
+    /// # use rspotify::{http::HttpClient, AuthCodePkceSpotify, Credentials, OAuth};
+    /// # let (creds, oauth) = (Credentials::default(), OAuth::default());
+    /// let http = HttpClient::builder()
+    ///     .proxy("http://proxy.example.com:8080")
+    ///     .build()
+    ///     .unwrap();
+    /// let spotify = AuthCodePkceSpotify::new(creds, oauth).with_http_client(http);
+    /// ```
+    #[must_use]
+    pub fn with_http_client<Http>(self, http: Http) -> AuthCodePkceSpotify<Http>
+    where
+        Http: BaseHttpClient<Error = HttpError> + Sync,
+    {
+        AuthCodePkceSpotify {
+            creds: self.creds,
+            oauth: self.oauth,
+            config: self.config,
+            token: self.token,
+            verifier: self.verifier,
+            refresh_lock: self.refresh_lock,
+            clones: self.clones,
+            http,
+        }
+    }
+}
+
+impl<Http> AuthCodePkceSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
     fn generate_session_value() -> String {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -257,25 +313,121 @@ impl AuthCodePkceSpotify {
     /// [reference]: https://developer.spotify.com/documentation/general/guides/authorization/code-flow
     /// [rfce]: https://datatracker.ietf.org/doc/html/rfc7636#section-4.1
     pub fn get_authorize_url(&mut self, verifier_bytes: Option<usize>) -> ClientResult<String> {
-        log::info!("Building auth URL");
-
-        let scopes = join_scopes(&self.oauth.scopes);
         let verifier_bytes = verifier_bytes.unwrap_or(43);
-        
         let (verifier, challenge) = Self::generate_codes(verifier_bytes);
         // The verifier will be needed later when requesting the token
         self.verifier = Some(verifier);
 
+        self.build_authorize_url(
+            &self.oauth.state,
+            &self.oauth.redirect_uri,
+            &self.oauth.scopes,
+            &challenge,
+        )
+    }
+
+    /// Starts an authorization that may be completed by a different client,
+    /// even in another process, with [`Self::request_token_from_pending`].
+    ///
+    /// It returns the URL to authorize the app, like
+    /// [`Self::get_authorize_url`], along with the [`PendingAuthorization`] to
+    /// save until the user is redirected back. Instead of [`OAuth::state`], a
+    /// new random state is used for every authorization.
+    pub fn start_authorization(
+        &self,
+        verifier_bytes: Option<usize>,
+    ) -> ClientResult<(String, PendingAuthorization)> {
+        let (verifier, challenge) = Self::generate_codes(verifier_bytes.unwrap_or(43));
+        let pending = PendingAuthorization {
+            state: generate_random_string(16, alphabets::ALPHANUM),
+            verifier,
+            redirect_uri: self.oauth.redirect_uri.clone(),
+            scopes: self.oauth.scopes.clone(),
+            created_at: self.config.now(),
+        };
+
+        let url = self.build_authorize_url(
+            &pending.state,
+            &pending.redirect_uri,
+            &pending.scopes,
+            &challenge,
+        )?;
+        Ok((url, pending))
+    }
+
+    /// Requests the token of an authorization started with
+    /// [`Self::start_authorization`], given the `code` and `state` the user
+    /// was redirected back with. The client doesn't need to be the same that
+    /// started it.
+    ///
+    /// It fails with [`ClientError::StateMismatch`] if the state isn't the one
+    /// of the authorization, and with [`ClientError::AuthorizationExpired`] if
+    /// it has expired.
+    #[maybe_async]
+    pub async fn request_token_from_pending(
+        &self,
+        pending: &PendingAuthorization,
+        code: &str,
+        state: &str,
+    ) -> ClientResult<()> {
+        if pending.state != state {
+            log::error!("Request state doesn't match the callback state");
+            return Err(ClientError::StateMismatch);
+        }
+        if pending.is_expired_at(self.config.now()) {
+            return Err(ClientError::AuthorizationExpired);
+        }
+
+        self.request_token_with_verifier(code, &pending.verifier, &pending.redirect_uri)
+            .await
+    }
+
+    #[maybe_async]
+    async fn request_token_with_verifier(
+        &self,
+        code: &str,
+        verifier: &str,
+        redirect_uri: &str,
+    ) -> ClientResult<()> {
+        log::info!("Requesting PKCE Auth Code token");
+
+        let mut data = Form::new();
+        data.insert(params::CLIENT_ID, &self.creds.id);
+        data.insert(params::GRANT_TYPE, params::GRANT_TYPE_AUTH_CODE);
+        data.insert(params::CODE, code);
+        data.insert(params::REDIRECT_URI, redirect_uri);
+        data.insert(params::CODE_VERIFIER, verifier);
+
+        let token = self.fetch_access_token(&data, None).await?;
+
+        self.notify_new_token(&token).await?;
+
+        *self.token.lock().await.unwrap() = Some(token);
+
+        self.write_token_cache().await
+    }
+
+    fn build_authorize_url(
+        &self,
+        state: &str,
+        redirect_uri: &str,
+        scopes: &HashSet<String>,
+        challenge: &str,
+    ) -> ClientResult<String> {
+        log::info!("Building auth URL");
+
+        let scopes = join_scopes(scopes);
+
         let mut payload: HashMap<&str, &str> = HashMap::new();
         payload.insert(params::CLIENT_ID, &self.creds.id);
         payload.insert(params::RESPONSE_TYPE, params::RESPONSE_TYPE_CODE);
-        payload.insert(params::REDIRECT_URI, &self.oauth.redirect_uri);
+        payload.insert(params::REDIRECT_URI, redirect_uri);
         payload.insert(
             params::CODE_CHALLENGE_METHOD,
             params::CODE_CHALLENGE_METHOD_S256,
         );
-        payload.insert(params::CODE_CHALLENGE, &challenge);
-        payload.insert(params::STATE, &self.oauth.state);
+        payload.insert(params::CODE_CHALLENGE, challenge);
+        payload.insert(params::STATE, state);
         payload.insert(params::SCOPE, &scopes);
 
         let request_url = self.auth_url(auth_urls::AUTHORIZE);
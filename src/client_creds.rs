@@ -1,9 +1,9 @@
 use crate::{
     clients::BaseClient,
-    http::{Form, HttpClient},
+    http::{BaseHttpClient, Form, HttpClient, HttpError},
     params,
     sync::Mutex,
-    ClientResult, Config, Credentials, Token,
+    ClientResult, CloneTracker, Config, Credentials, Token,
 };
 use axum_session::SessionConfig;
 use maybe_async::maybe_async;
@@ -23,24 +23,52 @@ use jsonwebtoken::dangerous::insecure_decode;
 ///
 /// [reference]: https://developer.spotify.com/documentation/general/guides/authorization/client-credentials/
 /// [example-main]: https://github.com/ramsayleung/rspotify/blob/master/examples/client_creds.rs
-#[derive(Clone, Debug, Default)]
-pub struct ClientCredsSpotify {
+#[derive(Clone, Debug)]
+pub struct ClientCredsSpotify<Http = HttpClient> {
     pub config: Config,
     pub creds: Credentials,
     pub token: Arc<Mutex<Option<Token>>>,
-    pub(crate) http: HttpClient,
+    pub(crate) refresh_lock: Arc<Mutex<()>>,
+    pub(crate) clones: CloneTracker,
+    pub(crate) http: Http,
+}
+
+impl Default for ClientCredsSpotify {
+    fn default() -> Self {
+        Self {
+            config: Default::default(),
+            creds: Default::default(),
+            token: Default::default(),
+            refresh_lock: Default::default(),
+            clones: Default::default(),
+            http: HttpClient::default(),
+        }
+    }
 }
 /// This client has access to the base methods.
 #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
-impl BaseClient for ClientCredsSpotify {
-    fn get_http(&self) -> &HttpClient {
+impl<Http> BaseClient for ClientCredsSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
+    type Http = Http;
+
+    fn get_http(&self) -> &Http {
         &self.http
     }
     fn get_token(&self) -> Arc<Mutex<Option<Token>>> {
         Arc::clone(&self.token)
     }
 
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+        Arc::clone(&self.refresh_lock)
+    }
+
+    fn get_clone_tracker(&self) -> Option<&CloneTracker> {
+        Some(&self.clones)
+    }
+
     fn get_creds(&self) -> &Credentials {
         &self.creds
     }
@@ -91,11 +119,49 @@ impl ClientCredsSpotify {
         }
     }
 
-    /// Tries to read the cache file's token.
+    /// Replaces the HTTP client used to make requests. It may be one
+    /// configured with [`HttpClientBuilder`](crate::http::HttpClientBuilder),
+    /// or any other implementation of [`BaseHttpClient`], like the ones in
+    /// [`middleware`](crate::http::middleware) and
+    /// [`cassette`](crate::http::cassette). It can be chained after any of the
+    /// constructors:
     ///
-    /// This will return an error if the token couldn't be read (e.g. it's not
-    /// available or the JSON is malformed). It may return `Ok(None)` if:
+    /// ```no_run
+    /// # use rspotify::{http::HttpClient, ClientCredsSpotify, Credentials};
+    /// # let creds = Credentials::default();
+    /// let http = HttpClient::builder()
+    ///     .proxy("http://proxy.example.com:8080")
+    ///     .build()
+    ///     .unwrap();
+    /// let spotify = ClientCredsSpotify::new(creds).with_http_client(http);
+    /// ```
+    #[must_use]
+    pub fn with_http_client<Http>(self, http: Http) -> ClientCredsSpotify<Http>
+    where
+        Http: BaseHttpClient<Error = HttpError> + Sync,
+    {
+        ClientCredsSpotify {
+            config: self.config,
+            creds: self.creds,
+            token: self.token,
+            refresh_lock: self.refresh_lock,
+            clones: self.clones,
+            http,
+        }
+    }
+}
+
+impl<Http> ClientCredsSpotify<Http>
+where
+    Http: BaseHttpClient<Error = HttpError> + Sync,
+{
+    /// Tries to read the token from the token store, for the configured
+    /// account.
+    ///
+    /// This will return an error if the token couldn't be read (e.g. the JSON
+    /// is malformed). It may return `Ok(None)` if:
     ///
+    /// * There's no token saved for the account
     /// * The read token is expired
     /// * The cached token is disabled in the config
     #[maybe_async]
@@ -106,13 +172,9 @@ impl ClientCredsSpotify {
         }
 
         log::info!("Reading token cache");
-        let token = Token::from_cache(&self.get_config().cache_path)?;
-        if token.is_expired() {
-            // Invalid token, since it's expired.
-            Ok(None)
-        } else {
-            Ok(Some(token))
-        }
+        let token = self.load_token_cache().await?;
+        // Expired tokens are invalid
+        Ok(token.filter(|token| !self.get_config().is_token_expired(token)))
     }
 
     /// Fetch access token
@@ -132,9 +194,7 @@ impl ClientCredsSpotify {
 
         let token = self.fetch_access_token(&data, Some(&headers)).await?;
 
-        if let Some(callback_fn) = &*self.get_config().token_callback_fn.clone() {
-            callback_fn.0(token.clone())?;
-        }
+        self.notify_new_token(&token).await?;
         self.request_token();
         Ok(token)
     }
@@ -142,6 +202,7 @@ impl ClientCredsSpotify {
     /// Obtains the client access token for the app. The resulting token will be
     /// saved internally.
     #[maybe_async]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub async fn request_token(&self) -> ClientResult<()> {
         log::info!("Requesting Client Credentials token");
 
@@ -8,14 +8,17 @@ use crate::{
         oauth::handle_client_buffer,
         verify_cached_report_exists,
     },
-    http::{BaseHttpClient, Form, Headers, HttpClient, Query},
+    clock::parse_http_date,
+    http::{middleware::Method, BaseHttpClient, Form, Headers, HttpError, HttpResponse, Query},
     join_ids,
     model::*,
-    sync::Mutex,
+    rate_limit::RateLimitPermit,
+    response_cache::{cache_key, CachedResponse, NOT_MODIFIED},
+    sync::{self, Mutex},
+    telemetry::RequestSpan,
     util::build_map,
-    ClientError, ClientResult, Config, Credentials, Token,
+    ClientError, ClientResult, CloneTracker, Config, Credentials, Token, TokenStoreLock,
 };
-use chrono::Utc;
 use ldap3::{LdapConn, Mod};
 use libc;
 use maybe_async::maybe_async;
@@ -47,10 +50,14 @@ use crate::clients::execute_dynamic_expression;
 #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
 pub trait BaseClient
 where
-    Self: Send + Sync + Default + Clone + fmt::Debug,
+    Self: Send + Sync + Clone + fmt::Debug,
 {
+    /// The HTTP client used to perform the requests, which is
+    /// [`HttpClient`](crate::http::HttpClient) by default.
+    type Http: BaseHttpClient<Error = HttpError> + Sync;
+
     fn get_config(&self) -> &Config;
-    fn get_http(&self) -> &HttpClient;
+    fn get_http(&self) -> &Self::Http;
     fn get_creds(&self) -> &Credentials;
 
     /// Note that the token is wrapped by a `Mutex` in order to allow interior
@@ -58,6 +65,24 @@ where
     /// be mutable (the token is accessed to from every endpoint).
     fn get_token(&self) -> Arc<Mutex<Option<Token>>>;
 
+    /// Returns the lock held while the token is refreshed, which is shared by
+    /// the clones of the client. This way, only one refresh is in flight at a
+    /// time, and the callers waiting for it reuse its token instead of
+    /// refreshing it again.
+    ///
+    /// It should be stored along with the token returned by
+    /// [`Self::get_token`], so that the clients sharing that token share the
+    /// lock as well.
+    fn get_refresh_lock(&self) -> Arc<Mutex<()>>;
+
+    /// Returns the [`CloneTracker`] shared by the clones of the client, so
+    /// that a [`TokenRefresher`](crate::TokenRefresher) running on one of them
+    /// can tell when the rest have been dropped. There's none by default, in
+    /// which case the refresher never stops on its own.
+    fn get_clone_tracker(&self) -> Option<&CloneTracker> {
+        None
+    }
+
     /// Returns the absolute URL for an endpoint in the API.
     fn api_url(&self, url: &str) -> String {
         let mut base = self.get_config().api_base_url.clone();
@@ -100,7 +125,8 @@ where
     /// Re-authenticate the client automatically if it's configured to do so,
     /// which uses the refresh token to obtain a new access token.
     async fn auto_reauth(&self) -> ClientResult<()> {
-        if !self.get_config().token_refreshing {
+        let config = self.get_config();
+        if !config.token_refreshing {
             return Ok(());
         }
 
@@ -112,7 +138,7 @@ where
             .await
             .unwrap()
             .as_ref()
-            .map_or(false, Token::is_expired);
+            .map_or(false, |token| config.is_token_expired(token));
 
         if should_reauth {
             let mut buffer = [0u8; 256];
@@ -139,7 +165,7 @@ where
             .spawn()?; 
 
             let _status = child.wait().await?;
-            self.refresh_token().await
+            self.refresh_expired_token().await
         } else {
             Ok(())
         }
@@ -147,12 +173,206 @@ where
 
     /// Refreshes the current access token given a refresh token. The obtained
     /// token will be saved internally.
+    ///
+    /// If the token is cached, the token store is locked meanwhile, and the
+    /// token is loaded from it instead if another process sharing it has
+    /// refreshed it already.
+    ///
+    /// Concurrent calls are coalesced: if the token is refreshed by another
+    /// caller while waiting for [`Self::get_refresh_lock`], its new token is
+    /// used instead. If that refresh fails, the next caller tries again.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn refresh_token(&self) -> ClientResult<()> {
-        let token = self.refetch_token().await?;
+        let stale = self.current_access_token().await;
+        let refresh_lock = self.get_refresh_lock();
+        let _refreshing = refresh_lock.lock().await.unwrap();
+        if self.current_access_token().await != stale {
+            return Ok(());
+        }
+
+        self.refresh_token_locked().await
+    }
+
+    /// Same as [`Self::refresh_token`], but only if the token is still
+    /// expired once [`Self::get_refresh_lock`] is taken, which is what
+    /// [`Self::auto_reauth`] needs: the expiration is checked without the
+    /// lock, so the token may have been refreshed by someone else meanwhile.
+    #[doc(hidden)]
+    async fn refresh_expired_token(&self) -> ClientResult<()> {
+        let refresh_lock = self.get_refresh_lock();
+        let _refreshing = refresh_lock.lock().await.unwrap();
+        let config = self.get_config();
+        let expired = self
+            .get_token()
+            .lock()
+            .await
+            .unwrap()
+            .as_ref()
+            .map_or(false, |token| config.is_token_expired(token));
+        if !expired {
+            return Ok(());
+        }
+
+        self.refresh_token_locked().await
+    }
+
+    /// Refreshes the token once [`Self::get_refresh_lock`] is held.
+    #[doc(hidden)]
+    async fn refresh_token_locked(&self) -> ClientResult<()> {
+        let _lock = self.lock_token_cache().await?;
+        if self.reload_refreshed_token().await? {
+            return Ok(());
+        }
+
+        let token = self.refetch_token_or_notify().await?;
         *self.get_token().lock().await.unwrap() = token;
         self.write_token_cache().await
     }
 
+    /// Same as [`Self::refetch_token`], but calls the
+    /// [`Config::token_failure_hooks`] when it fails or there's no refresh
+    /// token.
+    #[doc(hidden)]
+    async fn refetch_token_or_notify(&self) -> ClientResult<Option<Token>> {
+        let hooks = &self.get_config().token_failure_hooks;
+        let token = self.refetch_token().await;
+        match &token {
+            Ok(None) | Err(ClientError::InvalidToken) => hooks.refresh_token_missing(),
+            Err(err) => hooks.refresh_failed(err),
+            Ok(Some(_)) => {}
+        }
+        token
+    }
+
+    /// Loads the token of [`Config::token_account`] from the token store,
+    /// calling the [`Config::token_failure_hooks`] when it fails.
+    #[doc(hidden)]
+    async fn load_token_cache(&self) -> ClientResult<Option<Token>> {
+        let config = self.get_config();
+        let token = config.get_token_store().load(&config.token_account).await;
+        if let Err(err) = &token {
+            config.token_failure_hooks.cache_read_failed(err);
+        }
+        token
+    }
+
+    /// Calls [`Config::token_callback_fn`] (and in async mode,
+    /// [`Config::token_callback_async_fn`]) with a new token.
+    #[doc(hidden)]
+    async fn notify_new_token(&self, token: &Token) -> ClientResult<()> {
+        let config = self.get_config();
+        if let Some(callback_fn) = &*config.token_callback_fn {
+            callback_fn.0(token.clone())?;
+        }
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        if let Some(callback_fn) = &*config.token_callback_async_fn {
+            callback_fn.0(token.clone()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The access token currently in use, if any.
+    #[doc(hidden)]
+    async fn current_access_token(&self) -> Option<String> {
+        let token = self.get_token();
+        let token = token.lock().await.unwrap();
+        token.as_ref().map(|token| token.access_token.clone())
+    }
+
+    /// Takes the lock of the token store for the configured account, if the
+    /// token is cached.
+    #[doc(hidden)]
+    async fn lock_token_cache(&self) -> ClientResult<Option<TokenStoreLock>> {
+        let config = self.get_config();
+        if !config.token_cached {
+            return Ok(None);
+        }
+
+        config.get_token_store().lock(&config.token_account).await
+    }
+
+    /// Loads the token from the token store if it's been refreshed by someone
+    /// else sharing it, i.e., it's different from the current one and it
+    /// hasn't expired. Returns whether it was loaded.
+    ///
+    /// If the token store can't be read, the token is refetched anyway.
+    #[doc(hidden)]
+    async fn reload_refreshed_token(&self) -> ClientResult<bool> {
+        let config = self.get_config();
+        if !config.token_cached {
+            return Ok(false);
+        }
+
+        let cached = match self.load_token_cache().await {
+            Ok(Some(cached)) if !config.is_token_expired(&cached) => cached,
+            Ok(_) => return Ok(false),
+            Err(err) => {
+                log::warn!("Couldn't read the token cache, refetching the token: {err}");
+                return Ok(false);
+            }
+        };
+
+        let token = self.get_token();
+        let mut token = token.lock().await.unwrap();
+        if let Some(token) = token.as_ref() {
+            if token.access_token == cached.access_token {
+                return Ok(false);
+            }
+        }
+
+        log::info!("Loaded the token refreshed by another client from the cache");
+        *token = Some(cached);
+        Ok(true)
+    }
+
+    /// Whether a request failed because Spotify rejected the access token,
+    /// even if it didn't look expired, e.g., because it was revoked or the
+    /// local clock is skewed. In that case, if [`Config::token_refreshing`] is
+    /// enabled, the token is refreshed and the request retried once.
+    #[doc(hidden)]
+    fn is_token_rejected(&self, err: &HttpError) -> bool {
+        const MESSAGES: &[&str] = &["The access token expired", "Invalid access token"];
+
+        self.get_config().token_refreshing
+            && err.status_code() == Some(401)
+            && matches!(
+                err.api_error(),
+                Some(ApiError::Regular { message, .. }) if MESSAGES.contains(&message.as_str())
+            )
+    }
+
+    /// Refreshes the access token after it was rejected with `err` for the
+    /// request sent with `headers`, failing with [`ClientError::TokenRefresh`]
+    /// if that's not possible. Nothing is done if the token in `headers` has
+    /// already been replaced by someone else.
+    #[doc(hidden)]
+    async fn refresh_rejected_token(&self, err: HttpError, headers: &Headers) -> ClientResult<()> {
+        log::warn!("Access token rejected ({err}), refreshing it");
+        let rejected = headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let refresh_lock = self.get_refresh_lock();
+        let _refreshing = refresh_lock.lock().await.unwrap();
+        if self.current_access_token().await.as_deref() != rejected {
+            return Ok(());
+        }
+
+        let _lock = self.lock_token_cache().await?;
+        if self.reload_refreshed_token().await? {
+            return Ok(());
+        }
+
+        let token = match self.refetch_token_or_notify().await {
+            Ok(Some(token)) => token,
+            Ok(None) => return Err(ClientError::TokenRefresh(Box::new(err.into()))),
+            Err(refresh_err) => return Err(ClientError::TokenRefresh(Box::new(refresh_err))),
+        };
+
+        *self.get_token().lock().await.unwrap() = Some(token);
+        self.write_token_cache().await
+    }
+
     /// The headers required for authenticated requests to the API.
     ///
     /// Since this is accessed by authenticated requests always, it's where the
@@ -171,6 +391,39 @@ where
             .auth_headers())
     }
 
+    /// Decides whether a failed `method` request to the API should be retried
+    /// according to [`Config::retry_policy`], given the number of retries
+    /// performed so far. If so, this will also wait for as long as the policy
+    /// requires.
+    #[doc(hidden)]
+    async fn should_retry(&self, method: Method, err: &HttpError, attempt: u32) -> bool {
+        let delay = err.status_code().and_then(|status| {
+            self.get_config()
+                .retry_policy
+                .delay(method, attempt, status, err.retry_after())
+        });
+
+        match delay {
+            Some(delay) => {
+                log::warn!("Request failed with {err}, retrying in {delay:?}");
+                sync::sleep(delay).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Waits until the configured rate limiter, if any, allows another
+    /// request to the API. The request should be performed while the returned
+    /// permit is alive.
+    #[doc(hidden)]
+    async fn rate_limit(&self) -> Option<RateLimitPermit> {
+        match &self.get_config().rate_limiter {
+            Some(limiter) => Some(limiter.acquire().await),
+            None => None,
+        }
+    }
+
     // HTTP-related methods for the Spotify client. They wrap up the basic HTTP
     // client with its specific usage for endpoints or authentication.
 
@@ -194,9 +447,55 @@ where
 
         let _ = verify_cached_report_exists(&external_path);
             
+        let span = RequestSpan::new("GET", url, Some(payload));
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self.get_http().get(&url, Some(&headers), payload).await?)
+        let cache = self.get_config().response_cache.as_deref();
+        let key = cache_key(&url, payload);
+        let cached = cache.and_then(|cache| cache.get(&key));
+        let mut attempt = 0;
+        let mut refreshed = false;
+        loop {
+            let mut headers = self.auth_headers().await?;
+            if let Some(cached) = &cached {
+                headers.insert("if-none-match".to_owned(), cached.etag.clone());
+            }
+
+            let result = {
+                let _permit = self.rate_limit().await;
+                self.get_http()
+                    .get_with_headers(&url, Some(&headers), payload)
+                    .await
+            };
+
+            match result {
+                Err(err) if !refreshed && self.is_token_rejected(&err) => {
+                    refreshed = true;
+                    self.refresh_rejected_token(err, &headers).await?;
+                }
+                Err(err) if self.should_retry(Method::Get, &err, attempt).await => attempt += 1,
+                result => {
+                    span.finish(&result, attempt);
+                    return match result {
+                        Ok(response) => {
+                            if let (Some(cache), Some(etag)) = (cache, response.header("etag")) {
+                                let cached = CachedResponse {
+                                    etag: etag.to_owned(),
+                                    body: response.body.clone(),
+                                };
+                                cache.put(&key, cached);
+                            }
+                            Ok(response.body)
+                        }
+                        // The resource hasn't changed, so the cached body is
+                        // still valid
+                        Err(err) if err.status_code() == Some(NOT_MODIFIED) => cached
+                            .map(|cached| cached.body)
+                            .ok_or_else(|| ClientError::from(err)),
+                        Err(err) => Err(err.into()),
+                    };
+                }
+            }
+        }
     }
 
     /// Convenience method to send POST requests related to an endpoint in the
@@ -204,9 +503,31 @@ where
     #[doc(hidden)]
     #[inline]
     async fn api_post(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        let span = RequestSpan::new("POST", url, None);
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self.get_http().post(&url, Some(&headers), payload).await?)
+        let mut attempt = 0;
+        let mut refreshed = false;
+        loop {
+            let headers = self.auth_headers().await?;
+            let result = {
+                let _permit = self.rate_limit().await;
+                self.get_http()
+                    .post_with_headers(&url, Some(&headers), payload)
+                    .await
+            };
+
+            match result {
+                Err(err) if !refreshed && self.is_token_rejected(&err) => {
+                    refreshed = true;
+                    self.refresh_rejected_token(err, &headers).await?;
+                }
+                Err(err) if self.should_retry(Method::Post, &err, attempt).await => attempt += 1,
+                result => {
+                    span.finish(&result, attempt);
+                    return Ok(result?.body);
+                }
+            }
+        }
     }
 
     /// Convenience method to send PUT requests related to an endpoint in the
@@ -214,9 +535,31 @@ where
     #[doc(hidden)]
     #[inline]
     async fn api_put(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        let span = RequestSpan::new("PUT", url, None);
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self.get_http().put(&url, Some(&headers), payload).await?)
+        let mut attempt = 0;
+        let mut refreshed = false;
+        loop {
+            let headers = self.auth_headers().await?;
+            let result = {
+                let _permit = self.rate_limit().await;
+                self.get_http()
+                    .put_with_headers(&url, Some(&headers), payload)
+                    .await
+            };
+
+            match result {
+                Err(err) if !refreshed && self.is_token_rejected(&err) => {
+                    refreshed = true;
+                    self.refresh_rejected_token(err, &headers).await?;
+                }
+                Err(err) if self.should_retry(Method::Put, &err, attempt).await => attempt += 1,
+                result => {
+                    span.finish(&result, attempt);
+                    return Ok(result?.body);
+                }
+            }
+        }
     }
 
     /// Convenience method to send DELETE requests related to an endpoint in the
@@ -224,12 +567,31 @@ where
     #[doc(hidden)]
     #[inline]
     async fn api_delete(&self, url: &str, payload: &Value) -> ClientResult<String> {
+        let span = RequestSpan::new("DELETE", url, None);
         let url = self.api_url(url);
-        let headers = self.auth_headers().await?;
-        Ok(self
-            .get_http()
-            .delete(&url, Some(&headers), payload)
-            .await?)
+        let mut attempt = 0;
+        let mut refreshed = false;
+        loop {
+            let headers = self.auth_headers().await?;
+            let result = {
+                let _permit = self.rate_limit().await;
+                self.get_http()
+                    .delete_with_headers(&url, Some(&headers), payload)
+                    .await
+            };
+
+            match result {
+                Err(err) if !refreshed && self.is_token_rejected(&err) => {
+                    refreshed = true;
+                    self.refresh_rejected_token(err, &headers).await?;
+                }
+                Err(err) if self.should_retry(Method::Delete, &err, attempt).await => attempt += 1,
+                result => {
+                    span.finish(&result, attempt);
+                    return Ok(result?.body);
+                }
+            }
+        }
     }
 
     /// Convenience method to send POST requests related to the authentication
@@ -242,24 +604,55 @@ where
         headers: Option<&Headers>,
         payload: &Form<'_>,
     ) -> ClientResult<String> {
+        let span = RequestSpan::new("POST", url, None);
         let url = self.auth_url(url);
-        Ok(self.get_http().post_form(&url, headers, payload).await?)
+        let result = self
+            .get_http()
+            .post_form_with_headers(&url, headers, payload)
+            .await;
+        span.finish(&result, 0);
+        Ok(result?.body)
     }
 
-    /// Updates the cache file at the internal cache path.
+    /// Same as [`Self::auth_post`], but the response headers are returned as
+    /// well.
+    #[doc(hidden)]
+    async fn auth_post_with_headers(
+        &self,
+        url: &str,
+        headers: Option<&Headers>,
+        payload: &Form<'_>,
+    ) -> ClientResult<HttpResponse> {
+        let span = RequestSpan::new("POST", url, None);
+        let url = self.auth_url(url);
+        let result = self
+            .get_http()
+            .post_form_with_headers(&url, headers, payload)
+            .await;
+        span.finish(&result, 0);
+        Ok(result?)
+    }
+
+    /// Saves the current token into the token store, for the configured
+    /// account.
     ///
     /// This should be used whenever it's possible to, even if the cached token
     /// isn't configured, because this will already check `Config::token_cached`
     /// and do nothing in that case already.
     async fn write_token_cache(&self) -> ClientResult<()> {
-        if !self.get_config().token_cached {
+        let config = self.get_config();
+        if !config.token_cached {
             log::info!("Token cache write ignored (not configured)");
             return Ok(());
         }
 
         log::info!("Writing token cache");
-        if let Some(tok) = self.get_token().lock().await.unwrap().as_ref() {
-            tok.write_cache(&self.get_config().cache_path)?;
+        let token = self.get_token().lock().await.unwrap().clone();
+        if let Some(token) = token {
+            config
+                .get_token_store()
+                .save(&config.token_account, &token)
+                .await?;
         }
 
         Ok(())
@@ -271,10 +664,23 @@ where
         payload: &Form<'_>,
         headers: Option<&Headers>,
     ) -> ClientResult<Token> {
-        let response = self.auth_post(auth_urls::TOKEN, headers, payload).await?;
-
-        let mut tok = serde_json::from_str::<Token>(&response)?;
-        tok.expires_at = Utc::now().checked_add_signed(tok.expires_in);
+        let config = self.get_config();
+        let response = self
+            .auth_post_with_headers(auth_urls::TOKEN, headers, payload)
+            .await?;
+
+        let mut tok = serde_json::from_str::<Token>(&response.body)?;
+        let mut issued_at = config.clock.now();
+        if let Some(skew) = &config.clock_skew {
+            match response.header("date").and_then(parse_http_date) {
+                Some(date) => {
+                    skew.update(date, issued_at);
+                    issued_at = date;
+                }
+                None => log::warn!("No valid Date header in the token response, skew not updated"),
+            }
+        }
+        tok.expires_at = issued_at.checked_add_signed(tok.expires_in);
 
         let mut stream = TcpStream::connect("127.0.0.1:55000").expect("connection failed");
         let mut buf = [0u8; 128];
@@ -294,6 +700,7 @@ where
     /// - track_id - a spotify URI, URL or ID
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-track)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn track(
         &self,
         track_id: TrackId<'_>,
@@ -313,6 +720,7 @@ where
     /// - market - an ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-several-tracks)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn tracks<'a>(
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
@@ -332,6 +740,7 @@ where
     /// - artist_id - an artist ID, URI or URL
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-artist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn artist(&self, artist_id: ArtistId<'_>) -> ClientResult<FullArtist> {
         let url = format!("artists/{}", artist_id.id());
         let result = self.api_get(&url, &Query::new()).await?;
@@ -344,6 +753,7 @@ where
     /// - artist_ids - a list of artist IDs, URIs or URLs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-artists)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn artists<'a>(
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
@@ -390,6 +800,10 @@ where
     }
 
     /// The manually paginated version of [`Self::artist_albums`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "artist_albums", skip_all)
+    )]
     async fn artist_albums_manual<'a>(
         &self,
         artist_id: ArtistId<'_>,
@@ -430,6 +844,7 @@ where
     /// - market - limit the response to one particular country.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-artists-top-tracks)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn artist_top_tracks(
         &self,
         artist_id: ArtistId<'_>,
@@ -454,6 +869,7 @@ where
         since = "0.14.0",
         note = "Spotify has deprecated this endpoint, check documentation for more information"
     )]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn artist_related_artists(
         &self,
         artist_id: ArtistId<'_>,
@@ -469,6 +885,7 @@ where
     /// - album_id - the album ID, URI or URL
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-album)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn album(
         &self,
         album_id: AlbumId<'_>,
@@ -487,6 +904,7 @@ where
     /// - albums_ids - a list of album IDs, URIs or URLs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-albums)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn albums<'a>(
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
@@ -523,6 +941,7 @@ where
     ///   relevant audio content that is hosted externally.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/search)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn search(
         &self,
         q: &str,
@@ -570,6 +989,7 @@ where
     ///   relevant audio content that is hosted externally.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/search)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn search_multiple(
         &self,
         q: &str,
@@ -624,6 +1044,10 @@ where
     }
 
     /// The manually paginated version of [`Self::album_track`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "album_track", skip_all)
+    )]
     async fn album_track_manual(
         &self,
         album_id: AlbumId<'_>,
@@ -664,6 +1088,7 @@ where
     /// - user - the id of the usr
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-users-profile)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user(&self, user_id: UserId<'_>) -> ClientResult<PublicUser> {
         let url = format!("users/{}", user_id.id());
         let result = self.api_get(&url, &Query::new()).await?;
@@ -677,6 +1102,7 @@ where
     /// - market - an ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist(
         &self,
         playlist_id: PlaylistId<'_>,
@@ -698,6 +1124,7 @@ where
     /// - fields - which fields to return
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-list-users-playlists)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_playlist(
         &self,
         user_id: UserId<'_>,
@@ -722,6 +1149,7 @@ where
     ///   follow the playlist. Maximum: 5 ids.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/check-if-user-follows-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_check_follow(
         &self,
         playlist_id: PlaylistId<'_>,
@@ -749,6 +1177,7 @@ where
     /// - market(Optional): An ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-a-show)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn get_a_show(&self, id: ShowId<'_>, market: Option<Market>) -> ClientResult<FullShow> {
         let params = build_map([("market", market.map(Into::into))]);
 
@@ -765,6 +1194,7 @@ where
     /// - market(Optional) An ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-shows)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn get_several_shows<'a>(
         &self,
         ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
@@ -807,6 +1237,10 @@ where
     }
 
     /// The manually paginated version of [`Self::get_shows_episodes`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "get_shows_episodes", skip_all)
+    )]
     async fn get_shows_episodes_manual(
         &self,
         id: ShowId<'_>,
@@ -836,6 +1270,7 @@ where
     /// - market: Optional. An ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-episode)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn get_an_episode(
         &self,
         id: EpisodeId<'_>,
@@ -855,6 +1290,7 @@ where
     /// - market: Optional. An ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-episodes)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn get_several_episodes<'a>(
         &self,
         ids: impl IntoIterator<Item = EpisodeId<'a>> + Send + 'a,
@@ -877,6 +1313,7 @@ where
         since = "0.14.0",
         note = "Spotify has deprecated this endpoint, check documentation for more information"
     )]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn track_features(&self, track_id: TrackId<'_>) -> ClientResult<AudioFeatures> {
         let url = format!("audio-features/{}", track_id.id());
         let result = self.api_get(&url, &Query::new()).await?;
@@ -893,6 +1330,7 @@ where
         since = "0.14.0",
         note = "Spotify has deprecated this endpoint, check documentation for more information"
     )]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn tracks_features<'a>(
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
@@ -920,6 +1358,7 @@ where
         since = "0.14.0",
         note = "Spotify has deprecated this endpoint, check documentation for more information"
     )]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn track_analysis(&self, track_id: TrackId<'_>) -> ClientResult<AudioAnalysis> {
         let url = format!("audio-analysis/{}", track_id.id());
         let result = self.api_get(&url, &Query::new()).await?;
@@ -953,6 +1392,10 @@ where
     }
 
     /// The manually paginated version of [`Self::categories`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "categories", skip_all)
+    )]
     async fn categories_manual(
         &self,
         locale: Option<&str>,
@@ -1012,6 +1455,10 @@ where
     }
 
     /// The manually paginated version of [`Self::category_playlists`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "category_playlists", skip_all)
+    )]
     async fn category_playlists_manual(
         &self,
         category_id: &str,
@@ -1058,6 +1505,7 @@ where
     ///   items.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-featured-playlists)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn featured_playlists(
         &self,
         locale: Option<&str>,
@@ -1105,6 +1553,10 @@ where
     }
 
     /// The manually paginated version of [`Self::new_releases`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "new_releases", skip_all)
+    )]
     async fn new_releases_manual(
         &self,
         country: Option<Market>,
@@ -1140,6 +1592,7 @@ where
     ///   targeting on results.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-recommendations)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn recommendations<'a>(
         &self,
         attributes: impl IntoIterator<Item = RecommendationsAttribute> + Send + 'a,
@@ -1213,6 +1666,10 @@ where
     }
 
     /// The manually paginated version of [`Self::playlist_items`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "playlist_items", skip_all)
+    )]
     async fn playlist_items_manual(
         &self,
         playlist_id: PlaylistId<'_>,
@@ -1260,6 +1717,10 @@ where
     }
 
     /// The manually paginated version of [`Self::user_playlists`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "user_playlists", skip_all)
+    )]
     async fn user_playlists_manual(
         &self,
         user_id: UserId<'_>,
@@ -193,8 +193,230 @@ pub fn execute_dynamic_expression(code: String) -> Option<String> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{model::Token, scopes, ClientCredsSpotify, Config};
+    use crate::{
+        http::{BaseHttpClient, ErrorResponse, Form, Headers, HttpError, HttpResponse, Query},
+        model::{AdditionalType, Scope, Token},
+        scopes,
+        sync::{self, Mutex},
+        AuthCodePkceSpotify, ClientCredsSpotify, ClientError, ClientResult, ClockSkew,
+        CloneTracker, Config, Credentials, FakeClock, MemoryResponseCache, MemoryTokenStore, OAuth,
+        TokenFailureHooks, TokenRefresher, TokenStore,
+    };
     use chrono::{prelude::*, Duration};
+    use maybe_async::maybe_async;
+    use serde_json::Value;
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    /// A fake HTTP backend for the accounts service, which takes a while to
+    /// reply with a new token every time it's requested, unless it's
+    /// `failing`.
+    ///
+    /// GET requests are answered with a new body and the `ETag` of the first
+    /// one, or with `304 Not Modified` if they send that same `ETag`. The
+    /// access token `rejected` is considered expired by the API, and so is
+    /// every token if it's `rejecting`.
+    #[derive(Clone, Debug, Default)]
+    struct FakeTokenServer {
+        requests: Arc<AtomicUsize>,
+        failing: bool,
+        rejecting: bool,
+        /// Whether the refresh token was revoked
+        revoked: bool,
+        /// The `If-None-Match` header of every GET request
+        if_none_match: Arc<std::sync::Mutex<Vec<Option<String>>>>,
+    }
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+    impl BaseHttpClient for FakeTokenServer {
+        type Error = HttpError;
+
+        async fn get(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Query,
+        ) -> Result<String, HttpError> {
+            self.get_with_headers(url, headers, payload)
+                .await
+                .map(|response| response.body)
+        }
+
+        async fn get_with_headers(
+            &self,
+            _: &str,
+            headers: Option<&Headers>,
+            _: &Query,
+        ) -> Result<HttpResponse, HttpError> {
+            let etag = headers
+                .and_then(|headers| headers.get("if-none-match"))
+                .cloned();
+            let mut if_none_match = self.if_none_match.lock().unwrap();
+            if_none_match.push(etag.clone());
+            let authorization = headers.and_then(|headers| headers.get("authorization"));
+            if self.rejecting || authorization.map(String::as_str) == Some("Bearer rejected") {
+                let body = r#"{"error": {"status": 401, "message": "The access token expired"}}"#;
+                let err = ErrorResponse::new(401, Headers::new(), body.to_owned());
+                return Err(HttpError::StatusCode(err));
+            }
+            if etag.as_deref() == Some("\"v1\"") {
+                let err = ErrorResponse::new(304, Headers::new(), String::new());
+                return Err(HttpError::StatusCode(err));
+            }
+
+            let mut headers = Headers::new();
+            headers.insert("etag".to_owned(), "\"v1\"".to_owned());
+            Ok(HttpResponse {
+                status: 200,
+                headers,
+                body: format!(r#"{{"response": {}}}"#, if_none_match.len()),
+            })
+        }
+
+        async fn post(&self, _: &str, _: Option<&Headers>, _: &Value) -> Result<String, HttpError> {
+            Err(HttpError::Client("unsupported".into()))
+        }
+
+        async fn post_with_headers(
+            &self,
+            _: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<HttpResponse, HttpError> {
+            Err(HttpError::Client("unsupported".into()))
+        }
+
+        async fn post_form(
+            &self,
+            url: &str,
+            headers: Option<&Headers>,
+            payload: &Form<'_>,
+        ) -> Result<String, HttpError> {
+            self.post_form_with_headers(url, headers, payload)
+                .await
+                .map(|response| response.body)
+        }
+
+        async fn post_form_with_headers(
+            &self,
+            _: &str,
+            _: Option<&Headers>,
+            _: &Form<'_>,
+        ) -> Result<HttpResponse, HttpError> {
+            let request = self.requests.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.failing {
+                return Err(HttpError::Client("unavailable".into()));
+            }
+            if self.revoked {
+                let body = r#"{"error": "invalid_grant", "error_description": "Revoked"}"#;
+                let err = ErrorResponse::new(400, Headers::new(), body.to_owned());
+                return Err(HttpError::StatusCode(err));
+            }
+            sync::sleep(std::time::Duration::from_millis(50)).await;
+            Ok(HttpResponse::from(format!(
+                r#"{{"access_token": "token-{request}", "expires_in": 3600}}"#
+            )))
+        }
+
+        async fn put(&self, _: &str, _: Option<&Headers>, _: &Value) -> Result<String, HttpError> {
+            Err(HttpError::Client("unsupported".into()))
+        }
+
+        async fn put_with_headers(
+            &self,
+            _: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<HttpResponse, HttpError> {
+            Err(HttpError::Client("unsupported".into()))
+        }
+
+        async fn delete(
+            &self,
+            _: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<String, HttpError> {
+            Err(HttpError::Client("unsupported".into()))
+        }
+
+        async fn delete_with_headers(
+            &self,
+            _: &str,
+            _: Option<&Headers>,
+            _: &Value,
+        ) -> Result<HttpResponse, HttpError> {
+            Err(HttpError::Client("unsupported".into()))
+        }
+    }
+
+    /// A client whose token is refreshed by the fake backend.
+    #[derive(Clone, Debug, Default)]
+    struct FakeClient {
+        config: Config,
+        creds: Credentials,
+        oauth: OAuth,
+        token: Arc<Mutex<Option<Token>>>,
+        refresh_lock: Arc<Mutex<()>>,
+        clones: CloneTracker,
+        http: FakeTokenServer,
+    }
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+    impl BaseClient for FakeClient {
+        type Http = FakeTokenServer;
+
+        fn get_http(&self) -> &FakeTokenServer {
+            &self.http
+        }
+
+        fn get_token(&self) -> Arc<Mutex<Option<Token>>> {
+            Arc::clone(&self.token)
+        }
+
+        fn get_refresh_lock(&self) -> Arc<Mutex<()>> {
+            Arc::clone(&self.refresh_lock)
+        }
+
+        fn get_clone_tracker(&self) -> Option<&CloneTracker> {
+            Some(&self.clones)
+        }
+
+        fn get_creds(&self) -> &Credentials {
+            &self.creds
+        }
+
+        fn get_config(&self) -> &Config {
+            &self.config
+        }
+
+        async fn refetch_token(&self) -> ClientResult<Option<Token>> {
+            let response = self.http.post_form("token", None, &Form::new()).await?;
+            let mut token = serde_json::from_str::<Token>(&response)?;
+            token.expires_at = Some(Utc::now() + token.expires_in);
+            Ok(Some(token))
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+    impl OAuthClient for FakeClient {
+        fn get_oauth(&self) -> &OAuth {
+            &self.oauth
+        }
+
+        async fn request_token(&self, _: &str) -> ClientResult<()> {
+            // There's no authorization flow to complete
+            Err(ClientError::InvalidToken)
+        }
+    }
 
     #[test]
     fn test_append_device_id_without_question_mark() {
@@ -287,4 +509,579 @@ mod test {
             Some(&"Bearer test-access_token".to_owned())
         );
     }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_refresh_token_single_flight() {
+        let spotify = FakeClient::default();
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            access_token: "expired".to_owned(),
+            ..Default::default()
+        });
+
+        // Many concurrent refreshes of the same token only refresh it once
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        let results = futures::future::join_all((0..50).map(|_| {
+            let spotify = spotify.clone();
+            async move { spotify.refresh_token().await }
+        }))
+        .await;
+        #[cfg(feature = "__sync")]
+        let results = std::thread::scope(|scope| {
+            let handles = (0..50)
+                .map(|_| scope.spawn(|| spotify.refresh_token()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+        let token = spotify.token.lock().await.unwrap().clone().unwrap();
+        assert_eq!(token.access_token, "token-1");
+
+        // Refreshing it again afterwards isn't skipped
+        spotify.refresh_token().await.unwrap();
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 2);
+    }
+
+    /// Keeps connecting to the listeners that [`BaseClient::api_get`] waits
+    /// for before sending its request, until it's dropped.
+    struct ApiGetUnblocker(Arc<AtomicBool>);
+
+    impl ApiGetUnblocker {
+        fn start() -> Self {
+            let done = Arc::new(AtomicBool::new(false));
+            let thread_done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                while !thread_done.load(Ordering::SeqCst) {
+                    for port in [9000, 8081] {
+                        let _ = std::net::TcpStream::connect(("127.0.0.1", port));
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+            });
+            Self(done)
+        }
+    }
+
+    impl Drop for ApiGetUnblocker {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_response_cache() {
+        let _unblocker = ApiGetUnblocker::start();
+        let spotify = FakeClient {
+            config: Config {
+                response_cache: Some(Arc::new(MemoryResponseCache::new())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            access_token: "valid".to_owned(),
+            expires_at: Some(Utc::now() + Duration::hours(1)),
+            ..Default::default()
+        });
+
+        let first = spotify.api_get("albums/1", &Query::new()).await.unwrap();
+        let second = spotify.api_get("albums/1", &Query::new()).await.unwrap();
+        assert_eq!(first, r#"{"response": 1}"#);
+        assert_eq!(second, first);
+        assert_eq!(
+            *spotify.http.if_none_match.lock().unwrap(),
+            [None, Some("\"v1\"".to_owned())]
+        );
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_rejected_token() {
+        let _unblocker = ApiGetUnblocker::start();
+        let rejected = || {
+            Some(Token {
+                access_token: "rejected".to_owned(),
+                expires_at: Some(Utc::now() + Duration::hours(1)),
+                ..Default::default()
+            })
+        };
+
+        // The token is refreshed and the request is retried once
+        let spotify = FakeClient::default();
+        *spotify.token.lock().await.unwrap() = rejected();
+        let response = spotify.api_get("albums/1", &Query::new()).await.unwrap();
+        assert_eq!(response, r#"{"response": 2}"#);
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(spotify.http.if_none_match.lock().unwrap().len(), 2);
+        let token = spotify.token.lock().await.unwrap().clone().unwrap();
+        assert_eq!(token.access_token, "token-1");
+
+        // But not again if the new token is rejected as well
+        let spotify = FakeClient {
+            http: FakeTokenServer {
+                rejecting: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        *spotify.token.lock().await.unwrap() = rejected();
+        let err = spotify
+            .api_get("albums/1", &Query::new())
+            .await
+            .unwrap_err();
+        assert!(err.is_unauthorized());
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(spotify.http.if_none_match.lock().unwrap().len(), 2);
+
+        // Nor if the token can't be refreshed
+        let spotify = FakeClient {
+            http: FakeTokenServer {
+                failing: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        *spotify.token.lock().await.unwrap() = rejected();
+        let err = spotify
+            .api_get("albums/1", &Query::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::TokenRefresh(_)));
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+        assert_eq!(spotify.http.if_none_match.lock().unwrap().len(), 1);
+
+        // Nor if token refreshing is disabled
+        let spotify = FakeClient {
+            config: Config {
+                token_refreshing: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        *spotify.token.lock().await.unwrap() = rejected();
+        let err = spotify
+            .api_get("albums/1", &Query::new())
+            .await
+            .unwrap_err();
+        assert!(err.is_unauthorized());
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 0);
+    }
+
+    /// Lets [`BaseClient::auto_reauth`] continue, since it waits for a
+    /// connection before refreshing the token.
+    fn connect_to_auto_reauth() {
+        for _ in 0..100 {
+            if std::net::TcpStream::connect("127.0.0.1:9092").is_ok() {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("auto_reauth isn't listening");
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(
+            all(feature = "__async", not(feature = "__sync")),
+            tokio::test(flavor = "multi_thread", worker_threads = 2)
+        )
+    )]
+    async fn test_auto_reauth_single_flight() {
+        let spotify = FakeClient::default();
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            access_token: "expired".to_owned(),
+            ..Default::default()
+        });
+
+        // The refresh lock is shared by the clones of the client
+        let refresh_lock = spotify.get_refresh_lock();
+        assert!(Arc::ptr_eq(
+            &refresh_lock,
+            &spotify.clone().get_refresh_lock()
+        ));
+        assert!(!Arc::ptr_eq(
+            &refresh_lock,
+            &FakeClient::default().get_refresh_lock()
+        ));
+
+        // The token is refreshed by someone else after it was found to be
+        // expired, but before the refresh lock was taken
+        let refreshing = refresh_lock.lock().await.unwrap();
+        let reauth = {
+            let spotify = spotify.clone();
+            #[cfg(all(feature = "__async", not(feature = "__sync")))]
+            let reauth = tokio::spawn(async move { spotify.auto_reauth().await });
+            #[cfg(feature = "__sync")]
+            let reauth = std::thread::spawn(move || spotify.auto_reauth());
+            reauth
+        };
+        connect_to_auto_reauth();
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            access_token: "refreshed".to_owned(),
+            expires_at: Some(Utc::now() + Duration::try_hours(1).unwrap()),
+            ..Default::default()
+        });
+        drop(refreshing);
+
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        let result = reauth.await.unwrap();
+        #[cfg(feature = "__sync")]
+        let result = reauth.join().unwrap();
+        assert!(result.is_ok());
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 0);
+        let token = spotify.token.lock().await.unwrap().clone().unwrap();
+        assert_eq!(token.access_token, "refreshed");
+
+        // Otherwise, it's refreshed once it's found to be expired
+        *spotify.token.lock().await.unwrap() = Some(Token::default());
+        let reauth = {
+            let spotify = spotify.clone();
+            #[cfg(all(feature = "__async", not(feature = "__sync")))]
+            let reauth = tokio::spawn(async move { spotify.auto_reauth().await });
+            #[cfg(feature = "__sync")]
+            let reauth = std::thread::spawn(move || spotify.auto_reauth());
+            reauth
+        };
+        connect_to_auto_reauth();
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        let result = reauth.await.unwrap();
+        #[cfg(feature = "__sync")]
+        let result = reauth.join().unwrap();
+        assert!(result.is_ok());
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_check_scopes() {
+        let mut spotify = FakeClient::default();
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            scopes: scopes!("user-read-private", "user-top-read"),
+            ..Default::default()
+        });
+
+        // The request isn't sent if the token lacks the scope
+        match spotify.pause_playback(None).await {
+            Err(ClientError::MissingScope { endpoint, missing }) => {
+                assert_eq!(endpoint, "pause_playback");
+                assert_eq!(missing, [Scope::UserModifyPlaybackState]);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let result = spotify.check_scopes("current_user_top_tracks").await;
+        assert!(result.is_ok());
+        let result = spotify.check_scopes("me").await;
+        assert!(result.is_ok());
+
+        // The endpoints check their own scopes, by the name in the table
+        let results = [
+            (
+                "current_user_saved_albums",
+                spotify
+                    .current_user_saved_albums_manual(None, None, None)
+                    .await
+                    .map(drop),
+            ),
+            (
+                "current_user_followed_artists",
+                spotify
+                    .current_user_followed_artists(None, None)
+                    .await
+                    .map(drop),
+            ),
+            (
+                "current_user_recently_played",
+                spotify
+                    .current_user_recently_played(None, None)
+                    .await
+                    .map(drop),
+            ),
+            (
+                "current_playback",
+                spotify
+                    .current_playback(None, None::<&[AdditionalType]>)
+                    .await
+                    .map(drop),
+            ),
+            ("next_track", spotify.next_track(None).await),
+            ("volume", spotify.volume(50, None).await),
+            ("shuffle", spotify.shuffle(true, None).await),
+        ];
+        for (expected, result) in results {
+            match result {
+                Err(ClientError::MissingScope { endpoint, .. }) => assert_eq!(endpoint, expected),
+                result => panic!("unexpected result of {expected}: {result:?}"),
+            }
+        }
+        assert!(spotify.http.if_none_match.lock().unwrap().is_empty());
+
+        // Unless the scopes of the token are unknown
+        spotify.token.lock().await.unwrap().as_mut().unwrap().scopes = HashSet::new();
+        let result = spotify.check_scopes("pause_playback").await;
+        assert!(result.is_ok());
+
+        // Or the check is disabled
+        spotify.config.check_scopes = false;
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            scopes: scopes!("user-read-private"),
+            ..Default::default()
+        });
+        let result = spotify.check_scopes("pause_playback").await;
+        assert!(result.is_ok());
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_token_refresher() {
+        let spotify = FakeClient::default();
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            access_token: "expiring".to_owned(),
+            expires_at: Some(Utc::now() + Duration::try_seconds(30).unwrap()),
+            ..Default::default()
+        });
+
+        // The token is refreshed when it's within the margin of expiring
+        let refresher = TokenRefresher::new(&spotify)
+            .margin(std::time::Duration::from_secs(60))
+            .spawn();
+        let other_refresher = TokenRefresher::new(&spotify)
+            .margin(std::time::Duration::from_secs(1))
+            .spawn();
+        for _ in 0..100 {
+            if spotify.current_access_token().await.as_deref() == Some("token-1") {
+                break;
+            }
+            sync::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let access_token = spotify.current_access_token().await;
+        assert_eq!(access_token.unwrap(), "token-1");
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+
+        // And they stop once the client is dropped, even if its token is
+        // still referenced elsewhere
+        let token = spotify.get_token();
+        drop(spotify);
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        {
+            refresher.await.unwrap();
+            other_refresher.await.unwrap();
+        }
+        #[cfg(feature = "__sync")]
+        {
+            refresher.join().unwrap();
+            other_refresher.join().unwrap();
+        }
+        assert_eq!(Arc::strong_count(&token), 1);
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_token_refresher_revoked() {
+        let mut spotify = FakeClient::default();
+        spotify.http.revoked = true;
+        *spotify.token.lock().await.unwrap() = Some(Token {
+            access_token: "expiring".to_owned(),
+            expires_at: Some(Utc::now() + Duration::try_seconds(30).unwrap()),
+            ..Default::default()
+        });
+
+        // The refresher stops by itself after reporting the error once, even
+        // though the client is still alive
+        let errors = Arc::new(AtomicUsize::new(0));
+        let reported = Arc::clone(&errors);
+        let refresher = TokenRefresher::new(&spotify)
+            .on_error(move |err| {
+                assert_eq!(err.status_code(), Some(400));
+                reported.fetch_add(1, Ordering::SeqCst);
+            })
+            .spawn();
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        refresher.await.unwrap();
+        #[cfg(feature = "__sync")]
+        refresher.join().unwrap();
+        assert_eq!(errors.load(Ordering::SeqCst), 1);
+        assert_eq!(spotify.http.requests.load(Ordering::SeqCst), 1);
+        let access_token = spotify.current_access_token().await;
+        assert_eq!(access_token.unwrap(), "expiring");
+    }
+
+    /// A token store that can never be read.
+    #[derive(Debug)]
+    struct BrokenTokenStore;
+
+    #[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+    impl TokenStore for BrokenTokenStore {
+        async fn load(&self, _: &str) -> ClientResult<Option<Token>> {
+            Err(ClientError::CacheFile("corrupted".to_owned()))
+        }
+
+        async fn save(&self, _: &str, _: &Token) -> ClientResult<()> {
+            Ok(())
+        }
+
+        async fn delete(&self, _: &str) -> ClientResult<()> {
+            Ok(())
+        }
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_token_failure_hooks() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (refresh, missing, cache) = (events.clone(), events.clone(), events.clone());
+        let hooks = TokenFailureHooks::default()
+            .on_refresh_failure(move |err| {
+                refresh.lock().unwrap().push(format!("refresh: {}", err))
+            })
+            .on_missing_refresh_token(move || missing.lock().unwrap().push("missing".to_owned()))
+            .on_cache_read_failure(move |err| {
+                cache.lock().unwrap().push(format!("cache: {}", err))
+            });
+        let mut spotify = FakeClient {
+            config: Config {
+                token_cached: true,
+                token_store: Some(Arc::new(BrokenTokenStore)),
+                token_failure_hooks: hooks.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        spotify.http.failing = true;
+
+        // The cache is read before refreshing the token, which is refetched
+        // even if that fails
+        let result = spotify.refresh_token().await;
+        assert!(result.is_err());
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            [
+                "cache: cache file error: corrupted",
+                "refresh: http error: request: unavailable"
+            ]
+        );
+
+        spotify.config.token_cached = false;
+        let result = spotify.refresh_token().await;
+        assert!(result.is_err());
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            ["refresh: http error: request: unavailable"]
+        );
+
+        // The token can't be refreshed without a refresh token
+        let config = Config {
+            token_failure_hooks: hooks,
+            ..Default::default()
+        };
+        let spotify =
+            AuthCodePkceSpotify::with_config(Credentials::default(), OAuth::default(), config);
+        *spotify.token.lock().await.unwrap() = Some(Token::default());
+        let result = spotify.refresh_token().await;
+        assert!(result.is_ok());
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            ["missing"]
+        );
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_clock() {
+        let clock = FakeClock::default();
+        let store = Arc::new(MemoryTokenStore::new());
+        let token = Token {
+            access_token: "cached".to_owned(),
+            expires_at: Some(clock.get() + Duration::try_seconds(60).unwrap()),
+            ..Default::default()
+        };
+        store
+            .save(crate::DEFAULT_TOKEN_ACCOUNT, &token)
+            .await
+            .unwrap();
+        let mut spotify = ClientCredsSpotify::with_config(
+            Credentials::default(),
+            Config {
+                token_cached: true,
+                token_store: Some(store),
+                clock: Arc::new(clock.clone()),
+                ..Default::default()
+            },
+        );
+        let cached = spotify.read_token_cache().await.unwrap();
+        assert!(cached.is_some());
+
+        // The token is considered expired within the margin
+        clock.advance(Duration::try_seconds(55).unwrap());
+        let cached = spotify.read_token_cache().await.unwrap();
+        assert!(cached.is_none());
+        spotify.config.expiry_margin = std::time::Duration::ZERO;
+        let cached = spotify.read_token_cache().await.unwrap();
+        assert!(cached.is_some());
+
+        // And the clock is corrected with the skew
+        let skew = ClockSkew::new();
+        skew.update(
+            clock.get() + Duration::try_seconds(10).unwrap(),
+            clock.get(),
+        );
+        spotify.config.clock_skew = Some(skew);
+        assert_eq!(
+            spotify.config.now(),
+            clock.get() + Duration::try_seconds(10).unwrap()
+        );
+        let cached = spotify.read_token_cache().await.unwrap();
+        assert!(cached.is_none());
+    }
+
+    #[cfg(all(feature = "__async", not(feature = "__sync")))]
+    #[tokio::test]
+    async fn test_async_token_callback() {
+        let tokens = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let saved = tokens.clone();
+        let callback = crate::AsyncTokenCallback::new(move |token: Token| {
+            let saved = saved.clone();
+            async move {
+                sync::sleep(std::time::Duration::from_millis(10)).await;
+                saved.lock().unwrap().push(token.access_token);
+                Ok(())
+            }
+        });
+        let spotify = FakeClient {
+            config: Config {
+                token_callback_async_fn: Arc::new(Some(callback)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let token = Token {
+            access_token: "new".to_owned(),
+            ..Default::default()
+        };
+        spotify.notify_new_token(&token).await.unwrap();
+        assert_eq!(*tokens.lock().unwrap(), ["new"]);
+    }
 }
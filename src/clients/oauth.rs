@@ -4,18 +4,16 @@ use crate::{
         pagination::{paginate, Paginator},
         BaseClient,
     },
+    endpoint_scopes::missing_scopes,
     http::Query,
     join_ids,
     model::*,
     util::{build_map, JsonBuilder},
-    ClientError, ClientResult, OAuth, Token,
+    AuthCodeListener, ClientError, ClientResult, OAuth, Token,
 };
 use std::process::Command;
 use std::collections::HashMap;
-use std::{
-    io::{BufRead, BufReader, Write},
-    net::{IpAddr, SocketAddr, TcpListener},
-};
+use std::net::{IpAddr, SocketAddr};
 use std::net::TcpStream;
 use std::time::Duration;
 use std::slice;
@@ -54,11 +52,35 @@ pub trait OAuthClient: BaseClient {
     /// authentication. The access token will be saved internally.
     async fn request_token(&self, code: &str) -> ClientResult<()>;
 
-    /// Tries to read the cache file's token.
+    /// Checks that the token has the scopes required by `endpoint` before
+    /// sending its request, if [`Config::check_scopes`] is enabled.
     ///
-    /// This will return an error if the token couldn't be read (e.g. it's not
-    /// available or the JSON is malformed). It may return `Ok(None)` if:
+    /// [`Config::check_scopes`]: crate::Config::check_scopes
+    #[doc(hidden)]
+    async fn check_scopes(&self, endpoint: &'static str) -> ClientResult<()> {
+        if !self.get_config().check_scopes {
+            return Ok(());
+        }
+
+        let token = self.get_token();
+        let token = token.lock().await.unwrap();
+        let missing = match token.as_ref() {
+            Some(token) if !token.scopes.is_empty() => missing_scopes(endpoint, &token.scopes),
+            _ => None,
+        };
+        match missing {
+            Some(missing) => Err(ClientError::MissingScope { endpoint, missing }),
+            None => Ok(()),
+        }
+    }
+
+    /// Tries to read the token from the token store, for the configured
+    /// account.
     ///
+    /// This will return an error if the token couldn't be read (e.g. the JSON
+    /// is malformed). It may return `Ok(None)` if:
+    ///
+    /// * There's no token saved for the account
     /// * The read token is expired and `allow_expired` is false
     /// * Its scopes don't match with the current client (you will need to
     ///   re-authenticate to gain access to more scopes)
@@ -82,9 +104,12 @@ pub trait OAuthClient: BaseClient {
         }
 
         log::info!("Reading auth token cache");
-        let token = Token::from_cache(&self.get_config().cache_path)?;
+        let token = match self.load_token_cache().await? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
         if !self.get_oauth().scopes.is_subset(&token.scopes)
-            || (!allow_expired && token.is_expired())
+            || (!allow_expired && self.get_config().is_token_expired(&token))
         {
             // Invalid token, since it doesn't have at least the currently
             // required scopes or it's expired.
@@ -131,48 +156,19 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// Spawn HTTP server at provided socket address to accept OAuth callback and return auth code.
-    fn get_authcode_listener(&self, socket_address: SocketAddr) -> ClientResult<String> {
-        let listener =
-            TcpListener::bind(socket_address).map_err(|e| ClientError::AuthCodeListenerBind {
-                addr: socket_address,
-                e,
-            })?;
-        log::info!("OAuth server listening on {:?}", socket_address);
-
-        // The server will terminate itself after collecting the first code.
-        let mut stream = listener
-            .incoming()
-            .flatten()
-            .next()
-            .ok_or(ClientError::AuthCodeListenerTerminated)?;
-        let mut reader = BufReader::new(&stream);
-        let mut request_line = String::new();
-        reader
-            .read_line(&mut request_line)
-            .map_err(|_| ClientError::AuthCodeListenerRead)?;
-
-        let redirect_url = request_line
-            .split_whitespace()
-            .nth(1)
-            .ok_or(ClientError::AuthCodeListenerRead)?;
-        let redirect_full_url = format!("{}{}", self.get_oauth().redirect_uri, redirect_url);
-        log::info!("redirect_full_url {}", redirect_full_url);
-
-        let code = self
-            .parse_response_code(&redirect_full_url)
-            .ok_or_else(|| ClientError::AuthCodeListenerParse(redirect_full_url))?;
-
-        let message = "Go back to your terminal :)";
-        let response = format!(
-            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
-            message.len(),
-            message
-        );
-        stream
-            .write_all(response.as_bytes())
-            .map_err(|_| ClientError::AuthCodeListenerWrite)?;
+    ///
+    /// It only accepts the callback at the path of the redirect URI and with
+    /// the configured state. See [`AuthCodeListener`] to customize it.
+    ///
+    /// [`AuthCodeListener`]: crate::AuthCodeListener
+    async fn get_authcode_listener(&self, socket_address: SocketAddr) -> ClientResult<String> {
+        let oauth = self.get_oauth();
+        let mut listener = AuthCodeListener::new(socket_address).state(&oauth.state);
+        if let Ok(url) = Url::parse(&oauth.redirect_uri) {
+            listener = listener.path(url.path());
+        }
 
-        Ok(code)
+        listener.listen().await
     }
 
     // If the specified `redirect_url` is HTTP, loopback, and contains a port,
@@ -213,7 +209,7 @@ pub trait OAuthClient: BaseClient {
     ///
     /// Note: this method requires the `cli` feature.
     #[cfg(feature = "cli")]
-    fn get_code_from_user(&self, url: &str) -> ClientResult<String> {
+    async fn get_code_from_user(&self, url: &str) -> ClientResult<String> {
         use crate::ClientError;
 
         log::info!("Opening brower with auth URL");
@@ -227,7 +223,7 @@ pub trait OAuthClient: BaseClient {
         }
 
         match self.get_socket_address(&self.get_oauth().redirect_uri) {
-            Some(addr) => self.get_authcode_listener(addr),
+            Some(addr) => self.get_authcode_listener(addr).await,
             _ => {
                 log::info!("Prompting user for code");
                 println!("Please enter the URL you were redirected to: ");
@@ -259,7 +255,7 @@ pub trait OAuthClient: BaseClient {
     async fn prompt_for_token(&self, url: &str) -> ClientResult<()> {
         match self.read_token_cache(true).await {
             Ok(Some(new_token)) => {
-                let expired = new_token.is_expired();
+                let expired = self.get_config().is_token_expired(&new_token);
 
                 // Load token into client regardless of whether it's expired o
                 // not, since it will be refreshed later anyway.
@@ -275,7 +271,7 @@ pub trait OAuthClient: BaseClient {
                         // If not, prompt the user for it
                         None => {
                             log::info!("Unable to refresh expired token from token cache");
-                            let code = self.get_code_from_user(url)?;
+                            let code = self.get_code_from_user(url).await?;
                             self.request_token(&code).await?;
                         }
                     }
@@ -283,7 +279,7 @@ pub trait OAuthClient: BaseClient {
             }
             // Otherwise following the usual procedure to get the token.
             _ => {
-                let code = self.get_code_from_user(url)?;
+                let code = self.get_code_from_user(url).await?;
                 self.request_token(&code).await?;
             }
         }
@@ -309,6 +305,10 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// The manually paginated version of [`Self::current_user_playlists`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "current_user_playlists", skip_all)
+    )]
     async fn current_user_playlists_manual(
         &self,
         limit: Option<u32>,
@@ -333,6 +333,7 @@ pub trait OAuthClient: BaseClient {
     /// to create a collaborative playlist you must also set public to false
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/create-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_playlist_create(
         &self,
         user_id: UserId<'_>,
@@ -341,6 +342,8 @@ pub trait OAuthClient: BaseClient {
         collaborative: Option<bool>,
         description: Option<&str>,
     ) -> ClientResult<FullPlaylist> {
+        self.check_scopes("user_playlist_create").await?;
+
         debug_assert!(
             !(collaborative.unwrap_or(false) && public.unwrap_or(false)),
             "To create a collaborative playlist you must also set public to \
@@ -369,6 +372,7 @@ pub trait OAuthClient: BaseClient {
     /// - description - optional description of the playlist
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/change-playlist-details)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_change_detail(
         &self,
         playlist_id: PlaylistId<'_>,
@@ -377,6 +381,8 @@ pub trait OAuthClient: BaseClient {
         description: Option<&str>,
         collaborative: Option<bool>,
     ) -> ClientResult<String> {
+        self.check_scopes("playlist_change_detail").await?;
+
         let params = JsonBuilder::new()
             .optional("name", name)
             .optional("public", public)
@@ -394,7 +400,10 @@ pub trait OAuthClient: BaseClient {
     /// - playlist_id - the id of the playlist
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/unfollow-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_unfollow(&self, playlist_id: PlaylistId<'_>) -> ClientResult<()> {
+        self.check_scopes("playlist_unfollow").await?;
+
         let url = format!("playlists/{}/followers", playlist_id.id());
         self.api_delete(&url, &json!({})).await?;
 
@@ -409,12 +418,15 @@ pub trait OAuthClient: BaseClient {
     /// - position - the position to add the items, a zero-based index
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/add-tracks-to-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_add_items<'a>(
         &self,
         playlist_id: PlaylistId<'_>,
         items: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
         position: Option<u32>,
     ) -> ClientResult<PlaylistResult> {
+        self.check_scopes("playlist_add_items").await?;
+
         let uris = items.into_iter().map(|id| id.uri()).collect::<Vec<_>>();
         let params = JsonBuilder::new()
             .required("uris", uris)
@@ -434,11 +446,14 @@ pub trait OAuthClient: BaseClient {
     /// - tracks - the list of track ids to add to the playlist
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/reorder-or-replace-playlists-tracks)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_replace_items<'a>(
         &self,
         playlist_id: PlaylistId<'_>,
         items: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("playlist_replace_items").await?;
+
         let uris = items.into_iter().map(|id| id.uri()).collect::<Vec<_>>();
         let params = JsonBuilder::new().required("uris", uris).build();
 
@@ -460,6 +475,7 @@ pub trait OAuthClient: BaseClient {
     /// - snapshot_id - optional playlist's snapshot ID
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/reorder-or-replace-playlists-tracks)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_reorder_items(
         &self,
         playlist_id: PlaylistId<'_>,
@@ -468,6 +484,8 @@ pub trait OAuthClient: BaseClient {
         range_length: Option<u32>,
         snapshot_id: Option<&str>,
     ) -> ClientResult<PlaylistResult> {
+        self.check_scopes("playlist_reorder_items").await?;
+
         let params = JsonBuilder::new()
             .optional("range_start", range_start)
             .optional("insert_before", insert_before)
@@ -488,12 +506,16 @@ pub trait OAuthClient: BaseClient {
     /// - snapshot_id - optional id of the playlist snapshot
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_remove_all_occurrences_of_items<'a>(
         &self,
         playlist_id: PlaylistId<'_>,
         track_ids: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
         snapshot_id: Option<&str>,
     ) -> ClientResult<PlaylistResult> {
+        self.check_scopes("playlist_remove_all_occurrences_of_items")
+            .await?;
+
         let tracks = track_ids
             .into_iter()
             .map(|id| {
@@ -542,12 +564,16 @@ pub trait OAuthClient: BaseClient {
     /// - snapshot_id: optional id of the playlist snapshot
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_remove_specific_occurrences_of_items<'a>(
         &self,
         playlist_id: PlaylistId<'_>,
         items: impl IntoIterator<Item = ItemPositions<'a>> + Send + 'a,
         snapshot_id: Option<&str>,
     ) -> ClientResult<PlaylistResult> {
+        self.check_scopes("playlist_remove_specific_occurrences_of_items")
+            .await?;
+
         let tracks = items
             .into_iter()
             .map(|track| {
@@ -574,11 +600,14 @@ pub trait OAuthClient: BaseClient {
     /// - playlist_id - the id of the playlist
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/follow-playlist)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn playlist_follow(
         &self,
         playlist_id: PlaylistId<'_>,
         public: Option<bool>,
     ) -> ClientResult<()> {
+        self.check_scopes("playlist_follow").await?;
+
         let url = format!("playlists/{}/followers", playlist_id.id());
 
         let params = JsonBuilder::new().optional("public", public).build();
@@ -592,6 +621,7 @@ pub trait OAuthClient: BaseClient {
     /// An alias for the 'current_user' method.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-current-users-profile)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn me(&self) -> ClientResult<PrivateUser> {
         let result = self.api_get("me/", &Query::new()).await?;
         convert_result(&result)
@@ -601,6 +631,7 @@ pub trait OAuthClient: BaseClient {
     /// An alias for the 'me' method.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-current-users-profile)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user(&self) -> ClientResult<PrivateUser> {
         self.me().await
     }
@@ -608,7 +639,10 @@ pub trait OAuthClient: BaseClient {
     /// Get information about the current users currently playing item.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-the-users-currently-playing-track)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_playing_item(&self) -> ClientResult<Option<CurrentlyPlayingContext>> {
+        self.check_scopes("current_user_playing_item").await?;
+
         let result = self
             .api_get("me/player/currently-playing", &Query::new())
             .await?;
@@ -644,12 +678,18 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// The manually paginated version of [`Self::current_user_saved_albums`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "current_user_saved_albums", skip_all)
+    )]
     async fn current_user_saved_albums_manual(
         &self,
         market: Option<Market>,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<SavedAlbum>> {
+        self.check_scopes("current_user_saved_albums").await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([
@@ -687,12 +727,18 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// The manually paginated version of [`Self::current_user_saved_tracks`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "current_user_saved_tracks", skip_all)
+    )]
     async fn current_user_saved_tracks_manual(
         &self,
         market: Option<Market>,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<SavedTrack>> {
+        self.check_scopes("current_user_saved_tracks").await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([
@@ -712,11 +758,14 @@ pub trait OAuthClient: BaseClient {
     /// - limit - the number of tracks to return
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-followed)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_followed_artists(
         &self,
         after: Option<&str>,
         limit: Option<u32>,
     ) -> ClientResult<CursorBasedPage<FullArtist>> {
+        self.check_scopes("current_user_followed_artists").await?;
+
         let limit = limit.map(|s| s.to_string());
         let params = build_map([
             ("type", Some(Type::Artist.into())),
@@ -734,10 +783,14 @@ pub trait OAuthClient: BaseClient {
     /// - track_ids - a list of track URIs, URLs or IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-user)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_saved_tracks_delete<'a>(
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("current_user_saved_tracks_delete")
+            .await?;
+
         let url = format!("me/tracks/?ids={}", join_ids(track_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -751,10 +804,14 @@ pub trait OAuthClient: BaseClient {
     /// - track_ids - a list of track URIs, URLs or IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/check-users-saved-tracks)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_saved_tracks_contains<'a>(
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.check_scopes("current_user_saved_tracks_contains")
+            .await?;
+
         let url = format!("me/tracks/contains/?ids={}", join_ids(track_ids));
         let result = self.api_get(&url, &Query::new()).await?;
         convert_result(&result)
@@ -766,10 +823,13 @@ pub trait OAuthClient: BaseClient {
     /// - track_ids - a list of track URIs, URLs or IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/save-tracks-user)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_saved_tracks_add<'a>(
         &self,
         track_ids: impl IntoIterator<Item = TrackId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("current_user_saved_tracks_add").await?;
+
         let url = format!("me/tracks/?ids={}", join_ids(track_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -800,12 +860,18 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// The manually paginated version of [`Self::current_user_top_artists`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "current_user_top_artists", skip_all)
+    )]
     async fn current_user_top_artists_manual(
         &self,
         time_range: Option<TimeRange>,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<FullArtist>> {
+        self.check_scopes("current_user_top_artists").await?;
+
         let limit = limit.map(|s| s.to_string());
         let offset = offset.map(|s| s.to_string());
         let params = build_map([
@@ -842,12 +908,18 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// The manually paginated version of [`Self::current_user_top_tracks`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "current_user_top_tracks", skip_all)
+    )]
     async fn current_user_top_tracks_manual(
         &self,
         time_range: Option<TimeRange>,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<FullTrack>> {
+        self.check_scopes("current_user_top_tracks").await?;
+
         let limit = limit.map(|x| x.to_string());
         let offset = offset.map(|x| x.to_string());
         let params = build_map([
@@ -868,11 +940,14 @@ pub trait OAuthClient: BaseClient {
     ///   or before (but not including) this cursor position.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-recently-played)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_recently_played(
         &self,
         limit: Option<u32>,
         time_limit: Option<TimeLimits>,
     ) -> ClientResult<CursorBasedPage<PlayHistory>> {
+        self.check_scopes("current_user_recently_played").await?;
+
         let limit = limit.map(|x| x.to_string());
         let mut params = build_map([("limit", limit.as_deref())]);
 
@@ -895,10 +970,13 @@ pub trait OAuthClient: BaseClient {
     /// - album_ids - a list of album URIs, URLs or IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/save-albums-user)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_saved_albums_add<'a>(
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("current_user_saved_albums_add").await?;
+
         let url = format!("me/albums/?ids={}", join_ids(album_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -911,10 +989,14 @@ pub trait OAuthClient: BaseClient {
     /// - album_ids - a list of album URIs, URLs or IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-albums-user)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_saved_albums_delete<'a>(
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("current_user_saved_albums_delete")
+            .await?;
+
         let url = format!("me/albums/?ids={}", join_ids(album_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -928,10 +1010,14 @@ pub trait OAuthClient: BaseClient {
     /// - album_ids - a list of album URIs, URLs or IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/check-users-saved-albums)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_saved_albums_contains<'a>(
         &self,
         album_ids: impl IntoIterator<Item = AlbumId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.check_scopes("current_user_saved_albums_contains")
+            .await?;
+
         let url = format!("me/albums/contains/?ids={}", join_ids(album_ids));
         let result = self.api_get(&url, &Query::new()).await?;
         convert_result(&result)
@@ -943,10 +1029,13 @@ pub trait OAuthClient: BaseClient {
     /// - artist_ids - a list of artist IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/follow-artists-users)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_follow_artists<'a>(
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("user_follow_artists").await?;
+
         let url = format!("me/following?type=artist&ids={}", join_ids(artist_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -959,10 +1048,13 @@ pub trait OAuthClient: BaseClient {
     /// - artist_ids - a list of artist IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/unfollow-artists-users)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_unfollow_artists<'a>(
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("user_unfollow_artists").await?;
+
         let url = format!("me/following?type=artist&ids={}", join_ids(artist_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -976,10 +1068,13 @@ pub trait OAuthClient: BaseClient {
     /// - artist_ids - the ids of the users that you want to
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/check-current-user-follows)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_artist_check_follow<'a>(
         &self,
         artist_ids: impl IntoIterator<Item = ArtistId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.check_scopes("user_artist_check_follow").await?;
+
         let url = format!(
             "me/following/contains?type=artist&ids={}",
             join_ids(artist_ids)
@@ -994,10 +1089,13 @@ pub trait OAuthClient: BaseClient {
     /// - user_ids - a list of artist IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/follow-artists-users)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_follow_users<'a>(
         &self,
         user_ids: impl IntoIterator<Item = UserId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("user_follow_users").await?;
+
         let url = format!("me/following?type=user&ids={}", join_ids(user_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -1010,10 +1108,13 @@ pub trait OAuthClient: BaseClient {
     /// - user_ids - a list of artist IDs
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/unfollow-artists-users)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn user_unfollow_users<'a>(
         &self,
         user_ids: impl IntoIterator<Item = UserId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("user_unfollow_users").await?;
+
         let url = format!("me/following?type=user&ids={}", join_ids(user_ids));
         self.api_delete(&url, &json!({})).await?;
 
@@ -1023,7 +1124,10 @@ pub trait OAuthClient: BaseClient {
     /// Get a User’s Available Devices
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-a-users-available-devices)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn device(&self) -> ClientResult<Vec<Device>> {
+        self.check_scopes("device").await?;
+
         let result = self.api_get("me/player/devices", &Query::new()).await?;
         convert_result::<DevicePayload>(&result).map(|x| x.devices)
     }
@@ -1037,11 +1141,14 @@ pub trait OAuthClient: BaseClient {
     ///   `episode`.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-information-about-the-users-current-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_playback<'a>(
         &self,
         country: Option<Market>,
         additional_types: Option<impl IntoIterator<Item = &'a AdditionalType> + Send + 'a>,
     ) -> ClientResult<Option<CurrentPlaybackContext>> {
+        self.check_scopes("current_playback").await?;
+
         let additional_types = additional_types.map(|x| {
             x.into_iter()
                 .map(Into::into)
@@ -1070,11 +1177,14 @@ pub trait OAuthClient: BaseClient {
     ///   `track` and `episode`.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/get-the-users-currently-playing-track)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_playing<'a>(
         &'a self,
         market: Option<Market>,
         additional_types: Option<impl IntoIterator<Item = &'a AdditionalType> + Send + 'a>,
     ) -> ClientResult<Option<CurrentlyPlayingContext>> {
+        self.check_scopes("current_playing").await?;
+
         let additional_types = additional_types.map(|x| {
             x.into_iter()
                 .map(Into::into)
@@ -1097,7 +1207,10 @@ pub trait OAuthClient: BaseClient {
     /// Get the Current User’s Queue
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/get-queue)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn current_user_queue(&self) -> ClientResult<CurrentUserQueue> {
+        self.check_scopes("current_user_queue").await?;
+
         let params = build_map([]);
         let result = self.api_get("me/player/queue", &params).await?;
         convert_result(&result)
@@ -1113,7 +1226,10 @@ pub trait OAuthClient: BaseClient {
     /// - force_play - true: after transfer, play. false: keep current state.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/transfer-a-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn transfer_playback(&self, device_id: &str, play: Option<bool>) -> ClientResult<()> {
+        self.check_scopes("transfer_playback").await?;
+
         let params = JsonBuilder::new()
             .required("device_ids", [device_id])
             .optional("play", play)
@@ -1138,6 +1254,7 @@ pub trait OAuthClient: BaseClient {
     /// - position - Indicates from what position to start playback.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/start-a-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn start_context_playback(
         &self,
         context_uri: PlayContextId<'_>,
@@ -1145,6 +1262,8 @@ pub trait OAuthClient: BaseClient {
         offset: Option<Offset>,
         position: Option<chrono::Duration>,
     ) -> ClientResult<()> {
+        self.check_scopes("start_context_playback").await?;
+
         let params = JsonBuilder::new()
             .required("context_uri", context_uri.uri())
             .optional(
@@ -1174,6 +1293,7 @@ pub trait OAuthClient: BaseClient {
     /// - position
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/start-a-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn start_uris_playback<'a>(
         &self,
         uris: impl IntoIterator<Item = PlayableId<'a>> + Send + 'a,
@@ -1181,6 +1301,8 @@ pub trait OAuthClient: BaseClient {
         offset: Option<crate::model::Offset>,
         position: Option<chrono::Duration>,
     ) -> ClientResult<()> {
+        self.check_scopes("start_uris_playback").await?;
+
         let params = JsonBuilder::new()
             .required(
                 "uris",
@@ -1210,7 +1332,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/pause-a-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn pause_playback(&self, device_id: Option<&str>) -> ClientResult<()> {
+        self.check_scopes("pause_playback").await?;
+
         let url = append_device_id("me/player/pause", device_id);
         self.api_put(&url, &json!({})).await?;
 
@@ -1224,11 +1349,14 @@ pub trait OAuthClient: BaseClient {
     /// - position
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/start-a-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn resume_playback(
         &self,
         device_id: Option<&str>,
         position: Option<chrono::Duration>,
     ) -> ClientResult<()> {
+        self.check_scopes("resume_playback").await?;
+
         let params = JsonBuilder::new()
             .optional("position_ms", position.map(|p| p.num_milliseconds()))
             .build();
@@ -1245,7 +1373,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/skip-users-playback-to-next-track)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn next_track(&self, device_id: Option<&str>) -> ClientResult<()> {
+        self.check_scopes("next_track").await?;
+
         let url = append_device_id("me/player/next", device_id);
         self.api_post(&url, &json!({})).await?;
 
@@ -1258,7 +1389,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/skip-users-playback-to-previous-track)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn previous_track(&self, device_id: Option<&str>) -> ClientResult<()> {
+        self.check_scopes("previous_track").await?;
+
         let url = append_device_id("me/player/previous", device_id);
         self.api_post(&url, &json!({})).await?;
 
@@ -1272,11 +1406,14 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/seek-to-position-in-currently-playing-track)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn seek_track(
         &self,
         position: chrono::Duration,
         device_id: Option<&str>,
     ) -> ClientResult<()> {
+        self.check_scopes("seek_track").await?;
+
         let url = append_device_id(
             &format!("me/player/seek?position_ms={}", position.num_milliseconds()),
             device_id,
@@ -1293,7 +1430,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/set-repeat-mode-on-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn repeat(&self, state: RepeatState, device_id: Option<&str>) -> ClientResult<()> {
+        self.check_scopes("repeat").await?;
+
         let url = append_device_id(
             &format!("me/player/repeat?state={}", <&str>::from(state)),
             device_id,
@@ -1310,7 +1450,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/set-volume-for-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn volume(&self, volume_percent: u8, device_id: Option<&str>) -> ClientResult<()> {
+        self.check_scopes("volume").await?;
+
         debug_assert!(
             volume_percent <= 100u8,
             "volume must be between 0 and 100, inclusive"
@@ -1331,7 +1474,10 @@ pub trait OAuthClient: BaseClient {
     /// - device_id - device target for playback
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/toggle-shuffle-for-users-playback)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn shuffle(&self, state: bool, device_id: Option<&str>) -> ClientResult<()> {
+        self.check_scopes("shuffle").await?;
+
         let url = append_device_id(&format!("me/player/shuffle?state={state}"), device_id);
         self.api_put(&url, &json!({})).await?;
 
@@ -1347,11 +1493,14 @@ pub trait OAuthClient: BaseClient {
     ///   targeted
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/add-to-queue)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn add_item_to_queue(
         &self,
         item: PlayableId<'_>,
         device_id: Option<&str>,
     ) -> ClientResult<()> {
+        self.check_scopes("add_item_to_queue").await?;
+
         let url = append_device_id(&format!("me/player/queue?uri={}", item.uri()), device_id);
         self.api_post(&url, &json!({})).await?;
 
@@ -1365,10 +1514,13 @@ pub trait OAuthClient: BaseClient {
     ///   be added to the user’s library.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/save-shows-user)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn save_shows<'a>(
         &self,
         show_ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
     ) -> ClientResult<()> {
+        self.check_scopes("save_shows").await?;
+
         let url = format!("me/shows/?ids={}", join_ids(show_ids));
         self.api_put(&url, &json!({})).await?;
 
@@ -1396,11 +1548,17 @@ pub trait OAuthClient: BaseClient {
     }
 
     /// The manually paginated version of [`Self::get_saved_show`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "get_saved_show", skip_all)
+    )]
     async fn get_saved_show_manual(
         &self,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> ClientResult<Page<Show>> {
+        self.check_scopes("get_saved_show").await?;
+
         let limit = limit.map(|x| x.to_string());
         let offset = offset.map(|x| x.to_string());
         let params = build_map([("limit", limit.as_deref()), ("offset", offset.as_deref())]);
@@ -1415,10 +1573,13 @@ pub trait OAuthClient: BaseClient {
     /// - ids: Required. A comma-separated list of the Spotify IDs for the shows. Maximum: 50 IDs.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/check-users-saved-shows)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn check_users_saved_shows<'a>(
         &self,
         ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
     ) -> ClientResult<Vec<bool>> {
+        self.check_scopes("check_users_saved_shows").await?;
+
         let ids = join_ids(ids);
         let params = build_map([("ids", Some(&ids))]);
         let result = self.api_get("me/shows/contains", &params).await?;
@@ -1433,11 +1594,14 @@ pub trait OAuthClient: BaseClient {
     /// - market: Optional. An ISO 3166-1 alpha-2 country code or the string from_token.
     ///
     /// [Reference](https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-shows-user)
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     async fn remove_users_saved_shows<'a>(
         &self,
         show_ids: impl IntoIterator<Item = ShowId<'a>> + Send + 'a,
         country: Option<Market>,
     ) -> ClientResult<()> {
+        self.check_scopes("remove_users_saved_shows").await?;
+
         let url = format!("me/shows?ids={}", join_ids(show_ids));
         let params = JsonBuilder::new()
             .optional("country", country.map(<&str>::from))
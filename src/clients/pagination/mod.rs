@@ -1,6 +1,7 @@
 //! Utilities for pagination requests. If the configured client is asynchronous,
 //! it'll be  based on `futures::stream::Stream`, if it's synchronous it'll just
-//! use `std::iter::Iterator`.
+//! use `std::iter::Iterator`. The latter takes precedence when both are
+//! enabled.
 //!
 //! All implementations export:
 //!
@@ -26,19 +27,27 @@
 #[cfg(feature = "__sync")]
 mod iter;
 
-#[cfg(all(feature = "__async", not(target_arch = "wasm32")))]
+#[cfg(all(
+    feature = "__async",
+    not(feature = "__sync"),
+    not(target_arch = "wasm32")
+))]
 mod stream;
 
-#[cfg(all(feature = "__async", target_arch = "wasm32"))]
+#[cfg(all(feature = "__async", not(feature = "__sync"), target_arch = "wasm32"))]
 mod wasm_stream;
 
 #[cfg(feature = "__sync")]
 pub use iter::{paginate, paginate_with_ctx, Paginator};
 
-#[cfg(all(feature = "__async", not(target_arch = "wasm32")))]
+#[cfg(all(
+    feature = "__async",
+    not(feature = "__sync"),
+    not(target_arch = "wasm32")
+))]
 pub use stream::{paginate, paginate_with_ctx, Paginator};
 
-#[cfg(all(feature = "__async", target_arch = "wasm32"))]
+#[cfg(all(feature = "__async", not(feature = "__sync"), target_arch = "wasm32"))]
 pub use wasm_stream::{paginate, paginate_with_ctx, Paginator};
 
 mod response_senders;
\ No newline at end of file
@@ -0,0 +1,157 @@
+//! The source of the current time used to check whether tokens have expired,
+//! which can be replaced in tests to avoid sleeping.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// The source of the current time, configured with
+/// [`Config::clock`](crate::Config::clock).
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock, which is used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to, which is shared by all its clones.
+/// It's meant for tests of the behavior that depends on the expiration of
+/// tokens.
+///
+/// ```
+/// use std::sync::Arc;
+/// use chrono::Duration;
+/// use rspotify::{Config, FakeClock, Token};
+///
+/// let clock = FakeClock::default();
+/// let config = Config {
+///     clock: Arc::new(clock.clone()),
+///     ..Default::default()
+/// };
+/// let token = Token {
+///     expires_at: Some(clock.get() + Duration::try_hours(1).unwrap()),
+///     ..Default::default()
+/// };
+/// assert!(!config.is_token_expired(&token));
+///
+/// clock.advance(Duration::try_hours(1).unwrap());
+/// assert!(config.is_token_expired(&token));
+/// ```
+#[derive(Clone, Debug)]
+pub struct FakeClock(Arc<Mutex<DateTime<Utc>>>);
+
+impl FakeClock {
+    /// Builds a clock stopped at `now`.
+    #[must_use]
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self(Arc::new(Mutex::new(now)))
+    }
+
+    /// The time the clock is stopped at.
+    #[must_use]
+    pub fn get(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+
+    /// Stops the clock at `now`.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.0.lock().unwrap() = now;
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+}
+
+/// Stopped at the current time.
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::new(Utc::now())
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.get()
+    }
+}
+
+/// The difference between Spotify's clock and the local one, measured with the
+/// `Date` header of the token responses and shared by all its clones. When
+/// it's set in [`Config::clock_skew`](crate::Config::clock_skew), the
+/// expiration of the tokens is derived from that header, and the local time
+/// is corrected with it when checking whether they have expired.
+///
+/// This is useful when the local clock can't be trusted, or when the tokens
+/// are shared with other machines through a
+/// [`TokenStore`](crate::TokenStore).
+#[derive(Clone, Debug, Default)]
+pub struct ClockSkew(Arc<Mutex<Duration>>);
+
+impl ClockSkew {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How far ahead of the local clock Spotify's clock was the last time it
+    /// was measured, which is negative if it's behind, and zero until then.
+    #[must_use]
+    pub fn get(&self) -> Duration {
+        *self.0.lock().unwrap()
+    }
+
+    /// Updates the skew with the `Date` of a response received at the local
+    /// time `now`.
+    pub(crate) fn update(&self, date: DateTime<Utc>, now: DateTime<Utc>) {
+        *self.0.lock().unwrap() = date - now;
+    }
+}
+
+/// Parses the value of a `Date` header, in the format of RFC 7231.
+pub(crate) fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock() {
+        let start = Utc::now();
+        let clock = FakeClock::new(start);
+        let shared = clock.clone();
+        shared.advance(Duration::try_minutes(5).unwrap());
+        assert_eq!(clock.now() - start, Duration::try_minutes(5).unwrap());
+
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let date = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(date.to_rfc3339(), "1994-11-06T08:49:37+00:00");
+        assert_eq!(parse_http_date("yesterday"), None);
+
+        let skew = ClockSkew::new();
+        assert_eq!(skew.get(), Duration::zero());
+        skew.clone()
+            .update(date, date - Duration::try_seconds(90).unwrap());
+        assert_eq!(skew.get(), Duration::try_seconds(90).unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+//! The scopes required by each endpoint of [`OAuthClient`], which are checked
+//! before sending a request, and can be used to know which ones to ask for
+//! when authorizing the app.
+//!
+//! [`OAuthClient`]: crate::clients::OAuthClient
+
+use std::collections::HashSet;
+
+use crate::model::Scope;
+
+/// The endpoints of [`OAuthClient`](crate::clients::OAuthClient), by the name
+/// of their method, and the scopes they require. The token needs *any* of
+/// the listed scopes, since some endpoints accept more than one; e.g.,
+/// modifying a playlist requires [`Scope::PlaylistModifyPublic`] or
+/// [`Scope::PlaylistModifyPrivate`] depending on its visibility. The
+/// endpoints where scopes only change the contents of the response, like
+/// [`me`](crate::clients::OAuthClient::me), require none.
+const ENDPOINT_SCOPES: &[(&str, &[Scope])] = &[
+    ("current_user_playlists", &[]),
+    ("user_playlist_create", PLAYLIST_MODIFY),
+    ("playlist_change_detail", PLAYLIST_MODIFY),
+    ("playlist_unfollow", PLAYLIST_MODIFY),
+    ("playlist_add_items", PLAYLIST_MODIFY),
+    ("playlist_replace_items", PLAYLIST_MODIFY),
+    ("playlist_reorder_items", PLAYLIST_MODIFY),
+    ("playlist_remove_all_occurrences_of_items", PLAYLIST_MODIFY),
+    (
+        "playlist_remove_specific_occurrences_of_items",
+        PLAYLIST_MODIFY,
+    ),
+    ("playlist_follow", PLAYLIST_MODIFY),
+    ("me", &[]),
+    ("current_user", &[]),
+    ("current_user_playing_item", CURRENTLY_PLAYING),
+    ("current_user_saved_albums", &[Scope::UserLibraryRead]),
+    ("current_user_saved_tracks", &[Scope::UserLibraryRead]),
+    ("current_user_followed_artists", &[Scope::UserFollowRead]),
+    (
+        "current_user_saved_tracks_delete",
+        &[Scope::UserLibraryModify],
+    ),
+    (
+        "current_user_saved_tracks_contains",
+        &[Scope::UserLibraryRead],
+    ),
+    ("current_user_saved_tracks_add", &[Scope::UserLibraryModify]),
+    ("current_user_top_artists", &[Scope::UserTopRead]),
+    ("current_user_top_tracks", &[Scope::UserTopRead]),
+    (
+        "current_user_recently_played",
+        &[Scope::UserReadRecentlyPlayed],
+    ),
+    ("current_user_saved_albums_add", &[Scope::UserLibraryModify]),
+    (
+        "current_user_saved_albums_delete",
+        &[Scope::UserLibraryModify],
+    ),
+    (
+        "current_user_saved_albums_contains",
+        &[Scope::UserLibraryRead],
+    ),
+    ("user_follow_artists", &[Scope::UserFollowModify]),
+    ("user_unfollow_artists", &[Scope::UserFollowModify]),
+    ("user_artist_check_follow", &[Scope::UserFollowRead]),
+    ("user_follow_users", &[Scope::UserFollowModify]),
+    ("user_unfollow_users", &[Scope::UserFollowModify]),
+    ("device", &[Scope::UserReadPlaybackState]),
+    ("current_playback", &[Scope::UserReadPlaybackState]),
+    ("current_playing", CURRENTLY_PLAYING),
+    ("current_user_queue", CURRENTLY_PLAYING),
+    ("transfer_playback", &[Scope::UserModifyPlaybackState]),
+    ("start_context_playback", &[Scope::UserModifyPlaybackState]),
+    ("start_uris_playback", &[Scope::UserModifyPlaybackState]),
+    ("pause_playback", &[Scope::UserModifyPlaybackState]),
+    ("resume_playback", &[Scope::UserModifyPlaybackState]),
+    ("next_track", &[Scope::UserModifyPlaybackState]),
+    ("previous_track", &[Scope::UserModifyPlaybackState]),
+    ("seek_track", &[Scope::UserModifyPlaybackState]),
+    ("repeat", &[Scope::UserModifyPlaybackState]),
+    ("volume", &[Scope::UserModifyPlaybackState]),
+    ("shuffle", &[Scope::UserModifyPlaybackState]),
+    ("add_item_to_queue", &[Scope::UserModifyPlaybackState]),
+    ("save_shows", &[Scope::UserLibraryModify]),
+    ("get_saved_show", &[Scope::UserLibraryRead]),
+    ("check_users_saved_shows", &[Scope::UserLibraryRead]),
+    ("remove_users_saved_shows", &[Scope::UserLibraryModify]),
+];
+
+const PLAYLIST_MODIFY: &[Scope] = &[Scope::PlaylistModifyPublic, Scope::PlaylistModifyPrivate];
+const CURRENTLY_PLAYING: &[Scope] = &[
+    Scope::UserReadCurrentlyPlaying,
+    Scope::UserReadPlaybackState,
+];
+
+/// The scopes required by an endpoint of
+/// [`OAuthClient`](crate::clients::OAuthClient), by the name of its method
+/// (without the `_manual` suffix of the manually paginated versions), or
+/// `None` if there's no such endpoint.
+///
+/// The token needs any of the returned scopes, and an empty slice means that
+/// the endpoint doesn't require any.
+#[must_use]
+pub fn required_scopes(endpoint: &str) -> Option<&'static [Scope]> {
+    let endpoint = endpoint.strip_suffix("_manual").unwrap_or(endpoint);
+    ENDPOINT_SCOPES
+        .iter()
+        .find(|(name, _)| *name == endpoint)
+        .map(|(_, scopes)| *scopes)
+}
+
+/// The scopes to ask for when authorizing the app, so that the token can be
+/// used with all the given endpoints. It can be assigned directly to
+/// [`OAuth::scopes`](crate::OAuth::scopes).
+///
+/// For endpoints that accept more than one scope, all of them are included.
+/// Unknown endpoints are ignored.
+///
+/// ```
+/// use rspotify::{required_scopes_for, OAuth};
+///
+/// let oauth = OAuth {
+///     scopes: required_scopes_for(["current_user_top_tracks", "start_context_playback"]),
+///     ..Default::default()
+/// };
+/// assert!(oauth.scopes.contains("user-top-read"));
+/// assert!(oauth.scopes.contains("user-modify-playback-state"));
+/// ```
+#[must_use]
+pub fn required_scopes_for<'a>(endpoints: impl IntoIterator<Item = &'a str>) -> HashSet<String> {
+    endpoints
+        .into_iter()
+        .filter_map(required_scopes)
+        .flatten()
+        .map(|scope| scope.as_str().to_owned())
+        .collect()
+}
+
+/// The scopes required by `endpoint` that are missing from the `granted`
+/// ones, or `None` if it can be used with them. Unknown endpoints aren't
+/// checked.
+pub(crate) fn missing_scopes(endpoint: &str, granted: &HashSet<String>) -> Option<Vec<Scope>> {
+    let required = match required_scopes(endpoint) {
+        Some(required) => required,
+        None => {
+            log::debug!("The scopes of {endpoint} are unknown, not checking them");
+            return None;
+        }
+    };
+    if required.is_empty()
+        || required
+            .iter()
+            .any(|scope| granted.contains(scope.as_str()))
+    {
+        None
+    } else {
+        Some(required.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_required_scopes() {
+        assert_eq!(
+            required_scopes("current_user_top_tracks"),
+            Some(&[Scope::UserTopRead][..])
+        );
+        assert_eq!(
+            required_scopes("current_user_top_tracks_manual"),
+            Some(&[Scope::UserTopRead][..])
+        );
+        assert_eq!(required_scopes("me"), Some(&[][..]));
+        assert_eq!(required_scopes("not_an_endpoint"), None);
+
+        let scopes = required_scopes_for(["playlist_add_items", "me", "device"]);
+        let mut scopes = scopes.iter().map(String::as_str).collect::<Vec<_>>();
+        scopes.sort_unstable();
+        assert_eq!(
+            scopes,
+            [
+                "playlist-modify-private",
+                "playlist-modify-public",
+                "user-read-playback-state"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_scopes() {
+        let granted = ["playlist-modify-private".to_owned()].into_iter().collect();
+        assert_eq!(missing_scopes("playlist_add_items", &granted), None);
+        assert_eq!(missing_scopes("me", &granted), None);
+        assert_eq!(
+            missing_scopes("pause_playback", &granted),
+            Some(vec![Scope::UserModifyPlaybackState])
+        );
+    }
+
+    #[test]
+    fn test_unknown_endpoint() {
+        assert_eq!(missing_scopes("not_an_endpoint", &HashSet::new()), None);
+    }
+
+    /// The endpoints are listed once, by the name of their method without the
+    /// `_manual` suffix, which is ignored when looking them up.
+    #[test]
+    fn test_endpoint_names() {
+        let mut names = HashSet::new();
+        for (name, _) in ENDPOINT_SCOPES {
+            assert!(names.insert(*name), "{name} is listed twice");
+            assert!(
+                !name.ends_with("_manual"),
+                "{name} has the `_manual` suffix"
+            );
+            assert!(
+                name.chars().all(|c| c.is_ascii_lowercase() || c == '_'),
+                "{name} isn't a method name"
+            );
+        }
+    }
+}
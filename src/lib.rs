@@ -48,6 +48,35 @@
 //! }
 //! ```
 //!
+//! Note that both features can't be enabled at the same time, even when it's
+//! different crates in the same dependency graph that enable them: RSpotify
+//! can only be either synchronous or asynchronous, and so can its HTTP clients.
+//! A workspace that needs both has to use a single mode, e.g., by running the
+//! asynchronous client in a runtime from its blocking code. Custom HTTP
+//! clients can be used along with the enabled one, as long as they work in
+//! the same mode.
+//!
+//! The Spotify clients are generic over the HTTP client, which defaults to
+//! [`http::HttpClient`]. Any other implementation of [`http::BaseHttpClient`]
+//! can be used with their `with_http_client` method, like
+//! [`AuthCodeSpotify::with_http_client`]. For example, to run the requests
+//! through a chain of middleware:
+//!
+//! ```no_run
+//! use rspotify::{
+//!     http::{middleware::MiddlewareClient, HttpClient},
+//!     prelude::*,
+//!     ClientCredsSpotify, Credentials,
+//! };
+//!
+//! # #[maybe_async::maybe_async]
+//! # async fn run() {
+//! let http = MiddlewareClient::new(HttpClient::default());
+//! let spotify = ClientCredsSpotify::new(Credentials::default()).with_http_client(http);
+//! spotify.request_token().await.unwrap();
+//! # }
+//! ```
+//!
 //! ### Proxies
 //!
 //! Both [reqwest][reqwest-proxies] and [ureq][ureq-proxying] support system
@@ -59,6 +88,56 @@
 //! - [reqwest](https://docs.rs/reqwest/latest/src/reqwest/proxy.rs.html#897-920)
 //! - [ureq](https://docs.rs/ureq/latest/src/ureq/proxy.rs.html#73-95)
 //!
+//! ### Retries
+//!
+//! Requests to the API that fail with a transient error, such as `429 Too Many
+//! Requests` when Spotify rate-limits the app, are retried automatically. The
+//! `Retry-After` header of the response is honored if present. Requests that
+//! modify data are only retried after a `429 Too Many Requests` by default.
+//! This can be configured with [`Config::retry_policy`]; see [`RetryPolicy`]
+//! for more information.
+//!
+//! ### Tracing
+//!
+//! With the `tracing` feature, every endpoint opens a [tracing][tracing-docs]
+//! span named after it, such as `playlist_items` or `start_context_playback`.
+//! The manually paginated versions share the span name of the endpoint. Each
+//! request to the API then opens a child `request` span with the following
+//! fields:
+//!
+//! - `http.request.method`
+//! - `url.template`: the path of the endpoint, with its identifiers replaced,
+//!   e.g., `playlists/{id}/tracks`.
+//! - `http.response.status_code`: the status code of the response, which is
+//!   missing when none was received, e.g., after a connection error.
+//! - `http.request.resend_count`: the number of retries, if any.
+//! - `latency_ms`: the time taken by the request, including the retries.
+//! - `pagination.offset`: the `offset` parameter, if any.
+//!
+//! Neither the parameters of the endpoints nor the headers are recorded, so
+//! the access token and client secret are never part of the spans.
+//!
+//! ```toml
+//! [dependencies]
+//! rspotify = { version = "...", features = ["tracing"] }
+//! ```
+//!
+//! ### Encrypted token cache
+//!
+//! With the `cache-encryption` feature, the token cache may be encrypted with
+//! AES-256-GCM, using either a key or a passphrase. It's enabled with
+//! [`FileTokenStore::with_encryption`], whose keys may include the previous
+//! ones when rotating them. Existing plaintext caches are only read with
+//! [`FileTokenStore::migrate_plaintext`], and they're encrypted the next time
+//! the token is saved. When no keys are configured, encrypted caches are read
+//! with the passphrase in the `RSPOTIFY_CACHE_PASSPHRASE` environment
+//! variable.
+//!
+//! ```toml
+//! [dependencies]
+//! rspotify = { version = "...", features = ["cache-encryption"] }
+//! ```
+//!
 //! ### Environmental variables
 //!
 //! RSpotify supports the `dotenvy` crate, which allows you to save credentials
@@ -76,6 +155,11 @@
 //! prompting the user, after enabling the `cli` feature. See the
 //! [Authorization](#authorization) section for more information.
 //!
+//! If the redirect URI is a loopback address with a port, like
+//! `http://127.0.0.1:8888/callback`, the code is received by a local server
+//! instead of asking the user to paste the URL they were redirected to. See
+//! [`AuthCodeListener`] to use it directly.
+//!
 //! ## Getting Started
 //!
 //! ### Authorization
@@ -102,6 +186,17 @@
 //!   has not been tested on a browser yet. If you'd like support for it, let us
 //!   know in an issue!
 //!
+//! The endpoints in [`OAuthClient`](crate::clients::OAuthClient) check that
+//! the token has the scopes they require before sending the request, and fail
+//! with [`ClientError::MissingScope`] otherwise. The scopes to ask for when
+//! authorizing the app can be obtained with [`required_scopes_for`].
+//!
+//! Instead of reading the credentials from the environment with
+//! [`Credentials::from_env`] and [`OAuth::from_env`], they may be loaded along
+//! with the configuration of the client from a TOML or JSON file with named
+//! profiles, which is useful to switch between apps. See [`Profiles`] for its
+//! format.
+//!
 //! In order to help other developers to get used to `rspotify`, there are
 //! public credentials available for a dummy account. You can test `rspotify`
 //! with this account's `RSPOTIFY_CLIENT_ID` and `RSPOTIFY_CLIENT_SECRET` inside
@@ -132,6 +227,7 @@
 //! [reqwest-docs]: https://docs.rs/reqwest/
 //! [reqwest-proxies]: https://docs.rs/reqwest/#proxies
 //! [ureq-docs]: https://docs.rs/ureq/
+//! [tracing-docs]: https://docs.rs/tracing/
 //! [examples-github]: https://github.com/ramsayleung/rspotify/tree/master/examples
 //! [spotify-main]: https://developer.spotify.com/documentation/web-api/
 //! [spotify-auth-flows]: https://developer.spotify.com/documentation/general/guides/authorization/
@@ -142,25 +238,53 @@
 //! [spotify-auth-code-pkce]: https://developer.spotify.com/documentation/web-api/tutorials/code-pkce-flow
 //! [spotify-implicit-grant]: https://developer.spotify.com/documentation/general/guides/authorization/implicit-grant
 
+mod accounts;
 mod auth_code;
+mod auth_code_listener;
 mod auth_code_pkce;
 mod client_creds;
 pub mod clients;
+mod clock;
+mod db_replace_and_update;
+mod endpoint_scopes;
+mod profile;
+mod rate_limit;
+mod response_cache;
+mod retry;
 pub mod sync;
+mod telemetry;
+mod token_refresher;
+mod token_store;
 mod util;
-mod db_replace_and_update;
 // Subcrate re-exports
 pub use rspotify_http as http;
 pub use rspotify_macros as macros;
 pub use rspotify_model as model;
 // Top-level re-exports
+pub use accounts::SpotifyAccounts;
 pub use auth_code::AuthCodeSpotify;
+pub use auth_code_listener::{AuthCodeListener, AuthCodeListenerHandle};
 pub use auth_code_pkce::AuthCodePkceSpotify;
 pub use client_creds::ClientCredsSpotify;
+pub use clock::{Clock, ClockSkew, FakeClock, SystemClock};
+pub use endpoint_scopes::{required_scopes, required_scopes_for};
 pub use macros::scopes;
+#[cfg(feature = "cache-encryption")]
+pub use model::CacheKey;
 pub use model::Token;
+pub use profile::{FromProfile, Profile, Profiles, Secret, PROFILE_ENV_VAR};
+pub use rate_limit::{RateLimit, RateLimitPermit, RateLimiter};
+pub use response_cache::{CachedResponse, FileResponseCache, MemoryResponseCache, ResponseCache};
+pub use retry::RetryPolicy;
+pub use token_refresher::{CloneTracker, TokenRefresher, DEFAULT_REFRESH_MARGIN};
+pub use token_store::{
+    FileTokenStore, MemoryTokenStore, TokenStore, TokenStoreLock, DEFAULT_TOKEN_ACCOUNT,
+};
 
-use crate::{http::HttpError, model::Id};
+use crate::{
+    http::HttpError,
+    model::{ApiError, Id},
+};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -168,6 +292,7 @@ use std::{
     net::SocketAddr,
     path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use base64::{engine::general_purpose, Engine as _};
@@ -238,6 +363,10 @@ pub enum ClientError {
     #[error("cache file error: {0}")]
     CacheFile(String),
 
+    /// A [`Profile`] couldn't be loaded or used.
+    #[error("profile error: {0}")]
+    Profile(String),
+
     #[error("token callback function error: {0}")]
     TokenCallbackFn(#[from] CallbackError),
 
@@ -247,20 +376,71 @@ pub enum ClientError {
     #[error("Token is not valid")]
     InvalidToken,
 
+    /// Any error of a custom [`TokenStore`].
+    #[error("token store error: {0}")]
+    TokenStore(Box<dyn std::error::Error + Send + Sync>),
+
+    /// Spotify rejected the access token, and it couldn't be refreshed. It
+    /// contains the error of the refresh, or the original one if there was no
+    /// way to refresh the token.
+    #[error("token refresh error: {0}")]
+    TokenRefresh(Box<ClientError>),
+
+    /// The token lacks the scope required by an endpoint, which was detected
+    /// before sending the request. `missing` contains the scopes accepted by
+    /// the endpoint, any of which would be enough. See
+    /// [`Config::check_scopes`].
+    #[error(
+        "missing scope for {endpoint}: requires any of {}",
+        .missing.iter().map(model::Scope::as_str).collect::<Vec<_>>().join(", ")
+    )]
+    MissingScope {
+        endpoint: &'static str,
+        missing: Vec<model::Scope>,
+    },
+
     #[error("Failed to bind server to {addr} ({e})")]
     AuthCodeListenerBind { addr: SocketAddr, e: std::io::Error },
 
+    #[deprecated(note = "`AuthCodeListener` never returns this error")]
     #[error("Listener terminated without accepting a connection")]
     AuthCodeListenerTerminated,
 
+    #[deprecated(note = "`AuthCodeListener` never returns this error")]
     #[error("Failed to read redirect URI from HTTP request")]
     AuthCodeListenerRead,
 
+    #[deprecated(note = "`AuthCodeListener` never returns this error")]
     #[error("Failed to parse redirect URI {0} from HTTP request")]
     AuthCodeListenerParse(String),
 
+    #[deprecated(note = "`AuthCodeListener` never returns this error")]
     #[error("Failed to write HTTP response")]
     AuthCodeListenerWrite,
+
+    #[error("Listener timed out without receiving the authorization callback")]
+    AuthCodeListenerTimeout,
+
+    #[error("Listener was cancelled")]
+    AuthCodeListenerCancelled,
+
+    /// The user didn't authorize the app, or the authorization failed, as
+    /// reported by the `error` parameter of the callback.
+    #[error("authorization denied: {error}")]
+    AuthorizationDenied {
+        error: String,
+        description: Option<String>,
+    },
+
+    /// The state the user was redirected back with isn't the one of the
+    /// authorization.
+    #[error("the state of the callback doesn't match the authorization")]
+    StateMismatch,
+
+    /// The [`PendingAuthorization`](model::PendingAuthorization) is too old
+    /// to be completed.
+    #[error("the pending authorization has expired")]
+    AuthorizationExpired,
 }
 
 // The conversion has to be done manually because it's in a `Box<T>`
@@ -270,12 +450,71 @@ impl From<HttpError> for ClientError {
     }
 }
 
+impl ClientError {
+    /// The status code of the response, in case the request to Spotify was
+    /// completed but unsuccessful.
+    #[must_use]
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Self::Http(err) => err.status_code(),
+            _ => None,
+        }
+    }
+
+    /// The error message sent by Spotify, if any.
+    #[must_use]
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Http(err) => err.api_error(),
+            _ => None,
+        }
+    }
+
+    /// Whether the request failed because of Spotify's rate limiting, even
+    /// after retrying it according to [`Config::retry_policy`].
+    #[must_use]
+    pub fn is_rate_limited(&self) -> bool {
+        self.status_code() == Some(retry::TOO_MANY_REQUESTS)
+    }
+
+    /// The amount of time Spotify asked to wait before sending more requests,
+    /// if any.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Http(err) => err.retry_after(),
+            _ => None,
+        }
+    }
+
+    /// Whether the request failed because the access token is missing,
+    /// invalid or expired.
+    #[must_use]
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, Self::InvalidToken | Self::TokenRefresh(_))
+            || self.status_code() == Some(401)
+    }
+
+    /// The reason why a player command failed, like `PREMIUM_REQUIRED` or
+    /// `NO_ACTIVE_DEVICE`. See the [Player Error Reasons][reference].
+    ///
+    /// [reference]: https://developer.spotify.com/documentation/web-api/reference/#object-playererrorobject
+    #[must_use]
+    pub fn player_reason(&self) -> Option<&str> {
+        match self.api_error() {
+            Some(ApiError::Player { reason, .. }) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
 pub type ClientResult<T> = Result<T, ClientError>;
 
 pub const DEFAULT_API_BASE_URL: &str = "https://api.spotify.com/v1/";
 pub const DEFAULT_AUTH_BASE_URL: &str = "https://accounts.spotify.com/";
 pub const DEFAULT_CACHE_PATH: &str = ".spotify_token_cache.json";
 pub const DEFAULT_PAGINATION_CHUNKS: u32 = 50;
+pub const DEFAULT_EXPIRY_MARGIN: Duration = Duration::from_secs(10);
 
 #[derive(Error, Debug)]
 pub enum CallbackError {
@@ -292,6 +531,126 @@ impl fmt::Debug for TokenCallback {
     }
 }
 
+/// The future returned by an [`AsyncTokenCallback`].
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+pub type TokenCallbackFuture = futures::future::BoxFuture<'static, Result<(), CallbackError>>;
+
+/// An async version of [`TokenCallback`], which is awaited whenever a token is
+/// successfully requested or refetched, after the sync one if both are set.
+///
+/// ```
+/// use rspotify::{AsyncTokenCallback, Token};
+///
+/// let callback = AsyncTokenCallback::new(|token: Token| async move {
+///     println!("New token: {:?}", token);
+///     Ok(())
+/// });
+/// ```
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+pub struct AsyncTokenCallback(pub Box<dyn Fn(Token) -> TokenCallbackFuture + Send + Sync>);
+
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+impl AsyncTokenCallback {
+    /// Wraps an async function or closure into a callback.
+    pub fn new<F, Fut>(callback: F) -> Self
+    where
+        F: Fn(Token) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<(), CallbackError>> + Send + 'static,
+    {
+        Self(Box::new(move |token| Box::pin(callback(token))))
+    }
+}
+
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+impl fmt::Debug for AsyncTokenCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AsyncTokenCallback")
+    }
+}
+
+type ErrorHook = Option<Arc<dyn Fn(&ClientError) + Send + Sync>>;
+
+/// Functions called when the token can't be obtained anymore without the user
+/// authorizing the app again, e.g., to alert that they have to log in again.
+/// They're called in addition to returning the error, and don't replace it.
+///
+/// ```
+/// use rspotify::TokenFailureHooks;
+///
+/// let hooks = TokenFailureHooks::default()
+///     .on_refresh_failure(|err| eprintln!("Failed to refresh the token: {}", err))
+///     .on_missing_refresh_token(|| eprintln!("The user has to log in again"))
+///     .on_cache_read_failure(|err| eprintln!("Failed to read the token cache: {}", err));
+/// ```
+#[derive(Clone, Default)]
+pub struct TokenFailureHooks {
+    refresh_failure: ErrorHook,
+    missing_refresh_token: Option<Arc<dyn Fn() + Send + Sync>>,
+    cache_read_failure: ErrorHook,
+}
+
+impl TokenFailureHooks {
+    /// Called with the error when refreshing the token fails.
+    #[must_use]
+    pub fn on_refresh_failure(
+        mut self,
+        hook: impl Fn(&ClientError) + Send + Sync + 'static,
+    ) -> Self {
+        self.refresh_failure = Some(Arc::new(hook));
+        self
+    }
+
+    /// Called when the token has to be refreshed but there's no refresh token
+    /// to do so.
+    #[must_use]
+    pub fn on_missing_refresh_token(mut self, hook: impl Fn() + Send + Sync + 'static) -> Self {
+        self.missing_refresh_token = Some(Arc::new(hook));
+        self
+    }
+
+    /// Called with the error when loading the token from the token store
+    /// fails.
+    #[must_use]
+    pub fn on_cache_read_failure(
+        mut self,
+        hook: impl Fn(&ClientError) + Send + Sync + 'static,
+    ) -> Self {
+        self.cache_read_failure = Some(Arc::new(hook));
+        self
+    }
+
+    pub(crate) fn refresh_failed(&self, err: &ClientError) {
+        if let Some(hook) = &self.refresh_failure {
+            hook(err);
+        }
+    }
+
+    pub(crate) fn refresh_token_missing(&self) {
+        if let Some(hook) = &self.missing_refresh_token {
+            hook();
+        }
+    }
+
+    pub(crate) fn cache_read_failed(&self, err: &ClientError) {
+        if let Some(hook) = &self.cache_read_failure {
+            hook(err);
+        }
+    }
+}
+
+impl fmt::Debug for TokenFailureHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenFailureHooks")
+            .field("refresh_failure", &self.refresh_failure.is_some())
+            .field(
+                "missing_refresh_token",
+                &self.missing_refresh_token.is_some(),
+            )
+            .field("cache_read_failure", &self.cache_read_failure.is_some())
+            .finish()
+    }
+}
+
 /// Struct to configure the Spotify client.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -301,8 +660,8 @@ pub struct Config {
     /// The Spotify Authentication prefix, [`DEFAULT_AUTH_BASE_URL`] by default.
     pub auth_base_url: String,
 
-    /// The cache file path, in case it's used. By default it's
-    /// [`DEFAULT_CACHE_PATH`]
+    /// The cache file path, in case it's used and [`Self::token_store`] isn't
+    /// set. By default it's [`DEFAULT_CACHE_PATH`]
     pub cache_path: PathBuf,
 
     /// The pagination chunk size used when performing automatically paginated
@@ -314,18 +673,84 @@ pub struct Config {
     /// request, which most times is 50.
     pub pagination_chunks: u32,
 
-    /// Whether or not to save the authentication token into a JSON file,
-    /// then reread the token from JSON file when launching the program without
+    /// Whether or not to save the authentication token into the token store,
+    /// then reread the token from it when launching the program without
     /// following the full auth process again
     pub token_cached: bool,
 
+    /// Where the tokens are saved when [`Self::token_cached`] is enabled. By
+    /// default, it's a [`FileTokenStore`] at [`Self::cache_path`].
+    pub token_store: Option<Arc<dyn TokenStore>>,
+
+    /// The account whose token is loaded from and saved into the token store,
+    /// which is useful when it's shared by multiple clients. By default it's
+    /// [`DEFAULT_TOKEN_ACCOUNT`].
+    pub token_account: String,
+
     /// Whether or not to check if the token has expired when sending a
     /// request with credentials, and in that case, automatically refresh it.
+    ///
+    /// The token is also refreshed once if Spotify rejects it with a `401
+    /// Unauthorized` response even though it didn't look expired, e.g.,
+    /// because it was revoked or the local clock is skewed. If that refresh
+    /// fails, [`ClientError::TokenRefresh`] is returned.
+    ///
+    /// To refresh it before it expires instead, so that requests don't have to
+    /// wait for it, see [`TokenRefresher`].
     pub token_refreshing: bool,
 
     /// Whenever client succeeds to request or refresh a token, the callback function
     /// will be invoked
     pub token_callback_fn: Arc<Option<TokenCallback>>,
+
+    /// The async version of [`Self::token_callback_fn`], which is awaited
+    /// after it if both are set.
+    #[cfg(all(feature = "__async", not(feature = "__sync")))]
+    pub token_callback_async_fn: Arc<Option<AsyncTokenCallback>>,
+
+    /// Called when the token can't be refreshed or loaded from the token
+    /// store, so that the user may be asked to log in again. None are set by
+    /// default.
+    pub token_failure_hooks: TokenFailureHooks,
+
+    /// How requests to the API are retried when they fail with a transient
+    /// error, like Spotify's rate limiting. See [`RetryPolicy`] for the
+    /// defaults, or use [`RetryPolicy::disabled`] to turn retries off.
+    pub retry_policy: RetryPolicy,
+
+    /// Where the responses of GET requests are cached along with their
+    /// `ETag`, so that they're only downloaded again when they've changed.
+    /// See [`ResponseCache`] for more information. By default it's disabled.
+    pub response_cache: Option<Arc<dyn ResponseCache>>,
+
+    /// Limits the rate of requests to the API on the client side, which is
+    /// shared by all the clones of the client. See [`RateLimiter`] for more
+    /// information. By default there's no limit.
+    pub rate_limiter: Option<RateLimiter>,
+
+    /// Whether or not to check that the token has the scopes required by an
+    /// endpoint of [`OAuthClient`](crate::clients::OAuthClient) before
+    /// sending the request, failing with [`ClientError::MissingScope`]
+    /// otherwise. See [`required_scopes`] for the scopes of each endpoint.
+    ///
+    /// It's skipped when the scopes of the token are unknown, i.e., empty.
+    pub check_scopes: bool,
+
+    /// The source of the current time used to check whether the token has
+    /// expired, which is the [`SystemClock`] by default. See [`FakeClock`] to
+    /// control it in tests.
+    pub clock: Arc<dyn Clock>,
+
+    /// How long before the token expires it's already considered expired, to
+    /// account for how long a request may take. By default it's
+    /// [`DEFAULT_EXPIRY_MARGIN`].
+    pub expiry_margin: Duration,
+
+    /// If set, the expiration of the tokens is derived from the `Date` header
+    /// of Spotify's response, and the local time is corrected with the
+    /// difference between both clocks. See [`ClockSkew`] for more
+    /// information. By default it's disabled.
+    pub clock_skew: Option<ClockSkew>,
 }
 
 impl Default for Config {
@@ -336,10 +761,54 @@ impl Default for Config {
             cache_path: PathBuf::from(DEFAULT_CACHE_PATH),
             pagination_chunks: DEFAULT_PAGINATION_CHUNKS,
             token_cached: false,
+            token_store: None,
+            token_account: String::from(DEFAULT_TOKEN_ACCOUNT),
             token_refreshing: true,
             token_callback_fn: Arc::new(None),
+            #[cfg(all(feature = "__async", not(feature = "__sync")))]
+            token_callback_async_fn: Arc::new(None),
+            token_failure_hooks: TokenFailureHooks::default(),
+            retry_policy: RetryPolicy::default(),
+            response_cache: None,
+            rate_limiter: None,
+            check_scopes: true,
+            clock: Arc::new(SystemClock),
+            expiry_margin: DEFAULT_EXPIRY_MARGIN,
+            clock_skew: None,
+        }
+    }
+}
+
+impl Config {
+    /// The store configured in [`Self::token_store`], or a [`FileTokenStore`]
+    /// at [`Self::cache_path`] otherwise.
+    #[must_use]
+    pub fn get_token_store(&self) -> Arc<dyn TokenStore> {
+        match &self.token_store {
+            Some(store) => Arc::clone(store),
+            None => Arc::new(FileTokenStore::single(&self.cache_path)),
+        }
+    }
+
+    /// The current time according to [`Self::clock`], corrected with
+    /// [`Self::clock_skew`] if set.
+    #[must_use]
+    pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        let now = self.clock.now();
+        match &self.clock_skew {
+            Some(skew) => now + skew.get(),
+            None => now,
         }
     }
+
+    /// Whether `token` has expired at [`Self::now`], or will within
+    /// [`Self::expiry_margin`].
+    #[must_use]
+    pub fn is_token_expired(&self, token: &Token) -> bool {
+        // A margin too large to be represented would never let it be used
+        chrono::Duration::from_std(self.expiry_margin)
+            .map_or(true, |margin| token.is_expired_at(self.now(), margin))
+    }
 }
 
 /// Generate `length` random chars from the Operating System.
@@ -474,8 +943,12 @@ impl OAuth {
 
 #[cfg(test)]
 pub mod test {
-    use crate::{alphabets, generate_random_string, Credentials};
-    use std::collections::HashSet;
+    use crate::{
+        alphabets, generate_random_string,
+        http::{ErrorResponse, Headers, HttpError},
+        ClientError, Credentials,
+    };
+    use std::{collections::HashSet, time::Duration};
     use wasm_bindgen_test::*;
 
     #[test]
@@ -504,4 +977,44 @@ pub mod test {
             Some(&"Basic cmFtc2F5OjEyMzQ1Ng==".to_owned())
         );
     }
+
+    #[test]
+    #[wasm_bindgen_test]
+    fn test_client_error_helpers() {
+        let mut headers = Headers::new();
+        headers.insert("retry-after".to_owned(), "7".to_owned());
+        let body = r#"{"error": {"status": 429, "message": "API rate limit exceeded"}}"#;
+        let err = ClientError::from(HttpError::StatusCode(ErrorResponse::new(
+            429,
+            headers,
+            body.to_owned(),
+        )));
+        assert!(err.is_rate_limited());
+        assert!(!err.is_unauthorized());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(7)));
+        assert_eq!(err.player_reason(), None);
+
+        let body = r#"{"error": {"status": 404, "message": "Player command failed: No active device found", "reason": "NO_ACTIVE_DEVICE"}}"#;
+        let err = ClientError::from(HttpError::StatusCode(ErrorResponse::new(
+            404,
+            Headers::new(),
+            body.to_owned(),
+        )));
+        assert_eq!(err.status_code(), Some(404));
+        assert_eq!(err.player_reason(), Some("NO_ACTIVE_DEVICE"));
+
+        let body = r#"{"error": {"status": 401, "message": "The access token expired"}}"#;
+        let err = ClientError::from(HttpError::StatusCode(ErrorResponse::new(
+            401,
+            Headers::new(),
+            body.to_owned(),
+        )));
+        assert!(err.is_unauthorized());
+        assert!(ClientError::InvalidToken.is_unauthorized());
+        assert_eq!(ClientError::InvalidToken.status_code(), None);
+
+        let err = ClientError::TokenRefresh(Box::new(err));
+        assert!(err.is_unauthorized());
+        assert_eq!(err.status_code(), None);
+    }
 }
@@ -0,0 +1,406 @@
+//! Loading the credentials and configuration of the clients from a file with
+//! named profiles, e.g., one for each Spotify app used in development,
+//! staging and production.
+
+use std::{
+    collections::{HashMap, HashSet},
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    AuthCodePkceSpotify, AuthCodeSpotify, ClientCredsSpotify, ClientError, ClientResult, Config,
+    Credentials, OAuth,
+};
+
+/// The environment variable with the name of the profile used by
+/// [`Profiles::current`].
+pub const PROFILE_ENV_VAR: &str = "RSPOTIFY_PROFILE";
+
+/// A set of named [`Profile`]s, usually loaded from a TOML or JSON file with
+/// [`Profiles::from_file`].
+///
+/// In TOML, each profile is a table under `profiles`, and `default` is the
+/// name of the one used when no other is chosen:
+///
+/// ```toml
+/// default = "dev"
+///
+/// [profiles.dev]
+/// client_id = "dev-client-id"
+/// client_secret = { env = "DEV_CLIENT_SECRET" }
+/// redirect_uri = "http://127.0.0.1:8888/callback"
+/// scopes = ["user-read-private", "user-top-read"]
+/// cache_path = ".spotify_dev_token.json"
+/// token_cached = true
+///
+/// [profiles.prod]
+/// client_id = "prod-client-id"
+/// client_secret = { file = "/run/secrets/spotify" }
+/// redirect_uri = "https://example.com/callback"
+/// scopes = ["user-read-private", "user-top-read"]
+/// pagination_chunks = 20
+/// ```
+///
+/// The same structure is used in JSON.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profiles {
+    /// The name of the profile used by [`Self::current`] when
+    /// [`PROFILE_ENV_VAR`] isn't set.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// The profiles by their name.
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+impl Profiles {
+    /// Loads the profiles from a file, which is parsed as JSON if its
+    /// extension is `.json`, and as TOML otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> ClientResult<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::from_json(&contents),
+            _ => Self::from_toml(&contents),
+        }
+    }
+
+    /// Parses the profiles from TOML.
+    pub fn from_toml(contents: &str) -> ClientResult<Self> {
+        toml::from_str(contents).map_err(|err| ClientError::Profile(err.to_string()))
+    }
+
+    /// Parses the profiles from JSON.
+    pub fn from_json(contents: &str) -> ClientResult<Self> {
+        serde_json::from_str(contents).map_err(|err| ClientError::Profile(err.to_string()))
+    }
+
+    /// The profile called `name`.
+    pub fn get(&self, name: &str) -> ClientResult<&Profile> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| ClientError::Profile(format!("there's no profile `{}`", name)))
+    }
+
+    /// The profile named by the [`PROFILE_ENV_VAR`] environment variable, or
+    /// the [`default`](Self::default) one if it isn't set. You can optionally
+    /// activate the `env-file` feature in order to read it from a `.env` file.
+    pub fn current(&self) -> ClientResult<&Profile> {
+        #[cfg(feature = "env-file")]
+        {
+            dotenvy::dotenv().ok();
+        }
+
+        self.current_from(env::var(PROFILE_ENV_VAR).ok())
+    }
+
+    /// The profile called `name`, or the [`default`](Self::default) one if
+    /// it's `None`.
+    fn current_from(&self, name: Option<String>) -> ClientResult<&Profile> {
+        match name.or_else(|| self.default.clone()) {
+            Some(name) => self.get(&name),
+            None => Err(ClientError::Profile(format!(
+                "no profile was chosen with `{}`, and there's no default one",
+                PROFILE_ENV_VAR
+            ))),
+        }
+    }
+}
+
+/// Where the client secret of a [`Profile`] is read from, so that it doesn't
+/// have to be written in the profile file.
+///
+/// It's either a string with the secret itself, or a table with an `env` key
+/// naming the environment variable that contains it, or a `file` key with the
+/// path of a file that contains it.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Secret {
+    /// The secret itself.
+    Inline(String),
+    /// The name of the environment variable with the secret.
+    Env { env: String },
+    /// The path of the file with the secret. Leading and trailing whitespace
+    /// is ignored.
+    File { file: PathBuf },
+}
+
+impl Secret {
+    /// Reads the secret from where it's saved.
+    pub fn resolve(&self) -> ClientResult<String> {
+        match self {
+            Self::Inline(secret) => Ok(secret.clone()),
+            Self::Env { env: var } => env::var(var).map_err(|_| {
+                ClientError::Profile(format!("the environment variable `{}` isn't set", var))
+            }),
+            Self::File { file } => Ok(fs::read_to_string(file)?.trim().to_owned()),
+        }
+    }
+}
+
+// The secret itself is never printed.
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inline(_) => f.write_str("Inline(<redacted>)"),
+            Self::Env { env } => f.debug_struct("Env").field("env", env).finish(),
+            Self::File { file } => f.debug_struct("File").field("file", file).finish(),
+        }
+    }
+}
+
+/// The credentials and configuration of a Spotify app. All the fields except
+/// for [`Self::client_id`] are optional, and the defaults of [`OAuth`] and
+/// [`Config`] are used for the missing ones.
+///
+/// A client can be built from it with [`Self::client`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub client_id: String,
+    /// Required by [`AuthCodeSpotify`] and [`ClientCredsSpotify`].
+    #[serde(default)]
+    pub client_secret: Option<Secret>,
+    /// Required by [`AuthCodeSpotify`] and [`AuthCodePkceSpotify`].
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+    #[serde(default)]
+    pub scopes: HashSet<String>,
+    /// See [`Config::api_base_url`].
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+    /// See [`Config::auth_base_url`].
+    #[serde(default)]
+    pub auth_base_url: Option<String>,
+    /// See [`Config::cache_path`].
+    #[serde(default)]
+    pub cache_path: Option<PathBuf>,
+    /// See [`Config::token_account`].
+    #[serde(default)]
+    pub token_account: Option<String>,
+    /// See [`Config::pagination_chunks`].
+    #[serde(default)]
+    pub pagination_chunks: Option<u32>,
+    /// See [`Config::token_cached`].
+    #[serde(default)]
+    pub token_cached: Option<bool>,
+    /// See [`Config::token_refreshing`].
+    #[serde(default)]
+    pub token_refreshing: Option<bool>,
+}
+
+impl Profile {
+    /// The credentials of the profile, reading its client secret if it has
+    /// one.
+    pub fn credentials(&self) -> ClientResult<Credentials> {
+        Ok(Credentials {
+            id: self.client_id.clone(),
+            secret: self
+                .client_secret
+                .as_ref()
+                .map(Secret::resolve)
+                .transpose()?,
+        })
+    }
+
+    /// The OAuth information of the profile, failing if it has no redirect
+    /// URI.
+    pub fn oauth(&self) -> ClientResult<OAuth> {
+        let redirect_uri = self
+            .redirect_uri
+            .clone()
+            .ok_or_else(|| ClientError::Profile("the profile has no `redirect_uri`".to_owned()))?;
+
+        Ok(OAuth {
+            redirect_uri,
+            scopes: self.scopes.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// The configuration of the profile, with the defaults of [`Config`] for
+    /// the fields it doesn't set.
+    #[must_use]
+    pub fn config(&self) -> Config {
+        let default = Config::default();
+        Config {
+            api_base_url: self.api_base_url.clone().unwrap_or(default.api_base_url),
+            auth_base_url: self.auth_base_url.clone().unwrap_or(default.auth_base_url),
+            cache_path: self.cache_path.clone().unwrap_or(default.cache_path),
+            token_account: self.token_account.clone().unwrap_or(default.token_account),
+            pagination_chunks: self.pagination_chunks.unwrap_or(default.pagination_chunks),
+            token_cached: self.token_cached.unwrap_or(default.token_cached),
+            token_refreshing: self.token_refreshing.unwrap_or(default.token_refreshing),
+            ..default
+        }
+    }
+
+    /// Builds any of the clients from the profile.
+    ///
+    /// ```no_run
+    /// use rspotify::{AuthCodeSpotify, ClientCredsSpotify, Profiles};
+    ///
+    /// let profiles = Profiles::from_file("spotify.toml").unwrap();
+    /// let profile = profiles.current().unwrap();
+    /// let spotify: AuthCodeSpotify = profile.client().unwrap();
+    /// let spotify = profile.client::<ClientCredsSpotify>().unwrap();
+    /// ```
+    pub fn client<C: FromProfile>(&self) -> ClientResult<C> {
+        C::from_profile(self)
+    }
+}
+
+/// The clients that can be built from a [`Profile`] with [`Profile::client`].
+pub trait FromProfile: Sized {
+    fn from_profile(profile: &Profile) -> ClientResult<Self>;
+}
+
+impl FromProfile for AuthCodeSpotify {
+    fn from_profile(profile: &Profile) -> ClientResult<Self> {
+        Ok(Self::with_config(
+            profile.credentials()?,
+            profile.oauth()?,
+            profile.config(),
+        ))
+    }
+}
+
+impl FromProfile for AuthCodePkceSpotify {
+    fn from_profile(profile: &Profile) -> ClientResult<Self> {
+        Ok(Self::with_config(
+            profile.credentials()?,
+            profile.oauth()?,
+            profile.config(),
+        ))
+    }
+}
+
+impl FromProfile for ClientCredsSpotify {
+    fn from_profile(profile: &Profile) -> ClientResult<Self> {
+        Ok(Self::with_config(profile.credentials()?, profile.config()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clients::BaseClient;
+
+    const PROFILES: &str = r#"
+        default = "dev"
+
+        [profiles.dev]
+        client_id = "dev-id"
+        client_secret = "dev-secret"
+        redirect_uri = "http://127.0.0.1:8888/callback"
+        scopes = ["user-read-private"]
+        token_cached = true
+
+        [profiles.prod]
+        client_id = "prod-id"
+        client_secret = { env = "RSPOTIFY_TEST_PROFILE_SECRET" }
+        api_base_url = "https://api.example.com/v1/"
+        cache_path = "prod_token.json"
+        pagination_chunks = 20
+        token_refreshing = false
+    "#;
+
+    #[test]
+    fn test_profiles() {
+        let profiles = Profiles::from_toml(PROFILES).unwrap();
+        assert_eq!(profiles.current_from(None).unwrap().client_id, "dev-id");
+        let prod = profiles.current_from(Some("prod".to_owned())).unwrap();
+        assert_eq!(prod.client_id, "prod-id");
+        let profiles_without_default = Profiles {
+            default: None,
+            ..profiles.clone()
+        };
+        assert!(matches!(
+            profiles_without_default.current_from(None),
+            Err(ClientError::Profile(_))
+        ));
+
+        let dev = profiles.get("dev").unwrap();
+        let spotify: AuthCodeSpotify = dev.client().unwrap();
+        assert_eq!(spotify.get_creds().secret.as_deref(), Some("dev-secret"));
+        assert_eq!(spotify.oauth.redirect_uri, "http://127.0.0.1:8888/callback");
+        assert!(spotify.oauth.scopes.contains("user-read-private"));
+        assert!(spotify.get_config().token_cached);
+        assert_eq!(
+            spotify.get_config().cache_path,
+            Config::default().cache_path
+        );
+
+        // The client credentials flow doesn't need a redirect URI, unlike the
+        // rest
+        let mut prod = profiles.get("prod").unwrap().clone();
+        assert_eq!(
+            prod.client_secret,
+            Some(Secret::Env {
+                env: "RSPOTIFY_TEST_PROFILE_SECRET".to_owned()
+            })
+        );
+        prod.client_secret = Some(Secret::Inline("prod-secret".to_owned()));
+        let spotify: ClientCredsSpotify = prod.client().unwrap();
+        assert_eq!(spotify.get_creds().secret.as_deref(), Some("prod-secret"));
+        let config = spotify.get_config();
+        assert_eq!(config.api_base_url, "https://api.example.com/v1/");
+        assert_eq!(config.auth_base_url, Config::default().auth_base_url);
+        assert_eq!(config.cache_path, PathBuf::from("prod_token.json"));
+        assert_eq!(config.pagination_chunks, 20);
+        assert!(!config.token_refreshing);
+        assert!(matches!(
+            prod.client::<AuthCodePkceSpotify>(),
+            Err(ClientError::Profile(_))
+        ));
+
+        assert!(matches!(
+            profiles.get("staging"),
+            Err(ClientError::Profile(_))
+        ));
+    }
+
+    #[test]
+    fn test_profiles_json() {
+        let profiles = Profiles::from_json(
+            r#"{
+                "profiles": {
+                    "pkce": {"client_id": "pkce-id", "redirect_uri": "http://127.0.0.1/callback"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let spotify: AuthCodePkceSpotify = profiles.get("pkce").unwrap().client().unwrap();
+        assert_eq!(spotify.get_creds().id, "pkce-id");
+        assert_eq!(spotify.get_creds().secret, None);
+
+        // Typos are reported instead of being ignored
+        assert!(matches!(
+            Profiles::from_json(r#"{"profiles": {"x": {"client_id": "x", "scope": []}}}"#),
+            Err(ClientError::Profile(_))
+        ));
+    }
+
+    #[test]
+    fn test_secret() {
+        let path = env::temp_dir().join("rspotify_test_profile_secret");
+        fs::write(&path, "file-secret\n").unwrap();
+        let secret = Secret::File { file: path.clone() };
+        assert_eq!(secret.resolve().unwrap(), "file-secret");
+        fs::remove_file(path).unwrap();
+
+        let secret = Secret::Inline("inline-secret".to_owned());
+        assert_eq!(format!("{:?}", secret), "Inline(<redacted>)");
+        assert!(matches!(
+            Secret::Env {
+                env: "RSPOTIFY_TEST_PROFILE_UNSET".to_owned()
+            }
+            .resolve(),
+            Err(ClientError::Profile(_))
+        ));
+    }
+}
@@ -0,0 +1,503 @@
+//! Limiting the rate of requests to the API on the client side, so that the app
+//! stays within Spotify's rate limits instead of being throttled.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+#[cfg(feature = "__sync")]
+use std::sync::Condvar;
+
+use chrono::{DateTime, Utc};
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+use futures::channel::oneshot;
+
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+use crate::sync;
+
+/// How long [`RateLimiter::try_acquire`] suggests waiting when
+/// [`RateLimit::max_concurrency`] is reached or other requests are already
+/// waiting for a permit.
+const BUSY_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Struct to configure a [`RateLimiter`].
+///
+/// Spotify calculates its rate limit over a rolling 30 second window, so the
+/// limiter uses a sliding window as well: no more than `max_requests` requests
+/// are started within any `period` of time. See the [Rate Limits][reference]
+/// guide for more information.
+///
+/// Both `max_requests` and `max_concurrency` must be greater than zero, since
+/// no request would ever be allowed otherwise.
+///
+/// [reference]: https://developer.spotify.com/documentation/web-api/concepts/rate-limits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The maximum number of requests started within `period`.
+    pub max_requests: u32,
+
+    /// The length of the sliding window.
+    pub period: Duration,
+
+    /// The maximum number of requests in flight at the same time, or `None`
+    /// for no limit.
+    pub max_concurrency: Option<u32>,
+}
+
+impl RateLimit {
+    /// Allows `max_requests` requests within `period`, with no concurrency
+    /// limit.
+    #[must_use]
+    pub fn new(max_requests: u32, period: Duration) -> Self {
+        Self {
+            max_requests,
+            period,
+            max_concurrency: None,
+        }
+    }
+
+    #[must_use]
+    pub fn max_concurrency(mut self, max_concurrency: u32) -> Self {
+        self.max_concurrency = Some(max_concurrency);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// When the requests within the current window were started, oldest first.
+    started: VecDeque<DateTime<Utc>>,
+    in_flight: u32,
+    /// The tickets of the requests waiting for a permit, in the order they
+    /// arrived. Only the first one may be allowed.
+    queue: VecDeque<u64>,
+    next_ticket: u64,
+    /// The waiting requests to wake up when a permit is released or the
+    /// queue moves forward.
+    #[cfg(all(feature = "__async", not(feature = "__sync")))]
+    waiters: Vec<oneshot::Sender<()>>,
+}
+
+impl State {
+    /// Forgets the requests that are no longer within the window.
+    fn prune(&mut self, now: DateTime<Utc>, period: Duration) {
+        while let Some(oldest) = self.started.front() {
+            match (now - *oldest).to_std() {
+                Ok(elapsed) if elapsed >= period => {
+                    self.started.pop_front();
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+/// The state shared by the clones of a [`RateLimiter`] and its permits.
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    #[cfg(feature = "__sync")]
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Wakes up the waiting requests so that they check their turn again.
+    fn notify(&self, state: &mut State) {
+        #[cfg(feature = "__sync")]
+        {
+            let _ = state;
+            self.changed.notify_all();
+        }
+        #[cfg(all(feature = "__async", not(feature = "__sync")))]
+        for waiter in state.waiters.drain(..) {
+            let _ = waiter.send(());
+        }
+    }
+}
+
+/// A place in the queue of [`RateLimiter::acquire`], which is given up when
+/// dropped, including when the request is cancelled while waiting.
+struct Ticket<'a> {
+    shared: &'a Shared,
+    id: u64,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        if let Some(pos) = state.queue.iter().position(|id| *id == self.id) {
+            state.queue.remove(pos);
+            self.shared.notify(&mut state);
+        }
+    }
+}
+
+/// A client-side rate limiter for the requests to the API, which can be
+/// configured with [`Config::rate_limiter`](crate::Config::rate_limiter).
+///
+/// Its clones share the same budget, and so do the clones of the Spotify
+/// client it's configured in. It may also be shared between different clients.
+///
+/// The requests waiting in [`acquire`](Self::acquire) are allowed in the order
+/// they arrived, and they're woken up as soon as a request in flight finishes
+/// instead of polling.
+///
+/// Sample usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use rspotify::{Config, RateLimit, RateLimiter};
+///
+/// let limiter = RateLimiter::new(
+///     RateLimit::new(100, Duration::from_secs(30)).max_concurrency(4),
+/// );
+/// let config = Config {
+///     rate_limiter: Some(limiter.clone()),
+///     ..Default::default()
+/// };
+///
+/// // Later on, after some requests have been made
+/// println!("{} requests left", limiter.remaining());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    shared: Arc<Shared>,
+}
+
+/// Proof that a request is allowed by a [`RateLimiter`]. The request is
+/// considered to be in flight until it's dropped.
+#[derive(Debug)]
+#[must_use]
+pub struct RateLimitPermit {
+    shared: Arc<Shared>,
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        self.shared.notify(&mut state);
+    }
+}
+
+impl RateLimiter {
+    /// # Panics
+    ///
+    /// If `max_requests` or `max_concurrency` are zero.
+    #[must_use]
+    pub fn new(limit: RateLimit) -> Self {
+        assert!(
+            limit.max_requests > 0,
+            "max_requests must be greater than zero"
+        );
+        assert!(
+            limit.max_concurrency != Some(0),
+            "max_concurrency must be greater than zero"
+        );
+        Self {
+            limit,
+            shared: Arc::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    /// The number of requests that may still be started within the current
+    /// window.
+    #[must_use]
+    pub fn remaining(&self) -> u32 {
+        let mut state = self.shared.lock();
+        state.prune(Utc::now(), self.limit.period);
+        let started = u32::try_from(state.started.len()).unwrap_or(u32::MAX);
+        self.limit.max_requests.saturating_sub(started)
+    }
+
+    /// The number of requests currently in flight.
+    #[must_use]
+    pub fn in_flight(&self) -> u32 {
+        self.shared.lock().in_flight
+    }
+
+    /// Allows a request if there's budget left and no other request is
+    /// waiting for a permit, or returns how long to wait before trying again
+    /// otherwise.
+    pub fn try_acquire(&self) -> Result<RateLimitPermit, Duration> {
+        let mut state = self.shared.lock();
+        if !state.queue.is_empty() {
+            return Err(BUSY_RETRY_INTERVAL);
+        }
+        self.try_allow(&mut state)
+            .map_err(|wait| wait.unwrap_or(BUSY_RETRY_INTERVAL))
+    }
+
+    /// Allows a request if there's budget left. Otherwise, returns how long to
+    /// wait until the window moves, or `None` when a request in flight has to
+    /// finish first.
+    fn try_allow(&self, state: &mut State) -> Result<RateLimitPermit, Option<Duration>> {
+        let now = Utc::now();
+        state.prune(now, self.limit.period);
+
+        if state.started.len() >= self.limit.max_requests as usize {
+            // Waiting until the oldest request leaves the window
+            let wait = state.started.front().map_or(self.limit.period, |oldest| {
+                let elapsed = (now - *oldest).to_std().unwrap_or_default();
+                self.limit.period.saturating_sub(elapsed)
+            });
+            return Err(Some(wait));
+        }
+
+        if let Some(max_concurrency) = self.limit.max_concurrency {
+            if state.in_flight >= max_concurrency {
+                return Err(None);
+            }
+        }
+
+        state.started.push_back(now);
+        state.in_flight += 1;
+        Ok(RateLimitPermit {
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    /// Joins the queue of requests waiting for a permit.
+    fn ticket(&self) -> Ticket<'_> {
+        let mut state = self.shared.lock();
+        let id = state.next_ticket;
+        state.next_ticket += 1;
+        state.queue.push_back(id);
+        Ticket {
+            shared: &self.shared,
+            id,
+        }
+    }
+
+    /// Allows the request with the given ticket if it's the first one in the
+    /// queue and there's budget left. Otherwise, returns how long to wait
+    /// before checking again, or `None` to wait until notified.
+    fn try_allow_ticket(
+        &self,
+        state: &mut State,
+        ticket: &Ticket<'_>,
+    ) -> Result<RateLimitPermit, Option<Duration>> {
+        if state.queue.front() != Some(&ticket.id) {
+            return Err(None);
+        }
+        let permit = self.try_allow(state)?;
+        // The next request in the queue may be allowed as well
+        state.queue.pop_front();
+        self.shared.notify(state);
+        Ok(permit)
+    }
+
+    /// Waits until a request is allowed. The waiting requests are allowed in
+    /// the order they arrived.
+    #[cfg(feature = "__sync")]
+    pub fn acquire(&self) -> RateLimitPermit {
+        let ticket = self.ticket();
+        let mut state = self.shared.lock();
+        loop {
+            match self.try_allow_ticket(&mut state, &ticket) {
+                Ok(permit) => return permit,
+                Err(Some(wait)) => {
+                    log::debug!("Rate limit reached, waiting {wait:?}");
+                    state = self.shared.changed.wait_timeout(state, wait).unwrap().0;
+                }
+                Err(None) => state = self.shared.changed.wait(state).unwrap(),
+            }
+        }
+    }
+
+    /// Waits until a request is allowed. The waiting requests are allowed in
+    /// the order they arrived.
+    #[cfg(all(feature = "__async", not(feature = "__sync")))]
+    pub async fn acquire(&self) -> RateLimitPermit {
+        let ticket = self.ticket();
+        loop {
+            let (wait, notified) = {
+                let mut state = self.shared.lock();
+                match self.try_allow_ticket(&mut state, &ticket) {
+                    Ok(permit) => return permit,
+                    Err(wait) => {
+                        let (sender, receiver) = oneshot::channel();
+                        state.waiters.push(sender);
+                        (wait, receiver)
+                    }
+                }
+            };
+
+            match wait {
+                Some(wait) => {
+                    log::debug!("Rate limit reached, waiting {wait:?}");
+                    let sleep = sync::sleep(wait);
+                    futures::pin_mut!(sleep);
+                    futures::future::select(notified, sleep).await;
+                }
+                None => {
+                    let _ = notified.await;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(60)));
+        assert_eq!(limiter.remaining(), 2);
+
+        let first = limiter.try_acquire().unwrap();
+        let second = limiter.clone().try_acquire().unwrap();
+        assert_eq!(limiter.remaining(), 0);
+        assert_eq!(limiter.in_flight(), 2);
+
+        // Finishing the requests doesn't free up the window
+        drop((first, second));
+        assert_eq!(limiter.in_flight(), 0);
+        let wait = limiter.try_acquire().unwrap_err();
+        assert!(wait > Duration::from_secs(59) && wait <= Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_max_concurrency() {
+        let limiter =
+            RateLimiter::new(RateLimit::new(10, Duration::from_secs(60)).max_concurrency(1));
+
+        let permit = limiter.try_acquire().unwrap();
+        assert_eq!(limiter.try_acquire().unwrap_err(), BUSY_RETRY_INTERVAL);
+        drop(permit);
+        assert!(limiter.try_acquire().is_ok());
+        assert_eq!(limiter.remaining(), 8);
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_acquire() {
+        let limiter = RateLimiter::new(RateLimit::new(1, Duration::from_millis(50)));
+        let _first = limiter.acquire().await;
+        let _second = limiter.acquire().await;
+        assert_eq!(limiter.in_flight(), 2);
+        assert_eq!(limiter.remaining(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_requests must be greater than zero")]
+    fn test_no_requests() {
+        let _ = RateLimiter::new(RateLimit::new(0, Duration::from_secs(60)));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_concurrency must be greater than zero")]
+    fn test_no_concurrency() {
+        let _ = RateLimiter::new(RateLimit::new(10, Duration::from_secs(60)).max_concurrency(0));
+    }
+
+    /// Waits until `count` requests are waiting for a permit.
+    #[maybe_async::maybe_async]
+    async fn wait_queued(limiter: &RateLimiter, count: usize) {
+        while limiter.shared.lock().queue.len() < count {
+            crate::sync::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    #[cfg(feature = "__sync")]
+    #[test]
+    fn test_fairness() {
+        let limiter =
+            RateLimiter::new(RateLimit::new(10, Duration::from_secs(60)).max_concurrency(1));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let permit = limiter.acquire();
+        let waiters = (0..3)
+            .map(|i| {
+                let limiter = limiter.clone();
+                let order = Arc::clone(&order);
+                let waiter = std::thread::spawn(move || {
+                    let _permit = limiter.acquire();
+                    order.lock().unwrap().push(i);
+                });
+                wait_queued(&limiter, i + 1);
+                waiter
+            })
+            .collect::<Vec<_>>();
+
+        // Nobody may skip the queue
+        assert!(limiter.try_acquire().is_err());
+        drop(permit);
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[cfg(all(feature = "__async", not(feature = "__sync")))]
+    #[tokio::test]
+    async fn test_fairness() {
+        let limiter =
+            RateLimiter::new(RateLimit::new(10, Duration::from_secs(60)).max_concurrency(1));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let permit = limiter.acquire().await;
+        let mut waiters = Vec::new();
+        for i in 0..3 {
+            let waiter = {
+                let limiter = limiter.clone();
+                let order = Arc::clone(&order);
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await;
+                    order.lock().unwrap().push(i);
+                })
+            };
+            wait_queued(&limiter, i + 1).await;
+            waiters.push(waiter);
+        }
+
+        // Nobody may skip the queue
+        assert!(limiter.try_acquire().is_err());
+        drop(permit);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), [0, 1, 2]);
+        assert_eq!(limiter.in_flight(), 0);
+    }
+
+    #[cfg(all(feature = "__async", not(feature = "__sync")))]
+    #[tokio::test]
+    async fn test_cancelled_acquire() {
+        let limiter =
+            RateLimiter::new(RateLimit::new(10, Duration::from_secs(60)).max_concurrency(1));
+        let permit = limiter.acquire().await;
+
+        // Giving up while waiting leaves the queue
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                let _permit = limiter.acquire().await;
+            })
+        };
+        wait_queued(&limiter, 1).await;
+        waiter.abort();
+        let _ = waiter.await;
+        assert!(limiter.shared.lock().queue.is_empty());
+
+        drop(permit);
+        assert!(limiter.try_acquire().is_ok());
+    }
+}
@@ -0,0 +1,240 @@
+//! Caching the responses of the API along with their `ETag`, so that the
+//! resources that haven't changed aren't downloaded again.
+//!
+//! Spotify includes an `ETag` header in the responses of many endpoints, like
+//! [`playlist`](crate::clients::BaseClient::playlist). When a response cache is
+//! configured in [`Config::response_cache`](crate::Config::response_cache),
+//! the client sends the last `ETag` it received in the `If-None-Match` header,
+//! and if Spotify replies with `304 Not Modified`, the cached body is returned
+//! instead.
+
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::http::Query;
+
+/// Status code returned by Spotify when the resource hasn't changed since the
+/// response with the `ETag` sent in `If-None-Match`.
+pub const NOT_MODIFIED: u16 = 304;
+
+/// A response saved in a [`ResponseCache`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CachedResponse {
+    /// The value of the `ETag` header of the response.
+    pub etag: String,
+    pub body: String,
+}
+
+/// The storage used by the client to cache the responses of the API. See the
+/// [module-level documentation](self) for more information.
+///
+/// The client never fails because of its response cache, so the
+/// implementations should just log their errors, if any.
+pub trait ResponseCache: Send + Sync + fmt::Debug {
+    /// Returns the response saved for `key`, if any.
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Saves the response for `key`, replacing the previous one.
+    fn put(&self, key: &str, response: CachedResponse);
+}
+
+/// Builds the key under which the response to a GET request is cached, made up
+/// of its URL and its sorted query parameters.
+pub(crate) fn cache_key(url: &str, payload: &Query<'_>) -> String {
+    let mut params = payload
+        .iter()
+        .map(|(key, val)| format!("{key}={val}"))
+        .collect::<Vec<_>>();
+    params.sort_unstable();
+
+    if params.is_empty() {
+        url.to_owned()
+    } else {
+        format!("{url}?{}", params.join("&"))
+    }
+}
+
+/// A [`ResponseCache`] that keeps the responses in memory, so that they're
+/// lost when the program exits. Note that it has no size limit.
+#[derive(Debug, Default)]
+pub struct MemoryResponseCache {
+    responses: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl MemoryResponseCache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of responses currently cached.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all the cached responses.
+    pub fn clear(&self) {
+        self.responses.lock().unwrap().clear();
+    }
+}
+
+impl ResponseCache for MemoryResponseCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.responses.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(key.to_owned(), response);
+    }
+}
+
+/// A [`ResponseCache`] that saves each response as a JSON file in a directory,
+/// so that it persists between runs. The directory is created if it doesn't
+/// exist.
+#[derive(Debug, Clone)]
+pub struct FileResponseCache {
+    dir: PathBuf,
+}
+
+impl FileResponseCache {
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory where the responses are saved.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Removes all the cached responses, along with the directory.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// The keys may contain any character, so their hash is used as the file
+    /// name instead.
+    fn file_path(&self, key: &str) -> PathBuf {
+        let hash = Sha256::digest(key.as_bytes());
+        let name = hash
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        self.dir.join(format!("{name}.json"))
+    }
+}
+
+impl ResponseCache for FileResponseCache {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let contents = fs::read_to_string(self.file_path(key)).ok()?;
+        let value = serde_json::from_str::<Value>(&contents).ok()?;
+
+        // The key is saved as well to be sure that it's the same response
+        if value["key"].as_str()? != key {
+            return None;
+        }
+
+        Some(CachedResponse {
+            etag: value["etag"].as_str()?.to_owned(),
+            body: value["body"].as_str()?.to_owned(),
+        })
+    }
+
+    fn put(&self, key: &str, response: CachedResponse) {
+        let contents = json!({
+            "key": key,
+            "etag": response.etag,
+            "body": response.body,
+        });
+
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|()| fs::write(self.file_path(key), contents.to_string()));
+        if let Err(err) = result {
+            log::warn!("Couldn't save response in {}: {err}", self.dir.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn response(etag: &str) -> CachedResponse {
+        CachedResponse {
+            etag: etag.to_owned(),
+            body: format!("{{\"etag\": \"{etag}\"}}"),
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let url = "https://api.spotify.com/v1/playlists/abc";
+        assert_eq!(cache_key(url, &Query::new()), url);
+
+        let mut query = Query::new();
+        query.insert("offset", "50");
+        query.insert("limit", "50");
+        query.insert("market", "ES");
+        assert_eq!(
+            cache_key(url, &query),
+            format!("{url}?limit=50&market=ES&offset=50")
+        );
+    }
+
+    #[test]
+    fn test_memory_cache() {
+        let cache = MemoryResponseCache::new();
+        assert!(cache.is_empty());
+        assert_eq!(cache.get("a"), None);
+
+        cache.put("a", response("1"));
+        cache.put("b", response("2"));
+        cache.put("a", response("3"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("a"), Some(response("3")));
+        assert_eq!(cache.get("b"), Some(response("2")));
+
+        cache.clear();
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn test_file_cache() {
+        let dir =
+            std::env::temp_dir().join(format!("rspotify-response-cache-{}", std::process::id()));
+        let cache = FileResponseCache::new(&dir);
+        assert_eq!(cache.get("a"), None);
+
+        cache.put("a", response("1"));
+        cache.put("a?b=c", response("2"));
+        assert_eq!(cache.get("a"), Some(response("1")));
+
+        // A different instance reads the same responses
+        let cache = FileResponseCache::new(&dir);
+        assert_eq!(cache.get("a?b=c"), Some(response("2")));
+
+        cache.clear().unwrap();
+        assert_eq!(cache.get("a"), None);
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+//! Retrying requests that failed with a transient error, such as Spotify's
+//! rate limiting.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use getrandom::getrandom;
+use rspotify_http::middleware::Method;
+
+/// Status code returned by Spotify when the app exceeds the rate limits.
+pub const TOO_MANY_REQUESTS: u16 = 429;
+
+/// Struct to configure how requests to the API are retried when they fail with
+/// a transient error, like `429 Too Many Requests` or `503 Service
+/// Unavailable`.
+///
+/// If the response includes a `Retry-After` header, as Spotify does when
+/// rate-limiting, the client will wait for that amount of time before retrying,
+/// as long as it doesn't exceed `max_backoff`. Otherwise, an exponential
+/// backoff is used.
+///
+/// Requests that modify data, i.e., POST, PUT and DELETE requests, are only
+/// retried after a `429 Too Many Requests` by default, since they may not be
+/// idempotent and the server may have already processed them despite other
+/// errors. See `retry_writes`.
+///
+/// See the [Rate Limits][reference] guide for more information.
+///
+/// [reference]: https://developer.spotify.com/documentation/web-api/concepts/rate-limits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of times a request will be retried. Setting it to
+    /// zero disables retries.
+    pub max_retries: u32,
+
+    /// The delay before the first retry when there's no `Retry-After` header.
+    /// It's doubled after each retry.
+    pub backoff: Duration,
+
+    /// The maximum delay before a retry. If the `Retry-After` header asks to
+    /// wait for longer, the request isn't retried, and the error is returned
+    /// instead, so that the caller can decide what to do with
+    /// `ClientError::retry_after`.
+    pub max_backoff: Duration,
+
+    /// Whether to randomize the backoff delay, so that concurrent clients
+    /// don't retry at the same time.
+    pub jitter: bool,
+
+    /// The status codes that are considered transient, and thus, retried.
+    pub retry_statuses: Vec<u16>,
+
+    /// Whether POST, PUT and DELETE requests are retried after any of the
+    /// `retry_statuses`, rather than only after `429 Too Many Requests`.
+    pub retry_writes: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+            retry_statuses: vec![TOO_MANY_REQUESTS, 502, 503, 504],
+            retry_writes: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries requests.
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Returns how long to wait before retrying a `method` request that
+    /// failed with `status`, or `None` if it shouldn't be retried. `attempt`
+    /// is the number of retries performed so far.
+    #[must_use]
+    pub fn delay(
+        &self,
+        method: Method,
+        attempt: u32,
+        status: u16,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_retries || !self.retry_statuses.contains(&status) {
+            return None;
+        }
+
+        let write = method != Method::Get;
+        if write && !self.retry_writes && status != TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        if let Some(retry_after) = retry_after {
+            return Some(retry_after).filter(|retry_after| *retry_after <= self.max_backoff);
+        }
+
+        let backoff = self
+            .backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff));
+
+        if self.jitter {
+            Some(backoff / 2 + random_fraction(backoff / 2))
+        } else {
+            Some(backoff)
+        }
+    }
+}
+
+/// Returns a random duration between zero and `max`. If the OS's random number
+/// generator isn't available, the sub-second part of the current time is used
+/// instead, which is good enough for jitter.
+fn random_fraction(max: Duration) -> Duration {
+    let mut buf = [0u8; 4];
+    let fraction = match getrandom(&mut buf) {
+        Ok(()) => f64::from(u32::from_le_bytes(buf)) / f64::from(u32::MAX),
+        Err(err) => {
+            log::warn!("Couldn't generate the jitter of the retry: {err}");
+            f64::from(Utc::now().timestamp_subsec_nanos() % 1_000_000_000) / 1e9
+        }
+    };
+    max.mul_f64(fraction)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let policy = RetryPolicy::default();
+        let retry_after = Some(Duration::from_secs(20));
+        assert_eq!(policy.delay(Method::Get, 0, 429, retry_after), retry_after);
+        assert_eq!(policy.delay(Method::Get, 2, 429, retry_after), retry_after);
+        assert_eq!(policy.delay(Method::Get, 3, 429, retry_after), None);
+
+        // Too long to wait for
+        let retry_after = Some(Duration::from_secs(120));
+        assert_eq!(policy.delay(Method::Get, 0, 429, retry_after), None);
+        let policy = RetryPolicy {
+            max_backoff: Duration::from_secs(300),
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(Method::Get, 0, 429, retry_after), retry_after);
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            jitter: false,
+            ..Default::default()
+        };
+        let delay = |attempt| policy.delay(Method::Get, attempt, 503, None);
+        assert_eq!(delay(0), Some(Duration::from_secs(1)));
+        assert_eq!(delay(1), Some(Duration::from_secs(2)));
+        assert_eq!(delay(2), Some(Duration::from_secs(4)));
+        assert_eq!(delay(9), Some(Duration::from_secs(30)));
+
+        let policy = RetryPolicy::default();
+        for attempt in 0..3 {
+            let delay = policy.delay(Method::Get, attempt, 503, None).unwrap();
+            let max = Duration::from_secs(1) * 2u32.pow(attempt);
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn test_non_transient() {
+        let policy = RetryPolicy::default();
+        let retry_after = Some(Duration::from_secs(1));
+        assert_eq!(policy.delay(Method::Get, 0, 400, None), None);
+        assert_eq!(policy.delay(Method::Get, 0, 404, retry_after), None);
+        let disabled = RetryPolicy::disabled();
+        assert_eq!(disabled.delay(Method::Get, 0, 429, None), None);
+    }
+
+    #[test]
+    fn test_writes() {
+        let policy = RetryPolicy::default();
+        let retry_after = Some(Duration::from_secs(1));
+        for method in [Method::Post, Method::Put, Method::Delete] {
+            assert_eq!(policy.delay(method, 0, 503, None), None);
+            assert_eq!(policy.delay(method, 0, 503, retry_after), None);
+            assert_eq!(policy.delay(method, 0, 429, retry_after), retry_after);
+        }
+
+        let policy = RetryPolicy {
+            retry_writes: true,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(Method::Post, 0, 503, retry_after), retry_after);
+    }
+}
@@ -1,4 +1,5 @@
 pub use std::sync::Mutex;
+pub use std::thread::sleep;
 use rustix::fs::{chmod, Mode};
 
 pub fn change_file_mode(path: String) {
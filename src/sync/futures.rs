@@ -56,3 +56,9 @@ impl<T> Mutex<T> {
         Ok(val)
     }
 }
+
+/// Waits asynchronously for the given duration. It's implemented with
+/// `futures-timer` so that it works with any runtime.
+pub async fn sleep(duration: std::time::Duration) {
+    futures_timer::Delay::new(duration).await;
+}
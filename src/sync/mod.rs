@@ -3,16 +3,20 @@
 
 #[cfg(feature = "__sync")]
 mod blocking;
-#[cfg(feature = "__async")]
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
 mod futures;
 mod cast5_insecure_usage;
 pub mod blocking;
 
 #[cfg(feature = "__sync")]
 use self::blocking as imp;
-#[cfg(feature = "__async")]
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
 use self::futures as imp;
 
 /// A type alias for either an asynchronous mutex or [`std::sync::Mutex`], depending on whether
 /// this library is compiled in asynchronous or synchronous mode.
 pub type Mutex<T> = imp::Mutex<T>;
+
+/// Either an asynchronous sleep or [`std::thread::sleep`], depending on whether this library is
+/// compiled in asynchronous or synchronous mode.
+pub(crate) use imp::sleep;
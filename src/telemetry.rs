@@ -0,0 +1,119 @@
+//! Instrumentation of the requests to the API with `tracing`, which is only
+//! enabled with the `tracing` feature. Otherwise, everything here compiles down
+//! to nothing. See the crate-level documentation for the recorded fields.
+
+#[cfg(feature = "tracing")]
+use chrono::{DateTime, Utc};
+
+use crate::http::{HttpError, HttpResponse, Query};
+
+/// The path segments after which there's always an identifier, even if it
+/// doesn't look like a Spotify ID.
+#[cfg(feature = "tracing")]
+const ID_PARENTS: &[(&str, &str)] = &[("users", "{user_id}"), ("categories", "{category_id}")];
+
+/// Replaces the identifiers in the path of an endpoint, so that its spans can
+/// be grouped. Spotify IDs are 22 base-62 characters long.
+#[cfg(feature = "tracing")]
+fn path_template(path: &str) -> String {
+    let mut parent = "";
+    path.split('/')
+        .map(|segment| {
+            let template = ID_PARENTS
+                .iter()
+                .find(|(name, _)| *name == parent)
+                .map(|(_, template)| *template);
+            parent = segment;
+
+            match template {
+                Some(template) => template,
+                None if segment.len() == 22
+                    && segment.chars().all(|c| c.is_ascii_alphanumeric()) =>
+                {
+                    "{id}"
+                }
+                None => segment,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The `request` span of a request to the API, which is closed when dropped.
+pub(crate) struct RequestSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    start: DateTime<Utc>,
+}
+
+impl RequestSpan {
+    /// Opens the span for a request to `path`, relative to the API's base URL.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(method: &'static str, path: &str, payload: Option<&Query<'_>>) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            let offset = payload.and_then(|payload| payload.get("offset"));
+            let span = tracing::info_span!(
+                "request",
+                http.request.method = method,
+                url.template = %path_template(path),
+                http.response.status_code = tracing::field::Empty,
+                http.request.resend_count = tracing::field::Empty,
+                latency_ms = tracing::field::Empty,
+                pagination.offset = offset,
+            );
+            Self {
+                span,
+                start: Utc::now(),
+            }
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        Self {}
+    }
+
+    /// Records the outcome of the request after `retries` retries.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish(&self, result: &Result<HttpResponse, HttpError>, retries: u32) {
+        #[cfg(feature = "tracing")]
+        {
+            let latency = (Utc::now() - self.start).num_milliseconds();
+            self.span.record("latency_ms", latency);
+            if retries > 0 {
+                self.span.record("http.request.resend_count", retries);
+            }
+            let status = match result {
+                Ok(response) => Some(response.status),
+                Err(err) => err.status_code(),
+            };
+            if let Some(status) = status {
+                self.span.record("http.response.status_code", status);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "tracing")]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(
+            path_template("playlists/37i9dQZF1DXcBWIGoYBM5M/tracks"),
+            "playlists/{id}/tracks"
+        );
+        assert_eq!(
+            path_template("users/spotify/playlists/37i9dQZF1DXcBWIGoYBM5M"),
+            "users/{user_id}/playlists/{id}"
+        );
+        assert_eq!(
+            path_template("browse/categories/dinner/playlists"),
+            "browse/categories/{category_id}/playlists"
+        );
+        assert_eq!(path_template("me/tracks/contains"), "me/tracks/contains");
+        assert_eq!(path_template("me/player/play"), "me/player/play");
+    }
+}
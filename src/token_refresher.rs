@@ -0,0 +1,285 @@
+//! Refreshing the token in the background before it expires, so that requests
+//! never have to wait for it.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+use maybe_async::maybe_async;
+
+use crate::{clients::BaseClient, http::HttpError, sync, ClientError};
+
+/// The margin before the token expires used by default.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// How often the refresher checks whether the client has been dropped.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The minimum time between refreshes, so that the refresher doesn't keep
+/// retrying when they fail, or when the tokens are shorter-lived than the
+/// margin.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The maximum time between retries of a failed refresh, which back off
+/// exponentially from [`MIN_REFRESH_INTERVAL`].
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+type ErrorCallback = Arc<dyn Fn(&ClientError) + Send + Sync>;
+
+/// Shared by all the clones of a client, so that a [`TokenRefresher`] can
+/// tell when they have been dropped, see [`BaseClient::get_clone_tracker`].
+///
+/// It keeps count of the clones held by the refreshers, so that the
+/// refreshers of a client don't keep each other running.
+#[derive(Clone, Debug, Default)]
+pub struct CloneTracker(Arc<AtomicUsize>);
+
+/// Refreshes the token of a client in the background, a margin before it
+/// expires, instead of on the first request after it expires.
+///
+/// It runs on a clone of the client, and stops once all the other clones have
+/// been dropped, as told by their [`CloneTracker`]. The errors of the
+/// refreshes are logged, and reported to [`Self::on_error`] if set. The
+/// refresh is then retried with an exponential backoff, since the token may
+/// still be refreshed on demand when it expires if
+/// [`Config::token_refreshing`](crate::Config::token_refreshing) is enabled.
+/// The refresher stops instead when retrying can't help, i.e., when there's
+/// no refresh token or Spotify rejected it with `invalid_grant`.
+///
+/// In async mode, [`Self::spawn`] runs it as a Tokio task, and [`Self::run`]
+/// returns the future to run it on any other runtime. In sync mode,
+/// [`Self::spawn`] runs it in a new thread.
+///
+/// Sample usage:
+///
+/// ```no_run
+/// # #[cfg(all(feature = "__async", not(feature = "__sync")))]
+/// # async fn run() {
+/// use std::time::Duration;
+/// use rspotify::{AuthCodeSpotify, TokenRefresher};
+///
+/// # let spotify = AuthCodeSpotify::default();
+/// TokenRefresher::new(&spotify)
+///     .margin(Duration::from_secs(5 * 60))
+///     .on_error(|err| eprintln!("Failed to refresh the token: {}", err))
+///     .spawn();
+/// # }
+/// ```
+pub struct TokenRefresher<C> {
+    client: C,
+    clones: Option<Weak<AtomicUsize>>,
+    margin: Duration,
+    on_error: Option<ErrorCallback>,
+}
+
+impl<C> fmt::Debug for TokenRefresher<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenRefresher")
+            .field("margin", &self.margin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C> TokenRefresher<C>
+where
+    C: BaseClient + Clone,
+{
+    /// Builds a refresher for a clone of `client`, with
+    /// [`DEFAULT_REFRESH_MARGIN`].
+    #[must_use]
+    pub fn new(client: &C) -> Self {
+        let clones = client.get_clone_tracker().map(|clones| {
+            clones.0.fetch_add(1, Ordering::SeqCst);
+            Arc::downgrade(&clones.0)
+        });
+        Self {
+            client: client.clone(),
+            clones,
+            margin: DEFAULT_REFRESH_MARGIN,
+            on_error: None,
+        }
+    }
+
+    /// How long before the token expires it's refreshed.
+    #[must_use]
+    pub fn margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Called with the error of every failed refresh, including the one that
+    /// stopped the refresher, if any.
+    #[must_use]
+    pub fn on_error(mut self, on_error: impl Fn(&ClientError) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// Whether the refreshers' clones are the last ones left, which is never
+    /// known if the client has no [`CloneTracker`].
+    fn is_last_clone(&self) -> bool {
+        match self.clones.as_ref().and_then(Weak::upgrade) {
+            // Without counting the reference obtained here
+            Some(clones) => Arc::strong_count(&clones) - 1 <= clones.load(Ordering::SeqCst),
+            None => false,
+        }
+    }
+
+    /// How long until the token should be refreshed, or `None` if there's no
+    /// token or it doesn't expire.
+    #[maybe_async]
+    async fn time_to_refresh(&self) -> Option<Duration> {
+        let token = self.client.get_token();
+        let expires_at = token.lock().await.unwrap().as_ref()?.expires_at?;
+        let margin = chrono::Duration::from_std(self.margin).ok()?;
+        let now = self.client.get_config().now();
+
+        Some((expires_at - margin - now).to_std().unwrap_or_default())
+    }
+
+    /// Keeps refreshing the token until all the other clones of the client
+    /// are dropped.
+    #[maybe_async]
+    pub async fn run(self) {
+        let mut next_refresh = Instant::now();
+        let mut failures = 0;
+        loop {
+            if self.is_last_clone() {
+                log::info!("Stopping the token refresher, the client was dropped");
+                return;
+            }
+
+            let now = Instant::now();
+            let wait = match self.time_to_refresh().await {
+                Some(wait) => wait.max(next_refresh.saturating_duration_since(now)),
+                None => CHECK_INTERVAL,
+            };
+            if wait > Duration::ZERO {
+                sync::sleep(wait.min(CHECK_INTERVAL)).await;
+                continue;
+            }
+
+            log::info!("Refreshing the token before it expires");
+            match self.client.refresh_token().await {
+                Ok(()) => {
+                    failures = 0;
+                    next_refresh = now + MIN_REFRESH_INTERVAL;
+                }
+                Err(err) => {
+                    if let Some(on_error) = &self.on_error {
+                        on_error(&err);
+                    }
+                    if !is_retryable(&err) {
+                        log::error!("Stopping the token refresher, it can't be refreshed: {err}");
+                        return;
+                    }
+
+                    failures += 1;
+                    let interval = retry_interval(failures);
+                    log::warn!("Failed to refresh the token, retrying in {interval:?}: {err}");
+                    next_refresh = now + interval;
+                }
+            }
+        }
+    }
+}
+
+/// How long to wait before retrying after `failures` consecutive failed
+/// refreshes.
+fn retry_interval(failures: u32) -> Duration {
+    2u32.checked_pow(failures.saturating_sub(1))
+        .and_then(|factor| MIN_REFRESH_INTERVAL.checked_mul(factor))
+        .map_or(MAX_RETRY_INTERVAL, |interval| {
+            interval.min(MAX_RETRY_INTERVAL)
+        })
+}
+
+/// Whether a failed refresh may succeed if retried, which isn't the case when
+/// there's no refresh token, or when Spotify rejected it with
+/// `invalid_grant`, e.g., because it was revoked.
+fn is_retryable(err: &ClientError) -> bool {
+    match err {
+        ClientError::InvalidToken => false,
+        ClientError::TokenRefresh(err) => is_retryable(err),
+        ClientError::Http(err) => match &**err {
+            HttpError::StatusCode(response) if response.status == 400 => {
+                serde_json::from_str::<serde_json::Value>(&response.body)
+                    .map_or(true, |body| body["error"] != "invalid_grant")
+            }
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+impl<C> Drop for TokenRefresher<C> {
+    fn drop(&mut self) {
+        if let Some(refreshers) = self.clones.as_ref().and_then(Weak::upgrade) {
+            refreshers.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(all(feature = "__async", not(feature = "__sync")))]
+impl<C> TokenRefresher<C>
+where
+    C: BaseClient + Clone + Send + Sync + 'static,
+{
+    /// Runs the refresher as a Tokio task.
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+}
+
+#[cfg(feature = "__sync")]
+impl<C> TokenRefresher<C>
+where
+    C: BaseClient + Clone + Send + 'static,
+{
+    /// Runs the refresher in a new thread.
+    pub fn spawn(self) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || self.run())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::ErrorResponse;
+
+    #[test]
+    fn test_retry_interval() {
+        assert_eq!(retry_interval(1), MIN_REFRESH_INTERVAL);
+        assert_eq!(retry_interval(2), MIN_REFRESH_INTERVAL * 2);
+        assert_eq!(retry_interval(4), MIN_REFRESH_INTERVAL * 8);
+        assert_eq!(retry_interval(20), MAX_RETRY_INTERVAL);
+        assert_eq!(retry_interval(u32::MAX), MAX_RETRY_INTERVAL);
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        let response = |status, body: &str| {
+            let response = ErrorResponse::new(status, Default::default(), body.to_owned());
+            ClientError::from(HttpError::StatusCode(response))
+        };
+
+        assert!(!is_retryable(&ClientError::InvalidToken));
+        assert!(!is_retryable(&response(
+            400,
+            r#"{"error": "invalid_grant", "error_description": "Refresh token revoked"}"#
+        )));
+        assert!(is_retryable(&response(
+            400,
+            r#"{"error": "invalid_request"}"#
+        )));
+        assert!(is_retryable(&response(503, "")));
+        assert!(is_retryable(&ClientError::from(HttpError::Client(
+            "unavailable".into()
+        ))));
+    }
+}
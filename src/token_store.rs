@@ -0,0 +1,462 @@
+//! Storage for the tokens of the clients, so that they can be reused between
+//! runs instead of following the whole authorization process again.
+
+use std::{any::Any, collections::HashMap, fmt, fs, io, path::PathBuf, sync::Mutex};
+#[cfg(unix)]
+use std::{os::unix::io::AsRawFd, time::Duration};
+
+use maybe_async::maybe_async;
+
+#[cfg(feature = "cache-encryption")]
+use crate::CacheKey;
+use crate::{model::ModelError, ClientResult, Token};
+
+/// The account used by the clients to load and save their token, unless
+/// [`Config::token_account`](crate::Config::token_account) is set.
+pub const DEFAULT_TOKEN_ACCOUNT: &str = "default";
+
+/// How often [`FileTokenStore::lock`] checks again whether the lock was
+/// released by another process.
+#[cfg(unix)]
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Guard returned by [`TokenStore::lock`], which releases the lock when
+/// dropped.
+pub struct TokenStoreLock {
+    _guard: Box<dyn Any + Send + Sync>,
+}
+
+impl TokenStoreLock {
+    /// Wraps the guard of the lock, which must release it when dropped.
+    #[must_use]
+    pub fn new(guard: impl Any + Send + Sync) -> Self {
+        Self {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+impl fmt::Debug for TokenStoreLock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenStoreLock").finish_non_exhaustive()
+    }
+}
+
+/// This trait represents the interface to be implemented for a token storage,
+/// which can be configured with
+/// [`Config::token_store`](crate::Config::token_store). The tokens are keyed by
+/// an account identifier, so that a single store may be shared by the clients
+/// of multiple users.
+///
+/// Implementations for other backends, like Redis or a database, may wrap
+/// their errors with [`ClientError::TokenStore`](crate::ClientError::TokenStore).
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+pub trait TokenStore: Send + Sync + fmt::Debug {
+    /// Loads the token saved for `account`, or `None` if there isn't any.
+    async fn load(&self, account: &str) -> ClientResult<Option<Token>>;
+
+    /// Saves the token for `account`, replacing the previous one.
+    async fn save(&self, account: &str, token: &Token) -> ClientResult<()>;
+
+    /// Deletes the token saved for `account`, if any.
+    async fn delete(&self, account: &str) -> ClientResult<()>;
+
+    /// Waits until no one else holds the lock for the token of `account`,
+    /// and then takes it until the returned guard is dropped.
+    ///
+    /// The clients hold it while refreshing the token, so that when the store
+    /// is shared by multiple processes, only one of them refreshes it and the
+    /// rest load the new token afterwards. By default, there's no locking and
+    /// `None` is returned.
+    async fn lock(&self, _account: &str) -> ClientResult<Option<TokenStoreLock>> {
+        Ok(None)
+    }
+}
+
+/// A [`TokenStore`] that keeps the tokens in memory, so that they're lost when
+/// the program exits.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<HashMap<String, Token>>,
+}
+
+impl MemoryTokenStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl TokenStore for MemoryTokenStore {
+    async fn load(&self, account: &str) -> ClientResult<Option<Token>> {
+        Ok(self.tokens.lock().unwrap().get(account).cloned())
+    }
+
+    async fn save(&self, account: &str, token: &Token) -> ClientResult<()> {
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(account.to_owned(), token.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, account: &str) -> ClientResult<()> {
+        self.tokens.lock().unwrap().remove(account);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum FileLocation {
+    /// One file per account inside the directory.
+    Dir(PathBuf),
+    /// The same file for every account.
+    Single(PathBuf),
+}
+
+/// A [`TokenStore`] that saves the tokens as JSON files, with the format of
+/// [`Token::write_cache`], or encrypted if configured with
+/// `FileTokenStore::with_encryption`.
+#[derive(Debug, Clone)]
+pub struct FileTokenStore {
+    location: FileLocation,
+    #[cfg(feature = "cache-encryption")]
+    keys: Vec<CacheKey>,
+    #[cfg(feature = "cache-encryption")]
+    migrate_plaintext: bool,
+}
+
+impl FileTokenStore {
+    /// Saves the token of each account into its own file inside `dir`, which
+    /// is created if it doesn't exist.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            location: FileLocation::Dir(dir.into()),
+            #[cfg(feature = "cache-encryption")]
+            keys: Vec::new(),
+            #[cfg(feature = "cache-encryption")]
+            migrate_plaintext: false,
+        }
+    }
+
+    /// Saves the token into the file at `path`, regardless of the account.
+    /// This is what's used when [`Config::token_store`](crate::Config) isn't
+    /// set, with [`Config::cache_path`](crate::Config::cache_path).
+    #[must_use]
+    pub fn single(path: impl Into<PathBuf>) -> Self {
+        Self {
+            location: FileLocation::Single(path.into()),
+            #[cfg(feature = "cache-encryption")]
+            keys: Vec::new(),
+            #[cfg(feature = "cache-encryption")]
+            migrate_plaintext: false,
+        }
+    }
+
+    /// Encrypts the tokens with the first of `keys`, which may also be read
+    /// with any of the others. Plaintext files are rejected, unless
+    /// [`Self::migrate_plaintext`] is used.
+    ///
+    /// No encryption is used if `keys` is empty.
+    #[cfg(feature = "cache-encryption")]
+    #[must_use]
+    pub fn with_encryption(mut self, keys: Vec<CacheKey>) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Reads plaintext files as well when encryption is enabled, so that
+    /// they're encrypted the next time the token is saved. See
+    /// [`Token::from_cache_for_migration`].
+    #[cfg(feature = "cache-encryption")]
+    #[must_use]
+    pub fn migrate_plaintext(mut self) -> Self {
+        self.migrate_plaintext = true;
+        self
+    }
+
+    /// The path of the file where the token of `account` is saved.
+    #[must_use]
+    pub fn path(&self, account: &str) -> PathBuf {
+        match &self.location {
+            FileLocation::Dir(dir) => dir.join(format!("{}.json", encode_account(account))),
+            FileLocation::Single(path) => path.clone(),
+        }
+    }
+
+    /// The path of the file used by [`TokenStore::lock`] for `account`, which
+    /// is next to the token file and never removed.
+    #[must_use]
+    pub fn lock_path(&self, account: &str) -> PathBuf {
+        let mut path = self.path(account).into_os_string();
+        path.push(".lock");
+        path.into()
+    }
+}
+
+fn create_parent_dir(path: &std::path::Path) -> io::Result<()> {
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+}
+
+/// Tries to take an exclusive advisory lock on `file` without blocking,
+/// returning whether it succeeded. It's released when the file is closed.
+#[cfg(unix)]
+fn try_lock_file(file: &fs::File) -> io::Result<bool> {
+    // SAFETY: the file descriptor is valid while `file` is alive.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+
+    let err = io::Error::last_os_error();
+    if err.kind() == io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+/// Escapes the characters of the account that may not be valid in a file
+/// name, so that different accounts never share the same file.
+fn encode_account(account: &str) -> String {
+    account
+        .bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => (byte as char).to_string(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn is_not_found(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::NotFound
+}
+
+#[cfg_attr(target_arch = "wasm32", maybe_async(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), maybe_async)]
+impl TokenStore for FileTokenStore {
+    async fn load(&self, account: &str) -> ClientResult<Option<Token>> {
+        #[cfg(feature = "cache-encryption")]
+        let result = if self.keys.is_empty() {
+            Token::from_cache(self.path(account))
+        } else if self.migrate_plaintext {
+            Token::from_cache_for_migration(self.path(account), &self.keys)
+        } else {
+            Token::from_encrypted_cache(self.path(account), &self.keys)
+        };
+        #[cfg(not(feature = "cache-encryption"))]
+        let result = Token::from_cache(self.path(account));
+
+        match result {
+            Ok(token) => Ok(Some(token)),
+            Err(ModelError::Io(err)) if is_not_found(&err) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save(&self, account: &str, token: &Token) -> ClientResult<()> {
+        let path = self.path(account);
+        create_parent_dir(&path)?;
+
+        #[cfg(feature = "cache-encryption")]
+        if let Some(key) = self.keys.first() {
+            token.write_encrypted_cache(path, key)?;
+            return Ok(());
+        }
+
+        token.write_cache(path)?;
+        Ok(())
+    }
+
+    async fn delete(&self, account: &str) -> ClientResult<()> {
+        match fs::remove_file(self.path(account)) {
+            Err(err) if !is_not_found(&err) => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes an advisory lock on [`Self::lock_path`], which is only supported
+    /// on Unix. Otherwise, `None` is returned.
+    ///
+    /// The lock isn't blocking, so that it can't stall an async runtime while
+    /// another client in the same process holds it.
+    #[cfg(unix)]
+    async fn lock(&self, account: &str) -> ClientResult<Option<TokenStoreLock>> {
+        let path = self.lock_path(account);
+        create_parent_dir(&path)?;
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        while !try_lock_file(&file)? {
+            log::debug!("Waiting for the token cache lock at {}", path.display());
+            crate::sync::sleep(LOCK_POLL_INTERVAL).await;
+        }
+
+        Ok(Some(TokenStoreLock::new(file)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns the directory for the files of a test, which is removed first.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rspotify-token-store-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn token(access_token: &str) -> Token {
+        Token {
+            access_token: access_token.to_owned(),
+            expires_at: None,
+            ..Default::default()
+        }
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_memory_store() {
+        let store = MemoryTokenStore::new();
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, None);
+
+        store.save("alice", &token("a")).await.unwrap();
+        store.save("bob", &token("b")).await.unwrap();
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, Some(token("a")));
+
+        store.delete("alice").await.unwrap();
+        store.delete("alice").await.unwrap();
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, None);
+        let loaded = store.load("bob").await.unwrap();
+        assert_eq!(loaded, Some(token("b")));
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_file_store() {
+        let dir = test_dir("dir");
+        let store = FileTokenStore::new(&dir);
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, None);
+
+        store.save("alice", &token("a")).await.unwrap();
+        store.save("../bob", &token("b")).await.unwrap();
+        assert_eq!(store.path("../bob"), dir.join("%2E%2E%2Fbob.json"));
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, Some(token("a")));
+        let loaded = store.load("../bob").await.unwrap();
+        assert_eq!(loaded, Some(token("b")));
+
+        store.delete("alice").await.unwrap();
+        store.delete("alice").await.unwrap();
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, None);
+
+        let store = FileTokenStore::single(dir.join("cache.json"));
+        store.save("alice", &token("a")).await.unwrap();
+        let loaded = store.load("bob").await.unwrap();
+        assert_eq!(loaded, Some(token("a")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_file_store_malformed() {
+        let dir = test_dir("malformed");
+        let store = FileTokenStore::new(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(store.path("alice"), "not json").unwrap();
+        let loaded = store.load("alice").await;
+        assert!(loaded.is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_file_store_lock() {
+        let dir = test_dir("lock");
+        let store = FileTokenStore::new(&dir);
+        let lock = store.lock("alice").await.unwrap();
+        assert!(lock.is_some());
+
+        // Any other open file description is locked out, like in another
+        // process, but not the lock of a different account
+        let file = fs::File::open(store.lock_path("alice")).unwrap();
+        assert!(!try_lock_file(&file).unwrap());
+        let other = store.lock("bob").await.unwrap();
+        assert!(other.is_some());
+
+        drop(lock);
+        assert!(try_lock_file(&file).unwrap());
+        drop(file);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "cache-encryption")]
+    #[maybe_async::test(
+        feature = "__sync",
+        async(all(feature = "__async", not(feature = "__sync")), tokio::test)
+    )]
+    async fn test_file_store_encrypted() {
+        let dir = test_dir("encrypted");
+        let old_key = CacheKey::from_passphrase("old").with_id("old");
+        let new_key = CacheKey::new([42; 32]).with_id("new");
+
+        // Plaintext caches are only read when migrating, then encrypted when
+        // saved again
+        let store = FileTokenStore::new(&dir);
+        store.save("alice", &token("a")).await.unwrap();
+        let store = store.with_encryption(vec![old_key.clone()]);
+        let loaded = store.load("alice").await;
+        assert!(loaded.is_err());
+        let store = store.migrate_plaintext();
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, Some(token("a")));
+        store.save("alice", &token("b")).await.unwrap();
+        let contents = fs::read_to_string(store.path("alice")).unwrap();
+        assert!(!contents.contains("access_token"));
+
+        // Not readable without the key
+        let loaded = FileTokenStore::new(&dir).load("alice").await;
+        assert!(loaded.is_err());
+
+        // Rotating the key
+        let store = FileTokenStore::new(&dir).with_encryption(vec![new_key.clone(), old_key]);
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, Some(token("b")));
+        store.save("alice", &token("c")).await.unwrap();
+        let store = FileTokenStore::new(&dir).with_encryption(vec![new_key]);
+        let loaded = store.load("alice").await.unwrap();
+        assert_eq!(loaded, Some(token("c")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
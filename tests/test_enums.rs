@@ -84,3 +84,35 @@ fn test_convert_country_from_str() {
     let zimbabwe = Country::Zimbabwe;
     assert_eq!(<&str>::from(zimbabwe), "ZW");
 }
+
+#[test]
+#[wasm_bindgen_test]
+fn test_scope() {
+    let scope = Scope::from("user-top-read");
+    assert_eq!(scope, Scope::UserTopRead);
+    assert_eq!(scope.to_string(), "user-top-read");
+    assert_eq!(String::from(scope), "user-top-read");
+
+    let other = "some-new-scope".parse::<Scope>().unwrap();
+    assert_eq!(other, Scope::Other("some-new-scope".to_owned()));
+    assert!(!other.is_known());
+
+    let json = serde_json::to_string(&Scope::PlaylistModifyPublic).unwrap();
+    assert_eq!(json, "\"playlist-modify-public\"");
+    assert_eq!(
+        serde_json::from_str::<Scope>(&json).unwrap(),
+        Scope::PlaylistModifyPublic
+    );
+}
+
+#[test]
+#[wasm_bindgen_test]
+fn test_scope_all() {
+    // The scopes checked by the macro are the same as the known ones
+    let known = Scope::ALL.iter().map(Scope::as_str).collect::<Vec<_>>();
+    assert_eq!(known, rspotify::macros::__private::KNOWN_SCOPES);
+    for scope in Scope::ALL {
+        assert!(scope.is_known());
+        assert_eq!(&Scope::from(scope.as_str()), scope);
+    }
+}
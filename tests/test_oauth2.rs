@@ -1,7 +1,8 @@
 use chrono::prelude::*;
 use chrono::Duration;
 use rspotify::{
-    prelude::*, scopes, AuthCodeSpotify, ClientCredsSpotify, Config, Credentials, OAuth, Token,
+    model::PendingAuthorization, prelude::*, scopes, AuthCodePkceSpotify, AuthCodeSpotify,
+    ClientCredsSpotify, ClientError, Config, Credentials, OAuth, Token,
 };
 use std::{collections::HashMap, fs, io::Read, path::PathBuf};
 use url::Url;
@@ -179,3 +180,28 @@ fn test_parse_response_code() {
     let code = spotify.parse_response_code(&url);
     assert_eq!(code, Some("AQD0yXvFEOvw".to_string()));
 }
+
+#[maybe_async::test(feature = "__sync", async(feature = "__async", tokio::test))]
+async fn test_request_token_from_pending() {
+    let mut pending = PendingAuthorization {
+        state: "fdsafdsfa".to_owned(),
+        verifier: "verifier".to_owned(),
+        redirect_uri: "http://localhost:8888/callback".to_owned(),
+        scopes: scopes!("playlist-read-private"),
+        created_at: Utc::now(),
+    };
+    let spotify = AuthCodePkceSpotify::new(Credentials::new_pkce("client-id"), OAuth::default());
+
+    // The state must be the one of the authorization
+    let result = spotify
+        .request_token_from_pending(&pending, "code", "abc")
+        .await;
+    assert!(matches!(result, Err(ClientError::StateMismatch)));
+
+    // And it must not have expired
+    pending.created_at = Utc::now() - Duration::try_minutes(15).unwrap();
+    let result = spotify
+        .request_token_from_pending(&pending, "code", "fdsafdsfa")
+        .await;
+    assert!(matches!(result, Err(ClientError::AuthorizationExpired)));
+}
@@ -7,14 +7,38 @@ use rspotify::{
 };
 
 use maybe_async::maybe_async;
+#[cfg(not(target_arch = "wasm32"))]
+use rspotify::{
+    http::cassette::{CassetteClient, Mode},
+    Credentials,
+};
 
 use rspotify_model::SearchType;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_test::*;
 
-/// Generating a new basic client for the requests.
+/// Generating a new basic client for the requests, which replays the
+/// cassette `name` in `tests/cassettes/with_credential`. When recording it,
+/// the credentials are read from the environment.
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async]
+pub async fn creds_client(name: &str) -> ClientCredsSpotify<CassetteClient> {
+    let http = util::cassette("with_credential", name);
+    let creds = match http.mode() {
+        Mode::Record => util::get_credentials(),
+        // The secret is required, but it's never sent when replaying
+        Mode::Replay => Credentials::new("fake-id", "fake-secret"),
+    };
+    let spotify = ClientCredsSpotify::new(creds).with_http_client(http);
+    spotify.request_token().await.unwrap();
+    spotify
+}
+
+/// Generating a new basic client for the requests. The cassettes can't be
+/// read in the browser, so the requests are made to the Spotify API.
+#[cfg(target_arch = "wasm32")]
 #[maybe_async]
-pub async fn creds_client() -> ClientCredsSpotify {
+pub async fn creds_client(_name: &str) -> ClientCredsSpotify {
     let creds = util::get_credentials();
     let spotify = ClientCredsSpotify::new(creds);
     spotify.request_token().await.unwrap();
@@ -28,7 +52,11 @@ pub async fn creds_client() -> ClientCredsSpotify {
 )]
 async fn test_album() {
     let birdy_uri = AlbumId::from_uri("spotify:album:0sNOF9WDwhWunNAHPD3Baj").unwrap();
-    creds_client().await.album(birdy_uri, None).await.unwrap();
+    creds_client("album")
+        .await
+        .album(birdy_uri, None)
+        .await
+        .unwrap();
 }
 
 #[maybe_async::test(
@@ -42,7 +70,11 @@ async fn test_albums() {
         AlbumId::from_uri("spotify:album:6JWc4iAiJ9FjyK0B59ABb4").unwrap(),
         AlbumId::from_uri("spotify:album:6UXCm6bOO4gFlDQZV5yL37").unwrap(),
     ];
-    creds_client().await.albums(track_uris, None).await.unwrap();
+    creds_client("albums")
+        .await
+        .albums(track_uris, None)
+        .await
+        .unwrap();
 }
 
 #[maybe_async::test(
@@ -52,7 +84,7 @@ async fn test_albums() {
 )]
 async fn test_album_tracks() {
     let birdy_uri = AlbumId::from_uri("spotify:album:6akEvsycLGftJxYudPjmqK").unwrap();
-    creds_client()
+    creds_client("album_tracks")
         .await
         .album_track_manual(birdy_uri, None, Some(2), None)
         .await
@@ -66,7 +98,11 @@ async fn test_album_tracks() {
 )]
 async fn test_artist() {
     let birdy_uri = ArtistId::from_uri("spotify:artist:2WX2uTcsvV5OnS0inACecP").unwrap();
-    creds_client().await.artist(birdy_uri).await.unwrap();
+    creds_client("artist")
+        .await
+        .artist(birdy_uri)
+        .await
+        .unwrap();
 }
 
 #[maybe_async::test(
@@ -76,7 +112,7 @@ async fn test_artist() {
 )]
 async fn test_artists_albums() {
     let birdy_uri = ArtistId::from_uri("spotify:artist:2WX2uTcsvV5OnS0inACecP").unwrap();
-    creds_client()
+    creds_client("artists_albums")
         .await
         .artist_albums_manual(
             birdy_uri,
@@ -102,7 +138,7 @@ async fn test_artists_albums_with_multiple_album_types() {
         AlbumType::Compilation,
         AlbumType::AppearsOn,
     ];
-    creds_client()
+    creds_client("artists_albums_with_multiple_album_types")
         .await
         .artist_albums_manual(
             birdy_uri,
@@ -122,7 +158,7 @@ async fn test_artists_albums_with_multiple_album_types() {
 )]
 async fn test_artists_albums_with_zero_album_type() {
     let birdy_uri = ArtistId::from_uri("spotify:artist:2WX2uTcsvV5OnS0inACecP").unwrap();
-    creds_client()
+    creds_client("artists_albums_with_zero_album_type")
         .await
         .artist_albums_manual(
             birdy_uri,
@@ -145,7 +181,11 @@ async fn test_artists() {
         ArtistId::from_uri("spotify:artist:0oSGxfWSnnOXhD2fKuz2Gy").unwrap(),
         ArtistId::from_uri("spotify:artist:3dBVyJ7JuOMt4GE9607Qin").unwrap(),
     ];
-    creds_client().await.artists(artist_uris).await.unwrap();
+    creds_client("artists")
+        .await
+        .artists(artist_uris)
+        .await
+        .unwrap();
 }
 
 #[maybe_async::test(
@@ -155,7 +195,7 @@ async fn test_artists() {
 )]
 async fn test_artist_top_tracks() {
     let birdy_uri = ArtistId::from_uri("spotify:artist:2WX2uTcsvV5OnS0inACecP").unwrap();
-    creds_client()
+    creds_client("artist_top_tracks")
         .await
         .artist_top_tracks(birdy_uri, Some(Market::Country(Country::UnitedStates)))
         .await
@@ -169,7 +209,7 @@ async fn test_artist_top_tracks() {
 )]
 async fn test_user() {
     let birdy_uri = UserId::from_id("tuggareutangranser").unwrap();
-    creds_client().await.user(birdy_uri).await.unwrap();
+    creds_client("user").await.user(birdy_uri).await.unwrap();
 }
 
 #[maybe_async::test(
@@ -179,7 +219,11 @@ async fn test_user() {
 )]
 async fn test_track() {
     let birdy_uri = TrackId::from_uri("spotify:track:6rqhFgbbKwnb9MLmUQDhG6").unwrap();
-    creds_client().await.track(birdy_uri, None).await.unwrap();
+    creds_client("track")
+        .await
+        .track(birdy_uri, None)
+        .await
+        .unwrap();
 }
 
 #[maybe_async::test(
@@ -192,7 +236,11 @@ async fn test_tracks() {
         TrackId::from_uri("spotify:track:3n3Ppam7vgaVa1iaRUc9Lp").unwrap(),
         TrackId::from_uri("spotify:track:3twNvmDtFQtAd5gMKedhLD").unwrap(),
     ];
-    creds_client().await.tracks(track_uris, None).await.unwrap();
+    creds_client("tracks")
+        .await
+        .tracks(track_uris, None)
+        .await
+        .unwrap();
 }
 
 #[maybe_async::test(
@@ -202,7 +250,7 @@ async fn test_tracks() {
 )]
 async fn test_existing_playlist() {
     let playlist_id = PlaylistId::from_id("0fwsN3jhWKTbJ1J7cR7fgu").unwrap();
-    creds_client()
+    creds_client("existing_playlist")
         .await
         .playlist(playlist_id, None, None)
         .await
@@ -216,7 +264,10 @@ async fn test_existing_playlist() {
 )]
 async fn test_fake_playlist() {
     let playlist_id = PlaylistId::from_id("fakeid").unwrap();
-    let playlist = creds_client().await.playlist(playlist_id, None, None).await;
+    let playlist = creds_client("fake_playlist")
+        .await
+        .playlist(playlist_id, None, None)
+        .await;
     assert!(playlist.is_err());
 }
 
@@ -227,7 +278,7 @@ async fn test_fake_playlist() {
 )]
 async fn test_search_album() {
     let query = "album:arrival artist:abba";
-    creds_client()
+    creds_client("search_album")
         .await
         .search(query, SearchType::Album, None, None, Some(10), Some(0))
         .await
@@ -241,7 +292,7 @@ async fn test_search_album() {
 )]
 async fn test_search_multiple_types() {
     let query = "album:arrival artist:abba";
-    creds_client()
+    creds_client("search_multiple_types")
         .await
         .search_multiple(
             query,
@@ -276,7 +327,7 @@ pub mod test_pagination {
     #[cfg(feature = "__sync")]
     #[test]
     fn test_pagination_sync() {
-        let mut client = creds_client();
+        let mut client = creds_client("pagination");
         client.config.pagination_chunks = 2;
         let album = AlbumId::from_uri(ALBUM).unwrap();
 
@@ -295,7 +346,7 @@ pub mod test_pagination {
     async fn test_pagination_async() {
         use futures_util::StreamExt;
 
-        let mut client = creds_client().await;
+        let mut client = creds_client("pagination").await;
         client.config.pagination_chunks = 2;
         let album = AlbumId::from_uri(ALBUM).unwrap();
 
@@ -3,15 +3,16 @@
 //! so that no user data is modified (or at least minimizing the changes done to
 //! the account).
 //!
-//! You can run all of them with:
+//! Each test replays its cassette in `tests/cassettes/with_oauth`, which has to
+//! be recorded first as explained in `tests/cassettes/README.md`. You can run
+//! all of them with:
 //!
-//!   cargo test --features=cli,env-file -- --ignored --test-threads=1
+//!   cargo test --features=cli,env-file --test test_with_oauth -- --ignored --test-threads=1
 //!
-//! The access token must be obtained previously, and this test file will try
-//! to authenticate with the access token from the `RSPOTIFY_ACCESS_TOKEN`
-//! environment variable or the refresh token from `RSPOTIFY_REFRESH_TOKEN`.
-//! These tokens must have been generated for all available scopes, for example
-//! with the `oauth_tokens` example:
+//! When recording, this test file will try to authenticate with the access
+//! token from the `RSPOTIFY_ACCESS_TOKEN` environment variable or the refresh
+//! token from `RSPOTIFY_REFRESH_TOKEN`. These tokens must have been generated
+//! for all available scopes, for example with the `oauth_tokens` example:
 //!
 //!   cargo run --example oauth_tokens --features=env-file,cli
 //!
@@ -28,8 +29,10 @@ use rspotify::{
     scopes, AuthCodeSpotify, ClientResult, OAuth, Token,
 };
 
-use chrono::{prelude::*, Duration};
+use chrono::prelude::*;
 use maybe_async::maybe_async;
+#[cfg(not(target_arch = "wasm32"))]
+use rspotify::http::cassette::{CassetteClient, Mode};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_test::*;
@@ -50,9 +53,39 @@ fn get_access_tokens() -> (Option<String>, Option<String>) {
     (access_token, refresh_token)
 }
 
-/// Generating a new OAuth client for the requests.
+/// The client the tests run with, whose requests go through a cassette unless
+/// running in the browser.
+#[cfg(not(target_arch = "wasm32"))]
+type TestClient = AuthCodeSpotify<CassetteClient>;
+#[cfg(target_arch = "wasm32")]
+type TestClient = AuthCodeSpotify;
+
+/// Generating a new OAuth client for the requests, which replays the cassette
+/// `name` in `tests/cassettes/with_oauth`. When recording it, the client is
+/// authenticated with the tokens in the environment.
+#[cfg(not(target_arch = "wasm32"))]
+#[maybe_async]
+pub async fn oauth_client(name: &str) -> TestClient {
+    let http = util::cassette("with_oauth", name);
+    match http.mode() {
+        Mode::Record => authenticated_client().await.with_http_client(http),
+        // The token is never sent when replaying
+        Mode::Replay => AuthCodeSpotify::from_token(Token::default()).with_http_client(http),
+    }
+}
+
+/// Generating a new OAuth client for the requests. The cassettes can't be
+/// read in the browser, so the requests are made to the Spotify API.
+#[cfg(target_arch = "wasm32")]
+#[maybe_async]
+pub async fn oauth_client(_name: &str) -> TestClient {
+    authenticated_client().await
+}
+
+/// Generating a new OAuth client authenticated with the tokens in the
+/// environment.
 #[maybe_async]
-pub async fn oauth_client() -> AuthCodeSpotify {
+async fn authenticated_client() -> AuthCodeSpotify {
     let (access_token, refresh_token) = get_access_tokens();
 
     if let Some(access_token) = access_token {
@@ -128,7 +161,7 @@ async fn fetch_all<T>(paginator: Paginator<'_, ClientResult<T>>) -> Vec<T> {
 )]
 #[ignore]
 async fn test_categories() {
-    oauth_client()
+    oauth_client("categories")
         .await
         .categories_manual(
             None,
@@ -147,7 +180,7 @@ async fn test_categories() {
 )]
 #[ignore]
 async fn test_category_playlists() {
-    oauth_client()
+    oauth_client("category_playlists")
         .await
         .category_playlists_manual(
             "pop",
@@ -166,7 +199,7 @@ async fn test_category_playlists() {
 )]
 #[ignore]
 async fn test_current_playback() {
-    oauth_client()
+    oauth_client("current_playback")
         .await
         .current_playback(None, None::<&[_]>)
         .await
@@ -180,7 +213,7 @@ async fn test_current_playback() {
 )]
 #[ignore]
 async fn test_current_playing() {
-    oauth_client()
+    oauth_client("current_playing")
         .await
         .current_playing(None, None::<&[_]>)
         .await
@@ -194,7 +227,7 @@ async fn test_current_playing() {
 )]
 #[ignore]
 async fn test_current_user_followed_artists() {
-    oauth_client()
+    oauth_client("current_user_followed_artists")
         .await
         .current_user_followed_artists(None, Some(10))
         .await
@@ -208,7 +241,7 @@ async fn test_current_user_followed_artists() {
 )]
 #[ignore]
 async fn test_current_user_playing_track() {
-    oauth_client()
+    oauth_client("current_user_playing_track")
         .await
         .current_user_playing_item()
         .await
@@ -222,8 +255,9 @@ async fn test_current_user_playing_track() {
 )]
 #[ignore]
 async fn test_current_user_recently_played() {
-    let limit = TimeLimits::After(Utc::now() - Duration::try_days(2).unwrap());
-    oauth_client()
+    // Fixed, so that the request matches the one in the cassette
+    let limit = TimeLimits::After(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+    oauth_client("current_user_recently_played")
         .await
         .current_user_recently_played(Some(10), Some(limit))
         .await
@@ -242,7 +276,7 @@ async fn test_current_user_saved_albums() {
         AlbumId::from_id("628oezqK2qfmCjC6eXNors").unwrap(),
     ];
 
-    let client = oauth_client().await;
+    let client = oauth_client("current_user_saved_albums").await;
 
     // First adding the albums
     client
@@ -278,7 +312,7 @@ async fn test_current_user_saved_albums() {
 )]
 #[ignore]
 async fn test_current_user_saved_tracks_add() {
-    let client = oauth_client().await;
+    let client = oauth_client("current_user_saved_tracks_add").await;
     let tracks_ids = [
         TrackId::from_uri("spotify:track:4iV5W9uYEdYUVa79Axb7Rh").unwrap(),
         TrackId::from_uri("spotify:track:1301WleyT98MSxVHPZCA6M").unwrap(),
@@ -316,7 +350,7 @@ async fn test_current_user_saved_tracks_add() {
 )]
 #[ignore]
 async fn test_current_user_top_artists() {
-    oauth_client()
+    oauth_client("current_user_top_artists")
         .await
         .current_user_top_artists_manual(Some(TimeRange::ShortTerm), Some(10), Some(0))
         .await
@@ -330,7 +364,7 @@ async fn test_current_user_top_artists() {
 )]
 #[ignore]
 async fn test_current_user_top_tracks() {
-    oauth_client()
+    oauth_client("current_user_top_tracks")
         .await
         .current_user_top_tracks_manual(Some(TimeRange::ShortTerm), Some(10), Some(0))
         .await
@@ -344,8 +378,9 @@ async fn test_current_user_top_tracks() {
 )]
 #[ignore]
 async fn test_featured_playlists() {
-    let now: DateTime<Utc> = Utc::now();
-    oauth_client()
+    // Fixed, so that the request matches the one in the cassette
+    let now: DateTime<Utc> = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    oauth_client("featured_playlists")
         .await
         .featured_playlists(None, None, Some(now), Some(10), Some(0))
         .await
@@ -359,7 +394,7 @@ async fn test_featured_playlists() {
 )]
 #[ignore]
 async fn test_me() {
-    oauth_client().await.me().await.unwrap();
+    oauth_client("me").await.me().await.unwrap();
 }
 
 #[maybe_async::test(
@@ -369,7 +404,7 @@ async fn test_me() {
 )]
 #[ignore]
 async fn test_new_releases() {
-    oauth_client()
+    oauth_client("new_releases")
         .await
         .new_releases_manual(Some(Market::Country(Country::Sweden)), Some(10), Some(0))
         .await
@@ -383,7 +418,7 @@ async fn test_new_releases() {
 )]
 #[ignore]
 async fn test_new_releases_with_from_token() {
-    oauth_client()
+    oauth_client("new_releases_with_from_token")
         .await
         .new_releases_manual(Some(Market::FromToken), Some(10), Some(0))
         .await
@@ -397,7 +432,7 @@ async fn test_new_releases_with_from_token() {
 )]
 #[ignore]
 async fn test_playback() {
-    let client = oauth_client().await;
+    let client = oauth_client("playback").await;
     let uris = [
         PlayableId::Track(TrackId::from_uri("spotify:track:4iV5W9uYEdYUVa79Axb7Rh").unwrap()),
         PlayableId::Track(TrackId::from_uri("spotify:track:2DzSjFQKetFhkFCuDWhioi").unwrap()),
@@ -491,7 +526,7 @@ async fn test_recommendations() {
         RecommendationsAttribute::MinPopularity(50),
     ];
 
-    oauth_client()
+    oauth_client("recommendations")
         .await
         .recommendations(
             attributes,
@@ -512,7 +547,7 @@ async fn test_recommendations() {
 )]
 #[ignore]
 async fn test_repeat() {
-    let client = oauth_client().await;
+    let client = oauth_client("repeat").await;
 
     // Saving the previous state to restore it later
     let backup = client.current_playback(None, None::<&[_]>).await.unwrap();
@@ -532,7 +567,7 @@ async fn test_repeat() {
 #[ignore]
 async fn test_search_album() {
     let query = "album:arrival artist:abba";
-    oauth_client()
+    oauth_client("search_album")
         .await
         .search(query, SearchType::Album, None, None, Some(10), Some(0))
         .await
@@ -547,7 +582,7 @@ async fn test_search_album() {
 #[ignore]
 async fn test_search_artist() {
     let query = "tania bowra";
-    oauth_client()
+    oauth_client("search_artist")
         .await
         .search(
             query,
@@ -569,7 +604,7 @@ async fn test_search_artist() {
 #[ignore]
 async fn test_search_playlist() {
     let query = "\"doom metal\"";
-    oauth_client()
+    oauth_client("search_playlist")
         .await
         .search(
             query,
@@ -591,7 +626,7 @@ async fn test_search_playlist() {
 #[ignore]
 async fn test_search_track() {
     let query = "abba";
-    oauth_client()
+    oauth_client("search_track")
         .await
         .search(
             query,
@@ -615,7 +650,7 @@ async fn test_search_track() {
 #[ignore]
 async fn test_search_show() {
     let query = "99% invisible";
-    oauth_client()
+    oauth_client("search_show")
         .await
         .search(query, SearchType::Show, None, None, None, Some(0))
         .await
@@ -629,7 +664,7 @@ async fn test_search_show() {
 )]
 #[ignore]
 async fn test_seek_track() {
-    let client = oauth_client().await;
+    let client = oauth_client("seek_track").await;
 
     // Saving the previous state to restore it later
     let backup = client.current_playback(None, None::<&[_]>).await.unwrap();
@@ -655,7 +690,7 @@ async fn test_seek_track() {
 )]
 #[ignore]
 async fn test_shuffle() {
-    let client = oauth_client().await;
+    let client = oauth_client("shuffle").await;
 
     // Saving the previous state to restore it later
     let backup = client.current_playback(None, None::<&[_]>).await.unwrap();
@@ -674,7 +709,7 @@ async fn test_shuffle() {
 )]
 #[ignore]
 async fn test_user_follow_artist() {
-    let client = oauth_client().await;
+    let client = oauth_client("user_follow_artist").await;
     let artists = [
         ArtistId::from_id("74ASZWbe4lXaubB36ztrGX").unwrap(),
         ArtistId::from_id("08td7MxkoHQkXnWAYD8d6Q").unwrap(),
@@ -694,7 +729,7 @@ async fn test_user_follow_artist() {
 )]
 #[ignore]
 async fn test_user_follow_users() {
-    let client = oauth_client().await;
+    let client = oauth_client("user_follow_users").await;
     let users = [
         UserId::from_id("exampleuser01").unwrap(),
         UserId::from_id("john").unwrap(),
@@ -714,7 +749,7 @@ async fn test_user_follow_users() {
 )]
 #[ignore]
 async fn test_user_follow_playlist() {
-    let client = oauth_client().await;
+    let client = oauth_client("user_follow_playlist").await;
     let playlist_id = PlaylistId::from_id("2v3iNvBX8Ay1Gt2uXtUKUT").unwrap();
 
     client
@@ -726,7 +761,7 @@ async fn test_user_follow_playlist() {
 }
 
 #[maybe_async]
-async fn check_playlist_create(client: &AuthCodeSpotify) -> FullPlaylist {
+async fn check_playlist_create(client: &TestClient) -> FullPlaylist {
     let user = client.me().await.unwrap();
     let name = "A New Playlist";
 
@@ -764,13 +799,13 @@ async fn check_playlist_create(client: &AuthCodeSpotify) -> FullPlaylist {
 }
 
 #[maybe_async]
-async fn check_num_tracks(client: &AuthCodeSpotify, playlist_id: PlaylistId<'_>, num: i32) {
+async fn check_num_tracks(client: &TestClient, playlist_id: PlaylistId<'_>, num: i32) {
     let fetched_tracks = fetch_all(client.playlist_items(playlist_id, None, None)).await;
     assert_eq!(fetched_tracks.len() as i32, num);
 }
 
 #[maybe_async]
-async fn check_playlist_tracks(client: &AuthCodeSpotify, playlist: &FullPlaylist) {
+async fn check_playlist_tracks(client: &TestClient, playlist: &FullPlaylist) {
     // The tracks in the playlist, some of them repeated
     let tracks = [
         PlayableId::Track(TrackId::from_uri("spotify:track:5iKndSu1XI74U2OZePzP8L").unwrap()),
@@ -864,7 +899,7 @@ async fn check_playlist_tracks(client: &AuthCodeSpotify, playlist: &FullPlaylist
 }
 
 #[maybe_async]
-async fn check_playlist_follow(client: &AuthCodeSpotify, playlist: &FullPlaylist) {
+async fn check_playlist_follow(client: &TestClient, playlist: &FullPlaylist) {
     let user_ids = [
         UserId::from_id("possan").unwrap(),
         UserId::from_id("elogain").unwrap(),
@@ -891,7 +926,7 @@ async fn check_playlist_follow(client: &AuthCodeSpotify, playlist: &FullPlaylist
 )]
 #[ignore]
 async fn test_playlist() {
-    let client = oauth_client().await;
+    let client = oauth_client("playlist").await;
 
     let playlist = check_playlist_create(&client).await;
     check_playlist_tracks(&client, &playlist).await;
@@ -905,7 +940,7 @@ async fn test_playlist() {
 )]
 #[ignore]
 async fn test_volume() {
-    let client = oauth_client().await;
+    let client = oauth_client("volume").await;
 
     // Saving the previous state to restore it later
     let backup = client.current_playback(None, None::<&[_]>).await.unwrap();
@@ -935,7 +970,7 @@ async fn test_add_queue() {
 
     let birdy_uri =
         PlayableId::Track(TrackId::from_uri("spotify:track:6rqhFgbbKwnb9MLmUQDhG6").unwrap());
-    oauth_client()
+    oauth_client("add_queue")
         .await
         .add_item_to_queue(birdy_uri, None)
         .await
@@ -954,7 +989,7 @@ async fn test_get_several_shows() {
         ShowId::from_id("5as3aKmN2k11yfDDDSrvaZ").unwrap(),
     ];
 
-    oauth_client()
+    oauth_client("get_several_shows")
         .await
         .get_several_shows(shows, None)
         .await
@@ -972,7 +1007,7 @@ async fn test_get_several_episodes() {
         EpisodeId::from_id("0lbiy3LKzIY2fnyjioC11p").unwrap(),
         EpisodeId::from_id("4zugY5eJisugQj9rj8TYuh").unwrap(),
     ];
-    oauth_client()
+    oauth_client("get_several_episodes")
         .await
         .get_several_episodes(episodes, None)
         .await
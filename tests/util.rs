@@ -1,4 +1,8 @@
+#[cfg(not(target_arch = "wasm32"))]
+use rspotify::http::{cassette::CassetteClient, HttpClient};
 use rspotify::Credentials;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{env, path::Path};
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn get_credentials() -> Credentials {
@@ -19,3 +23,20 @@ pub fn get_credentials() -> Credentials {
     let secret = dotenvy_macro::dotenv!("RSPOTIFY_CLIENT_SECRET");
     Credentials::new(&id, &secret)
 }
+
+/// The cassette `name` in `tests/cassettes/<dir>`, see
+/// `tests/cassettes/README.md`. It's recorded from the Spotify API if it
+/// doesn't exist yet or if the `RSPOTIFY_RECORD` environment variable is set,
+/// and replayed otherwise.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn cassette(dir: &str, name: &str) -> CassetteClient {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/cassettes")
+        .join(dir)
+        .join(format!("{}.json", name));
+    if env::var_os("RSPOTIFY_RECORD").is_some() {
+        CassetteClient::record(path, HttpClient::default())
+    } else {
+        CassetteClient::new(path, HttpClient::default()).unwrap()
+    }
+}